@@ -0,0 +1,734 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::hash;
+use crate::logging::{info, warning};
+use crate::pack_mcmeta;
+use crate::references;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+// the config file that turns a directory of glyph images into a font
+const GLYPHS_CONFIG: &str = "glyphs.json";
+
+// the start of the private use area, where code points are assigned from by default
+const DEFAULT_START_CODE_POINT: u32 = 0xE000;
+
+// the default `height` of a bitmap provider
+const DEFAULT_HEIGHT: i64 = 8;
+
+// the glyphs in each row of a sheet
+const DEFAULT_COLUMNS: usize = 16;
+
+//------------------------------------------------------------//
+
+pub struct Glyph {
+    // the file name without `.png`, e.g. `heart`
+    pub name: String,
+    pub code_point: u32,
+    // whether `code_points` or the glyph map of a previous run gave the code point, instead of the order of the names
+    is_pinned: bool,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    size: u64,
+}
+
+/**
+ * A font generated from `assets/<namespace>/glyphs/<font>/`, which has a `glyphs.json` config and a png per glyph.
+ */
+pub struct GlyphFont {
+    // the overlay directory it is in (e.g. `overlay/`), or an empty string for the pack root
+    layer: String,
+    pub namespace: String,
+    pub font: String,
+    height: i64,
+    ascent: i64,
+    columns: usize,
+    pub glyphs: Vec<Glyph>,
+}
+
+impl GlyphFont {
+    pub fn name(&self) -> String {
+        return format!("{}:{}", self.namespace, self.font);
+    }
+
+    fn glyph_dir(&self) -> String {
+        return format!("{}assets/{}/glyphs/{}", self.layer, self.namespace, self.font);
+    }
+
+    fn font_path(&self) -> String {
+        return format!("{}assets/{}/font/{}.json", self.layer, self.namespace, self.font);
+    }
+
+    /**
+     * The pack path of the sheet of the glyphs of a size, e.g. `assets/demo/textures/font/icons/9x9.png`.
+     */
+    fn sheet_path(
+        &self,
+        (width, height): (u32, u32),
+    ) -> String {
+        return format!("{}assets/{}/textures/font/{}/{}x{}.png", self.layer, self.namespace, self.font, width, height);
+    }
+}
+
+/**
+ * The code points of the glyphs of each font by their name, e.g. `{ "demo:icons": { "heart": 0xE000 } }`.
+ */
+pub type AssignedCodePoints = collections::HashMap<String, collections::HashMap<String, u32>>;
+
+//------------------------------------------------------------//
+
+/**
+ * Reads a code point written as a number or as hex (`E000`, `U+E000` or `0xE000`).
+ */
+fn parse_code_point(
+    value: &serde_json::Value,
+) -> Option<u32> {
+    let code_point = match value {
+        serde_json::Value::Number(number) => u32::try_from(number.as_u64()?).ok()?,
+        serde_json::Value::String(text) => {
+            let hex = text.trim_start_matches("U+").trim_start_matches("u+").trim_start_matches("0x");
+
+            u32::from_str_radix(hex, 16).ok()?
+        },
+        _ => return None,
+    };
+
+    return char::from_u32(code_point).map(|_| return code_point);
+}
+
+/**
+ * Finds the first code point from `start` on that is a character and is not used yet.
+ */
+fn find_free_code_point(
+    start: u32,
+    used_code_points: &collections::HashSet<u32>,
+) -> Option<u32> {
+    let mut code_point = start;
+
+    while code_point <= char::MAX as u32 && (used_code_points.contains(&code_point) || char::from_u32(code_point).is_none()) {
+        code_point += 1;
+    }
+
+    return char::from_u32(code_point).map(|_| return code_point);
+}
+
+/**
+ * The code points the bitmap providers of a font file already use.
+ */
+fn read_used_code_points(
+    read_pack_file: &dyn Fn(&str) -> Option<Vec<u8>>,
+    font_path: &str,
+) -> collections::HashSet<u32> {
+    let font = validate::read_pack_json(read_pack_file, font_path);
+
+    let providers = font.as_ref()
+        .and_then(|font| return font.get("providers"))
+        .and_then(|providers| return providers.as_array());
+
+    return providers.into_iter().flatten()
+        .filter_map(|provider| return provider.get("chars").and_then(|chars| return chars.as_array()))
+        .flatten()
+        .filter_map(|row| return row.as_str())
+        .flat_map(|row| return row.chars())
+        .filter(|character| return *character != '\0')
+        .map(|character| return character as u32)
+        .collect();
+}
+
+/**
+ * Reads the glyphs of a font directory and assigns their code points.
+ *
+ * Glyphs listed in `code_points` keep theirs, and so do the glyphs in `previous_code_points` (from the glyph map of
+ * the previous run). The others get the free code points from `start` on in name order, skipping the code points
+ * the font file of the pack already uses.
+ */
+fn read_glyph_font(
+    pack_paths: &[String],
+    read_pack_file: &dyn Fn(&str) -> Option<Vec<u8>>,
+    config_path: &str,
+    previous_code_points: Option<&AssignedCodePoints>,
+    validation: &mut validate::Validation,
+) -> Option<GlyphFont> {
+    let components = pack_mcmeta::strip_overlay_dir(config_path).split('/').collect::<Vec<&str>>();
+
+    let config = validate::read_pack_json(read_pack_file, config_path)?;
+
+    let mut glyph_font = GlyphFont {
+        layer: references::layer_of(config_path).to_string(),
+        namespace: components[1].to_string(),
+        font: components[3].to_string(),
+        height: DEFAULT_HEIGHT,
+        ascent: 0,
+        columns: DEFAULT_COLUMNS,
+        glyphs: Vec::new(),
+    };
+
+    match config.get("height").map(|height| return height.as_i64()) {
+        Some(Some(height)) if height > 0 => glyph_font.height = height,
+        Some(_) => validation.error(config_path, "`height` has to be a positive whole number".to_string()),
+        None => {},
+    }
+
+    match config.get("ascent").map(|ascent| return ascent.as_i64()) {
+        Some(Some(ascent)) if ascent <= glyph_font.height => glyph_font.ascent = ascent,
+        Some(Some(ascent)) => validation.error(config_path, format!("`ascent` {} cannot be larger than `height` {}", ascent, glyph_font.height)),
+        Some(None) => validation.error(config_path, "`ascent` has to be a whole number".to_string()),
+        None => validation.error(config_path, "`ascent` is missing".to_string()),
+    }
+
+    match config.get("columns").map(|columns| return columns.as_u64()) {
+        Some(Some(columns)) if columns > 0 => glyph_font.columns = columns as usize,
+        Some(_) => validation.error(config_path, "`columns` has to be a positive whole number".to_string()),
+        None => {},
+    }
+
+    let start = match config.get("start").map(parse_code_point) {
+        Some(Some(start)) => start,
+        Some(None) => {
+            validation.error(config_path, "`start` has to be a code point, e.g. `U+E000`".to_string());
+
+            DEFAULT_START_CODE_POINT
+        },
+        None => DEFAULT_START_CODE_POINT,
+    };
+
+    let glyph_dir = format!("{}/", glyph_font.glyph_dir());
+
+    // the files and directories right in the glyph directory, with whether they are a file
+    let glyph_items = pack_paths.iter().filter_map(|pack_path| return pack_path.strip_prefix(&glyph_dir)).map(
+        |item_path| {
+            return match item_path.split_once('/') {
+                Some((dir_name, _)) => (format!("{}{}", glyph_dir, dir_name), false),
+                None => (format!("{}{}", glyph_dir, item_path), true),
+            };
+        }
+    ).filter(|(glyph_path, _)| return glyph_path != config_path).collect::<collections::BTreeSet<(String, bool)>>();
+
+    for (glyph_path, is_file) in glyph_items {
+        let glyph_file = path::Path::new(&glyph_path);
+
+        if !is_file || !crate::is_png_like_file(glyph_file) {
+            validation.warning(&glyph_path, "Is not a png file, so it is not a glyph and is left out".to_string());
+
+            continue;
+        }
+
+        let Some(glyph_contents) = read_pack_file(&glyph_path) else {
+            continue;
+        };
+
+        let (width, height, pixels) = match validate::decode_png_pixels(&glyph_contents) {
+            Ok(png_pixels) => png_pixels,
+            Err(_) => continue,
+        };
+
+        // the sheets are 8-bit images
+        let pixels = pixels.iter().map(|sample| return (sample >> 8) as u8).collect::<Vec<u8>>();
+
+        glyph_font.glyphs.push(
+            Glyph {
+                name: glyph_file.file_stem().expect("Failed to get file stem").to_str().expect("Failed to convert file stem to string").to_string(),
+                code_point: 0,
+                is_pinned: false,
+                width,
+                height,
+                pixels,
+                size: glyph_contents.len() as u64,
+            }
+        );
+    }
+
+    let mut used_code_points = read_used_code_points(read_pack_file, &glyph_font.font_path());
+    for (name, code_point) in config.get("code_points").and_then(|code_points| return code_points.as_object()).into_iter().flatten() {
+        let Some(glyph) = glyph_font.glyphs.iter_mut().find(|glyph| return glyph.name == *name) else {
+            validation.warning(config_path, format!("`code_points.{}` is not a glyph, there is no {}.png", name, name));
+
+            continue;
+        };
+
+        let Some(code_point) = parse_code_point(code_point) else {
+            validation.error(config_path, format!("`code_points.{}` has to be a code point, e.g. `U+E000`", name));
+
+            continue;
+        };
+
+        if !used_code_points.insert(code_point) {
+            validation.error(config_path, format!("`code_points.{}` U+{:04X} is already used in the font", name, code_point));
+
+            continue;
+        }
+
+        glyph.code_point = code_point;
+        glyph.is_pinned = true;
+    }
+
+    // glyphs keep the code points they had, so adding a glyph does not move the ones after it
+    let font_code_points = previous_code_points.and_then(|previous_code_points| return previous_code_points.get(&glyph_font.name()));
+
+    for glyph in glyph_font.glyphs.iter_mut().filter(|glyph| return !glyph.is_pinned) {
+        let Some(code_point) = font_code_points.and_then(|font_code_points| return font_code_points.get(&glyph.name)) else {
+            continue;
+        };
+
+        if used_code_points.insert(*code_point) {
+            glyph.code_point = *code_point;
+            glyph.is_pinned = true;
+        }
+    }
+
+    let mut next_code_point = start;
+
+    for glyph in glyph_font.glyphs.iter_mut().filter(|glyph| return !glyph.is_pinned) {
+        let Some(code_point) = find_free_code_point(next_code_point, &used_code_points) else {
+            validation.error(config_path, "There are not enough code points after `start` for every glyph".to_string());
+
+            return None;
+        };
+
+        glyph.code_point = code_point;
+        used_code_points.insert(code_point);
+        next_code_point = code_point;
+    }
+
+    let glyph_sizes = glyph_font.glyphs.iter().map(|glyph| return (glyph.width, glyph.height)).collect::<collections::BTreeSet<(u32, u32)>>();
+
+    for glyph_size in glyph_sizes {
+        let sheet_path = glyph_font.sheet_path(glyph_size);
+
+        if pack_paths.contains(&sheet_path) {
+            validation.error(config_path, format!("The sheet of the {}x{} glyphs would replace {}", glyph_size.0, glyph_size.1, sheet_path));
+        }
+    }
+
+    return Some(glyph_font);
+}
+
+/**
+ * Whether a pack path is in (or is) a `glyphs` directory, which the font generator reads instead of the file itself
+ * being part of the pack.
+ */
+pub fn is_glyph_path(
+    pack_path: &str,
+) -> bool {
+    let components = pack_mcmeta::strip_overlay_dir(pack_path).split('/').collect::<Vec<&str>>();
+
+    return components.len() >= 3 && components[0] == "assets" && components[2] == "glyphs";
+}
+
+/**
+ * Reads the fonts to generate from the `glyphs/<font>/glyphs.json` files of a pack (and its overlays).
+ *
+ * `read_pack_file` reads a file of the pack by its pack path, from the pack directory or from memory.
+ */
+pub fn read_glyph_fonts(
+    pack_paths: &[String],
+    read_pack_file: &dyn Fn(&str) -> Option<Vec<u8>>,
+    previous_code_points: Option<&AssignedCodePoints>,
+    validation: &mut validate::Validation,
+) -> Vec<GlyphFont> {
+    return pack_paths.iter().filter(
+        |pack_path| {
+            let components = pack_mcmeta::strip_overlay_dir(pack_path).split('/').collect::<Vec<&str>>();
+
+            return components.len() == 5 && components[0] == "assets" && components[2] == "glyphs" && components[4] == GLYPHS_CONFIG;
+        }
+    ).filter_map(|config_path| return read_glyph_font(pack_paths, read_pack_file, config_path, previous_code_points, validation)).collect();
+}
+
+/**
+ * Returns the pack paths the font generator reads (the glyph directories) and writes (sheets and font files).
+ */
+pub fn list_generated_paths(
+    pack_dir: &path::Path,
+) -> (collections::HashSet<String>, collections::HashSet<String>) {
+    let pack_paths = hash::list_pack_paths(pack_dir);
+    let glyph_fonts = read_glyph_fonts(
+        &pack_paths,
+        &|pack_path| return fs::read(pack_dir.join(pack_path)).ok(),
+        None,
+        &mut validate::Validation::default()
+    );
+
+    let source_paths = pack_paths.iter().filter(
+        |pack_path| return glyph_fonts.iter().any(|glyph_font| return pack_path.starts_with(format!("{}/", glyph_font.glyph_dir()).as_str()))
+    ).cloned().collect();
+
+    let generated_paths = glyph_fonts.iter().flat_map(
+        |glyph_font| {
+            let mut generated_paths = glyph_font.glyphs.iter().map(
+                |glyph| return glyph_font.sheet_path((glyph.width, glyph.height))
+            ).collect::<Vec<String>>();
+
+            generated_paths.push(glyph_font.font_path());
+
+            return generated_paths;
+        }
+    ).collect();
+
+    return (source_paths, generated_paths);
+}
+
+/**
+ * Builds the name to character mapping of the generated fonts, e.g. `{ "demo:icons": { "heart": "\ue000" } }`.
+ */
+pub fn glyph_map_to_json(
+    glyph_fonts: &[GlyphFont],
+) -> serde_json::Value {
+    let mut glyph_map = serde_json::Map::new();
+
+    for glyph_font in glyph_fonts {
+        let characters = glyph_font.glyphs.iter().map(
+            |glyph| return (glyph.name.clone(), serde_json::json!(char::from_u32(glyph.code_point).expect("Failed to convert code point").to_string()))
+        ).collect::<serde_json::Map<String, serde_json::Value>>();
+
+        glyph_map.insert(glyph_font.name(), serde_json::Value::Object(characters));
+    }
+
+    return serde_json::Value::Object(glyph_map);
+}
+
+/**
+ * Checks that the glyph map file is a .json, .yml or .yaml file, which picks its format.
+ */
+pub fn parse_glyph_map_path(
+    text: &str,
+) -> Result<path::PathBuf, String> {
+    let glyph_map_path = path::PathBuf::from(text);
+
+    return match glyph_map_path.extension().and_then(|extension| return extension.to_str()) {
+        Some("json") | Some("yml") | Some("yaml") => Ok(glyph_map_path),
+        _ => Err("the glyph map has to be a .json, .yml or .yaml file".to_string()),
+    };
+}
+
+/**
+ * Reads the code points a glyph map file written by a previous run gives the glyphs, or none if there is no such file.
+ */
+pub fn read_glyph_map(
+    glyph_map_path: &path::Path,
+) -> AssignedCodePoints {
+    let Ok(glyph_map_contents) = fs::read_to_string(glyph_map_path) else {
+        return AssignedCodePoints::new();
+    };
+
+    let glyph_map = match glyph_map_path.extension().and_then(|extension| return extension.to_str()) {
+        Some("yml") | Some("yaml") => serde_yaml::from_str::<serde_json::Value>(&glyph_map_contents).ok(),
+        _ => serde_json::from_str::<serde_json::Value>(&glyph_map_contents).ok(),
+    };
+
+    let Some(serde_json::Value::Object(glyph_map)) = glyph_map else {
+        warning!(
+            "The glyph map {} cannot be read, the glyphs without pinned code points get new ones",
+            glyph_map_path.to_str().expect("Failed to convert glyph_map_path to string")
+        );
+
+        return AssignedCodePoints::new();
+    };
+
+    return glyph_map.iter().map(
+        |(font_name, characters)| {
+            let code_points = characters.as_object().into_iter().flatten().filter_map(
+                |(name, character)| {
+                    let mut characters = character.as_str()?.chars();
+
+                    return match (characters.next(), characters.next()) {
+                        (Some(character), None) => Some((name.clone(), character as u32)),
+                        _ => None,
+                    };
+                }
+            ).collect();
+
+            return (font_name.clone(), code_points);
+        }
+    ).collect();
+}
+
+/**
+ * Writes the name to character mapping of the fonts a pack generates to a .json, .yml or .yaml file.
+ *
+ * `previous_code_points` are the ones the glyph map had before the run, which the generated fonts kept.
+ */
+pub fn write_glyph_map(
+    pack_dir: &path::Path,
+    glyph_map_path: &path::Path,
+    previous_code_points: &AssignedCodePoints,
+) {
+    let glyph_fonts = read_glyph_fonts(
+        &hash::list_pack_paths(pack_dir),
+        &|pack_path| return fs::read(pack_dir.join(pack_path)).ok(),
+        Some(previous_code_points),
+        &mut validate::Validation::default()
+    );
+    let glyph_map = glyph_map_to_json(&glyph_fonts);
+
+    let glyph_map_contents = match glyph_map_path.extension().and_then(|extension| return extension.to_str()) {
+        Some("yml") | Some("yaml") => serde_yaml::to_string(&glyph_map).expect("Failed to convert glyph map to string"),
+        _ => serde_json::to_string_pretty(&glyph_map).expect("Failed to convert glyph map to string"),
+    };
+
+    fs::write(glyph_map_path, glyph_map_contents).expect("Failed to write glyph map");
+
+    info!(
+        "Wrote the characters of {} glyphs to {}",
+        glyph_fonts.iter().map(|glyph_font| return glyph_font.glyphs.len()).sum::<usize>(),
+        glyph_map_path.to_str().expect("Failed to convert glyph_map_path to string")
+    );
+}
+
+/**
+ * Checks the glyph font configs of a pack and the code points they assign.
+ */
+pub fn check_glyph_fonts(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+    validation: &mut validate::Validation,
+) {
+    read_glyph_fonts(pack_paths, &|pack_path| return fs::read(pack_dir.join(pack_path)).ok(), None, validation);
+}
+
+//------------------------------------------------------------//
+
+fn encode_sheet(
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Vec<u8> {
+    let mut sheet_contents = Vec::new();
+
+    let mut png_encoder = png::Encoder::new(&mut sheet_contents, width, height);
+    png_encoder.set_color(png::ColorType::Rgba);
+    png_encoder.set_depth(png::BitDepth::Eight);
+
+    let mut png_writer = png_encoder.write_header().expect("Failed to write png header");
+    png_writer.write_image_data(pixels).expect("Failed to write png data");
+    png_writer.finish().expect("Failed to finish png");
+
+    return sheet_contents;
+}
+
+/**
+ * Packs the glyphs of a font into a sheet per glyph size and adds a bitmap provider for each sheet to the font file,
+ * returning the files it wrote.
+ */
+fn generate_bitmap_font(
+    pack_files: &mut crate::PackFiles,
+    glyph_font: &GlyphFont,
+) -> Vec<crate::ProcessedFile> {
+    let file_operation_start = std::time::Instant::now();
+
+    let mut glyph_sizes: collections::BTreeMap<(u32, u32), Vec<&Glyph>> = collections::BTreeMap::new();
+
+    for glyph in &glyph_font.glyphs {
+        glyph_sizes.entry((glyph.width, glyph.height)).or_default().push(glyph);
+    }
+
+    let mut processed_files: Vec<crate::ProcessedFile> = Vec::new();
+    let mut providers: Vec<serde_json::Value> = Vec::new();
+
+    for ((glyph_width, glyph_height), mut glyphs) in glyph_sizes {
+        glyphs.sort_by_key(|glyph| return glyph.code_point);
+
+        let columns = glyph_font.columns.min(glyphs.len());
+        let rows = glyphs.len().div_ceil(columns);
+
+        let (sheet_width, sheet_height) = (glyph_width * columns as u32, glyph_height * rows as u32);
+        let mut sheet_pixels = vec![0; (sheet_width * sheet_height * 4) as usize];
+
+        for (index, glyph) in glyphs.iter().enumerate() {
+            let (column, row) = ((index % columns) as u32, (index / columns) as u32);
+
+            for glyph_row in 0..glyph_height {
+                let sheet_start = (((row * glyph_height + glyph_row) * sheet_width + column * glyph_width) * 4) as usize;
+                let glyph_start = (glyph_row * glyph_width * 4) as usize;
+                let row_length = (glyph_width * 4) as usize;
+
+                sheet_pixels[sheet_start..sheet_start + row_length].copy_from_slice(&glyph.pixels[glyph_start..glyph_start + row_length]);
+            }
+        }
+
+        let sheet_path = glyph_font.sheet_path((glyph_width, glyph_height));
+
+        let sheet_contents = encode_sheet(sheet_width, sheet_height, &sheet_pixels);
+        let sheet_size = sheet_contents.len() as u64;
+
+        pack_files.insert(sheet_path.clone(), sheet_contents);
+
+        // the empty cells of the last row are `\u0000`, which Minecraft skips
+        let chars = glyphs.chunks(columns).map(
+            |row_glyphs| {
+                let mut row = row_glyphs.iter().map(
+                    |glyph| return char::from_u32(glyph.code_point).expect("Failed to convert code point")
+                ).collect::<String>();
+
+                row.extend(std::iter::repeat_n('\0', columns - row_glyphs.len()));
+
+                return row;
+            }
+        ).collect::<Vec<String>>();
+
+        providers.push(
+            serde_json::json!({
+                "type": "bitmap",
+                "file": format!("{}:font/{}/{}x{}.png", glyph_font.namespace, glyph_font.font, glyph_width, glyph_height),
+                "height": glyph_font.height,
+                "ascent": glyph_font.ascent,
+                "chars": chars,
+            })
+        );
+
+        processed_files.push(
+            crate::ProcessedFile {
+                path: path::PathBuf::from(&sheet_path),
+                size_before: glyphs.iter().map(|glyph| return glyph.size).sum(),
+                size_after: sheet_size,
+                duration: file_operation_start.elapsed(),
+            }
+        );
+    }
+
+    // providers the font file already has come first, like the glyphs drawn by hand
+    let font_path = glyph_font.font_path();
+    let size_before = pack_files.get(&font_path).map_or(0, |font_contents| return font_contents.len() as u64);
+
+    let mut font = pack_files.get(&font_path).and_then(
+        |font_contents| return serde_json::from_slice::<serde_json::Value>(font_contents).ok()
+    ).unwrap_or_else(|| return serde_json::json!({ "providers": [] }));
+
+    if let Some(font_providers) = font.get_mut("providers").and_then(|font_providers| return font_providers.as_array_mut()) {
+        font_providers.extend(providers);
+    }
+
+    let font_contents = serde_json::to_vec(&font).expect("Failed to convert font to string");
+
+    processed_files.push(
+        crate::ProcessedFile {
+            path: path::PathBuf::from(&font_path),
+            size_before,
+            size_after: font_contents.len() as u64,
+            duration: file_operation_start.elapsed(),
+        }
+    );
+
+    pack_files.insert(font_path, font_contents);
+
+    let glyph_dir = format!("{}/", glyph_font.glyph_dir());
+    pack_files.retain(|pack_path, _| return !pack_path.starts_with(&glyph_dir));
+
+    return processed_files;
+}
+
+/**
+ * Generates the bitmap fonts of the glyph directories of the pack files, removing the glyph directories.
+ *
+ * Glyph directories with errors are left as they are (`optimize` stops on them before this runs).
+ * `previous_code_points` are read from the glyph map, if one is written, and the glyphs in it keep their code points.
+ */
+pub fn generate_bitmap_fonts(
+    pack_files: &mut crate::PackFiles,
+    previous_code_points: Option<&AssignedCodePoints>,
+) -> crate::StageResult {
+    const STAGE_NAME: &str = "fonts";
+
+    let operation_start = std::time::Instant::now();
+
+    let mut validation = validate::Validation::default();
+    let glyph_fonts = read_glyph_fonts(
+        &pack_files.keys().cloned().collect::<Vec<String>>(),
+        &|pack_path| return pack_files.get(pack_path).cloned(),
+        previous_code_points,
+        &mut validation
+    );
+
+    validate::log_issues(&validation);
+
+    // without a glyph map, the code points of the glyphs only depend on the order of their names
+    if previous_code_points.is_none() {
+        for glyph_font in &glyph_fonts {
+            let unpinned_names = glyph_font.glyphs.iter().filter(
+                |glyph| return !glyph.is_pinned
+            ).map(|glyph| return glyph.name.as_str()).collect::<Vec<&str>>();
+
+            if !unpinned_names.is_empty() {
+                warning!(
+                    "{}: {} have no pinned code point, so adding a glyph can move them, pin them in `code_points` or keep them with --glyph-map",
+                    glyph_font.name(),
+                    unpinned_names.join(", ")
+                );
+            }
+        }
+    }
+
+    let valid_glyph_fonts = glyph_fonts.iter().filter(
+        |glyph_font| {
+            let glyph_dir = format!("{}/", glyph_font.glyph_dir());
+
+            return !validation.issues.iter().any(
+                |issue| return issue.severity == validate::Severity::Error && issue.path.starts_with(&glyph_dir)
+            );
+        }
+    ).collect::<Vec<&GlyphFont>>();
+
+    let processed_files = valid_glyph_fonts.iter().flat_map(
+        |glyph_font| return generate_bitmap_font(pack_files, glyph_font)
+    ).collect::<Vec<crate::ProcessedFile>>();
+
+    for processed_file in &processed_files {
+        crate::log_processed_file(STAGE_NAME, processed_file);
+    }
+
+    let operation_end = operation_start.elapsed();
+
+    let stage_result = crate::StageResult {
+        name: STAGE_NAME,
+        files: processed_files,
+        duration: operation_end,
+    };
+
+    crate::log_stage_result(
+        &stage_result,
+        format!(
+            "Generated {} bitmap fonts from {} glyphs in {:.2?}",
+            valid_glyph_fonts.len(),
+            valid_glyph_fonts.iter().map(|glyph_font| return glyph_font.glyphs.len()).sum::<usize>(),
+            operation_end
+        ).as_str(),
+    );
+
+    return stage_result;
+}
+
+//------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_free_code_point_skips_used_code_points_and_surrogates() {
+        assert_eq!(find_free_code_point(0xE000, &collections::HashSet::new()), Some(0xE000));
+        assert_eq!(find_free_code_point(0xE000, &collections::HashSet::from([0xE000, 0xE001])), Some(0xE002));
+        assert_eq!(find_free_code_point(0xD7FF, &collections::HashSet::from([0xD7FF])), Some(0xE000));
+    }
+
+    #[test]
+    fn find_free_code_point_stops_at_last_code_point() {
+        assert_eq!(find_free_code_point(0x10FFFE, &collections::HashSet::new()), Some(0x10FFFE));
+        assert_eq!(find_free_code_point(0x10FFFE, &collections::HashSet::from([0x10FFFE])), Some(0x10FFFF));
+        assert_eq!(find_free_code_point(0x10FFFE, &collections::HashSet::from([0x10FFFE, 0x10FFFF])), None);
+        assert_eq!(find_free_code_point(0x10FFFF, &collections::HashSet::from([0x10FFFF])), None);
+    }
+}
@@ -0,0 +1,269 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::logging;
+use crate::logging::{error, warning};
+use crate::report;
+
+//------------------------------------------------------------//
+
+// the asset categories a budget can be set for, see `report::category_of`
+const BUDGET_CATEGORIES: [&str; 6] = ["textures", "models", "sounds", "shaders", "lang", "other"];
+
+// the number of largest files listed when a budget is exceeded
+const BUDGET_TOP_FILES: usize = 10;
+
+//------------------------------------------------------------//
+
+/**
+ * The size limits a pack has to stay under.
+ *
+ * A warning is printed once a size reaches `warning_percentage` of its limit.
+ */
+pub struct Budgets {
+    pub max_size: Option<u64>,
+    pub max_namespace_sizes: Vec<(String, u64)>,
+    pub max_category_sizes: Vec<(String, u64)>,
+    pub warning_percentage: f64,
+}
+
+impl Budgets {
+    pub fn is_empty(
+        &self,
+    ) -> bool {
+        return self.max_size.is_none() && self.max_namespace_sizes.is_empty() && self.max_category_sizes.is_empty();
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Parses a size such as `250MB`, `100MiB`, `512k` or `1048576`.
+ *
+ * `KB`, `MB` and `GB` are powers of 1000, `KiB`, `MiB` and `GiB` powers of 1024, plain numbers are bytes.
+ */
+pub fn parse_size(
+    text: &str,
+) -> Result<u64, String> {
+    let text = text.trim();
+
+    let number_end = text.find(|character: char| return !character.is_ascii_digit() && character != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(number_end);
+
+    let number = number.parse::<f64>().map_err(|_| return format!("`{}` is not a size (e.g. 250MB or 100MiB)", text))?;
+
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000 * 1000,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "kib" => 1024,
+        "mib" => 1024 * 1024,
+        "gib" => 1024 * 1024 * 1024,
+        _ => return Err(format!("`{}` is not a size unit (use B, KB, MB, GB, KiB, MiB or GiB)", unit.trim())),
+    };
+
+    return Ok((number * multiplier as f64).round() as u64);
+}
+
+/**
+ * Parses a `<NAME>=<SIZE>` budget for a namespace or category.
+ */
+pub fn parse_group_budget(
+    text: &str,
+) -> Result<(String, u64), String> {
+    let Some((name, size)) = text.split_once('=') else {
+        return Err(format!("`{}` is not a budget, use <NAME>=<SIZE> (e.g. minecraft=50MB)", text));
+    };
+
+    if name.trim().is_empty() {
+        return Err(format!("`{}` is missing a name before `=`", text));
+    }
+
+    return Ok((name.trim().to_string(), parse_size(size)?));
+}
+
+/**
+ * Exits if a category budget names a category that does not exist.
+ */
+pub fn validate_budgets(
+    budgets: &Budgets,
+) {
+    for (category, _) in &budgets.max_category_sizes {
+        if !BUDGET_CATEGORIES.contains(&category.as_str()) {
+            crate::fail_program(
+                format!("Unknown budget category `{}`, use one of: {}", category, BUDGET_CATEGORIES.join(", ")).as_str()
+            );
+        }
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Lists the largest files counting towards an exceeded budget, `files` has to be sorted largest first.
+ */
+fn format_largest_files(
+    files: &[&report::FileReport],
+    total_size: u64,
+) -> String {
+    let mut lines = vec!["Largest files (uncompressed):".to_string()];
+
+    for file in files.iter().take(BUDGET_TOP_FILES) {
+        let percentage = match total_size {
+            0 => 0.0,
+            _ => file.size_after as f64 / total_size as f64 * 100.0,
+        };
+
+        lines.push(format!("  {:>12} {:>6.1}%  {}", report::format_bytes(file.size_after), percentage, file.path));
+    }
+
+    return lines.join("\n");
+}
+
+/**
+ * Checks one budget, returning whether it was exceeded.
+ */
+fn check_budget(
+    budgets: &Budgets,
+    scope: &str,
+    size: u64,
+    max_size: u64,
+    files: &[&report::FileReport],
+    groups: &[&report::GroupReport],
+) -> bool {
+    let usage_percentage = match max_size {
+        0 => 100.0,
+        _ => size as f64 / max_size as f64 * 100.0,
+    };
+
+    let is_exceeded = size > max_size;
+    let is_near_limit = usage_percentage >= budgets.warning_percentage;
+
+    if !is_exceeded && !is_near_limit {
+        return false;
+    }
+
+    let level = match is_exceeded {
+        true => logging::Level::Error,
+        false => logging::Level::Warn,
+    };
+
+    logging::event(
+        level,
+        "budget",
+        serde_json::json!({
+            "scope": scope,
+            "size": size,
+            "max_size": max_size,
+            "exceeded": is_exceeded,
+            "largest_files": files.iter().take(BUDGET_TOP_FILES).map(|file| return serde_json::json!({
+                "path": file.path,
+                "size": file.size_after,
+            })).collect::<Vec<serde_json::Value>>(),
+        }),
+    );
+
+    if logging::is_json() {
+        return is_exceeded;
+    }
+
+    let message = format!(
+        "{} is {} of its {} budget ({:.1}%)",
+        scope,
+        report::format_bytes(size),
+        report::format_bytes(max_size),
+        usage_percentage
+    );
+
+    if !is_exceeded {
+        warning!("{}", message);
+
+        return false;
+    }
+
+    let mut breakdown = Vec::<String>::new();
+
+    if !groups.is_empty() {
+        breakdown.push("Largest namespaces (uncompressed):".to_string());
+
+        for group in groups.iter().take(BUDGET_TOP_FILES) {
+            breakdown.push(format!("  {:>12} {:>6} files  {}", report::format_bytes(group.size_after), group.files, group.name));
+        }
+    }
+
+    breakdown.push(format_largest_files(files, files.iter().map(|file| return file.size_after).sum()));
+
+    error!("{}\n{}", message, breakdown.join("\n"));
+
+    return true;
+}
+
+/**
+ * Checks the pack against all budgets, printing a warning for each size near its limit
+ * and a breakdown of the largest contributors for each exceeded one.
+ *
+ * The total size is the size of the zip file if one was built, otherwise the size of all optimized files.
+ * Returns whether any budget was exceeded.
+ */
+pub fn check_budgets(
+    budgets: &Budgets,
+    report: &report::Report,
+) -> bool {
+    let mut is_exceeded = false;
+
+    // sorted largest first, so the breakdowns can just take from the front
+    let mut files = report.files.iter().collect::<Vec<&report::FileReport>>();
+    files.sort_by(|a, b| return b.size_after.cmp(&a.size_after));
+
+    if let Some(max_size) = budgets.max_size {
+        let (scope, size) = match report.zip_size {
+            Some(zip_size) => ("The zip file", zip_size),
+            None => ("The pack", report.size_after),
+        };
+
+        is_exceeded |= check_budget(
+            budgets,
+            scope,
+            size,
+            max_size,
+            &files,
+            &report.namespaces.iter().collect::<Vec<&report::GroupReport>>(),
+        );
+    }
+
+    for (namespace, max_size) in &budgets.max_namespace_sizes {
+        let namespace_files = files.iter().filter(
+            |file| return report::namespace_of(&file.path) == *namespace
+        ).copied().collect::<Vec<&report::FileReport>>();
+
+        is_exceeded |= check_budget(
+            budgets,
+            format!("Namespace `{}`", namespace).as_str(),
+            namespace_files.iter().map(|file| return file.size_after).sum(),
+            *max_size,
+            &namespace_files,
+            &[],
+        );
+    }
+
+    for (category, max_size) in &budgets.max_category_sizes {
+        let category_files = files.iter().filter(
+            |file| return report::category_of(&file.path) == category
+        ).copied().collect::<Vec<&report::FileReport>>();
+
+        is_exceeded |= check_budget(
+            budgets,
+            format!("Category `{}`", category).as_str(),
+            category_files.iter().map(|file| return file.size_after).sum(),
+            *max_size,
+            &category_files,
+            &[],
+        );
+    }
+
+    return is_exceeded;
+}
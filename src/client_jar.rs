@@ -0,0 +1,97 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::io::Read;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::logging::debug;
+use crate::source;
+
+//------------------------------------------------------------//
+
+/**
+ * The vanilla assets of a Minecraft client jar, which is a zip file with `assets/minecraft/...` in it.
+ */
+pub struct ClientJar {
+    jar_path: path::PathBuf,
+    entries: collections::HashSet<String>,
+}
+
+impl ClientJar {
+    /**
+     * Whether the jar has a file at a pack path (e.g. `assets/minecraft/textures/block/stone.png`).
+     */
+    pub fn contains(
+        &self,
+        pack_path: &str,
+    ) -> bool {
+        return self.entries.contains(pack_path);
+    }
+
+    /**
+     * Reads the contents of the jar files at some pack paths, skipping the paths the jar does not have.
+     */
+    pub fn read_files(
+        &self,
+        pack_paths: &[&str],
+    ) -> collections::HashMap<String, Vec<u8>> {
+        let jar_file = fs::File::open(&self.jar_path).expect("Failed to open client jar");
+        let mut jar_archive = zip::ZipArchive::new(jar_file).expect("Failed to read client jar");
+
+        let mut files = collections::HashMap::new();
+
+        for pack_path in pack_paths.iter().filter(|pack_path| return self.contains(pack_path)) {
+            let mut jar_entry = jar_archive.by_name(pack_path).expect("Failed to find file in client jar");
+
+            let mut contents = Vec::with_capacity(jar_entry.size() as usize);
+            jar_entry.read_to_end(&mut contents).expect("Failed to read file from client jar");
+
+            files.insert(pack_path.to_string(), contents);
+        }
+
+        return files;
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Reads the file list of a client jar, exiting if it is not a zip file.
+ */
+pub fn open_client_jar(
+    jar_path: &path::Path,
+) -> ClientJar {
+    if !source::is_zip_file(jar_path) {
+        crate::fail_program(
+            format!(
+                "Client jar {} does not exist or is not a jar file",
+                jar_path.to_str().expect("Failed to convert jar_path to string")
+            ).as_str()
+        );
+    }
+
+    let jar_file = fs::File::open(jar_path).expect("Failed to open client jar");
+    let jar_archive = zip::ZipArchive::new(jar_file).expect("Failed to read client jar");
+
+    let entries = jar_archive.file_names().filter(
+        |entry| return entry.starts_with("assets/")
+    ).map(|entry| return entry.to_string()).collect::<collections::HashSet<String>>();
+
+    debug!(
+        "Read {} assets from the client jar {}",
+        entries.len(),
+        jar_path.to_str().expect("Failed to convert jar_path to string")
+    );
+
+    return ClientJar { jar_path: jar_path.to_path_buf(), entries };
+}
+
@@ -0,0 +1,640 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::hash;
+use crate::logging::info;
+use crate::pack_mcmeta;
+use crate::source;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+// where the models with `overrides` for vanilla items are
+const ITEM_MODELS_DIR: &str = "assets/minecraft/models/item/";
+
+//------------------------------------------------------------//
+
+/**
+ * A `custom_model_data` value of an item and the model it picks.
+ */
+pub struct CustomModelData {
+    // e.g. `minecraft:paper`
+    pub item: String,
+    pub value: f64,
+    // e.g. `demo:item/ruby`
+    pub model: String,
+    // the pack path of the file it was found in
+    pub source: String,
+    // where in that file, e.g. `overrides[0]`
+    pub json_path: String,
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Adds the `minecraft` namespace to a resource location that has none.
+ */
+fn to_full_resource_location(
+    resource_location: &str,
+) -> String {
+    return match resource_location.contains(':') {
+        true => resource_location.to_string(),
+        false => format!("minecraft:{}", resource_location),
+    };
+}
+
+fn join_json_path(
+    json_path: &str,
+    key: &str,
+) -> String {
+    return match json_path.is_empty() {
+        true => key.to_string(),
+        false => format!("{}.{}", json_path, key),
+    };
+}
+
+fn is_type(
+    object: &serde_json::Map<String, serde_json::Value>,
+    model_type: &str,
+) -> bool {
+    return object.get("type").and_then(|value| return value.as_str()).is_some_and(
+        |value| return value.trim_start_matches("minecraft:") == model_type
+    );
+}
+
+/**
+ * Reads the `overrides` of a model of a vanilla item, which pick a model by `predicate.custom_model_data`.
+ *
+ * Only overrides with no other predicate are in the registry (a bow's `pulling` variants are not),
+ * but overrides with the same predicates are reported, as only the last of them is ever used.
+ */
+fn collect_overrides(
+    pack_path: &str,
+    item: String,
+    model: &serde_json::Value,
+    custom_model_data: &mut Vec<CustomModelData>,
+    validation: &mut validate::Validation,
+) {
+    let mut seen_predicates: collections::HashMap<String, usize> = collections::HashMap::new();
+
+    for (index, item_override) in model.get("overrides").and_then(|overrides| return overrides.as_array()).into_iter().flatten().enumerate() {
+        let Some(predicate) = item_override.get("predicate").and_then(|predicate| return predicate.as_object()) else {
+            continue;
+        };
+
+        let Some(value) = predicate.get("custom_model_data").and_then(|value| return value.as_f64()) else {
+            continue;
+        };
+
+        // the keys of a json object are sorted, so the same predicates always give the same string
+        let predicate_key = serde_json::Value::Object(predicate.clone()).to_string();
+
+        if let Some(first_index) = seen_predicates.insert(predicate_key, index) {
+            validation.error(
+                pack_path,
+                format!("`overrides[{}]` has the same predicate as `overrides[{}]`, so one of them is never used", index, first_index)
+            );
+
+            continue;
+        }
+
+        let Some(model) = item_override.get("model").and_then(|model| return model.as_str()) else {
+            continue;
+        };
+
+        if predicate.len() > 1 {
+            continue;
+        }
+
+        custom_model_data.push(
+            CustomModelData {
+                item: item.clone(),
+                value,
+                model: to_full_resource_location(model),
+                source: pack_path.to_string(),
+                json_path: format!("overrides[{}]", index),
+            }
+        );
+    }
+}
+
+/**
+ * Finds the first `range_dispatch` on `custom_model_data` (with the default `index` of 0) in an item model definition.
+ */
+fn find_range_dispatch(
+    json_path: String,
+    value: &serde_json::Value,
+) -> Option<(String, &serde_json::Map<String, serde_json::Value>)> {
+    match value {
+        serde_json::Value::Object(object) => {
+            let is_custom_model_data = object.get("property").and_then(|property| return property.as_str()).is_some_and(
+                |property| return property.trim_start_matches("minecraft:") == "custom_model_data"
+            );
+            let index = object.get("index").and_then(|index| return index.as_u64()).unwrap_or(0);
+
+            if is_type(object, "range_dispatch") && is_custom_model_data && index == 0 {
+                return Some((json_path, object));
+            }
+
+            return object.iter().find_map(|(key, value)| return find_range_dispatch(join_json_path(&json_path, key), value));
+        },
+        serde_json::Value::Array(values) => {
+            return values.iter().enumerate().find_map(
+                |(index, value)| return find_range_dispatch(format!("{}[{}]", json_path, index), value)
+            );
+        },
+        _ => return None,
+    }
+}
+
+/**
+ * Finds the first `minecraft:model` in an item model, which is the model of a `composite` or `condition` too.
+ */
+fn find_model(
+    value: &serde_json::Value,
+) -> Option<&str> {
+    match value {
+        serde_json::Value::Object(object) => {
+            if is_type(object, "model") {
+                return object.get("model").and_then(|model| return model.as_str());
+            }
+
+            return object.values().find_map(find_model);
+        },
+        serde_json::Value::Array(values) => return values.iter().find_map(find_model),
+        _ => return None,
+    }
+}
+
+/**
+ * Reads the entries of the `range_dispatch` on `custom_model_data` of an item model definition (since 1.21.4).
+ *
+ * A `range_dispatch` nested in another one (e.g. for each side of a `condition`) picks the same values again,
+ * so only the first one is in the registry.
+ */
+fn collect_range_dispatch(
+    pack_path: &str,
+    item: String,
+    item_definition: &serde_json::Value,
+    custom_model_data: &mut Vec<CustomModelData>,
+    validation: &mut validate::Validation,
+) {
+    let Some((json_path, range_dispatch)) = find_range_dispatch(String::new(), item_definition) else {
+        return;
+    };
+
+    let mut seen_thresholds: Vec<(f64, usize)> = Vec::new();
+
+    for (index, entry) in range_dispatch.get("entries").and_then(|entries| return entries.as_array()).into_iter().flatten().enumerate() {
+        let entry_path = format!("{}.entries[{}]", json_path, index);
+
+        let Some(value) = entry.get("threshold").and_then(|threshold| return threshold.as_f64()) else {
+            validation.error(pack_path, format!("`{}.threshold` has to be a number", entry_path));
+
+            continue;
+        };
+
+        if let Some((_, first_index)) = seen_thresholds.iter().find(|(threshold, _)| return *threshold == value) {
+            validation.error(
+                pack_path,
+                format!("`{}` has the same threshold {} as `{}.entries[{}]`, so one of them is never used", entry_path, value, json_path, first_index)
+            );
+
+            continue;
+        }
+
+        seen_thresholds.push((value, index));
+
+        let Some(model) = entry.get("model").and_then(find_model) else {
+            continue;
+        };
+
+        custom_model_data.push(
+            CustomModelData {
+                item: item.clone(),
+                value,
+                model: to_full_resource_location(model),
+                source: pack_path.to_string(),
+                json_path: entry_path,
+            }
+        );
+    }
+}
+
+/**
+ * Reads every `custom_model_data` value of the item models of a pack (and its overlays), from the `overrides` of
+ * `assets/minecraft/models/item/` and the `range_dispatch` definitions in `items/`.
+ *
+ * The same value of an item has to pick the same model in every file, since plugins can only give an item one value:
+ * anything else is reported as an error, as are values a file has twice.
+ */
+pub fn collect_custom_model_data(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+    validation: &mut validate::Validation,
+) -> Vec<CustomModelData> {
+    let mut custom_model_data: Vec<CustomModelData> = Vec::new();
+
+    for pack_path in pack_paths {
+        let asset_path = pack_mcmeta::strip_overlay_dir(pack_path);
+        let components = asset_path.splitn(4, '/').collect::<Vec<&str>>();
+
+        let Some(resource_path) = asset_path.strip_suffix(".json") else {
+            continue;
+        };
+
+        let is_item_model = asset_path.starts_with(ITEM_MODELS_DIR);
+        let is_item_definition = components.len() == 4 && components[0] == "assets" && components[2] == "items";
+
+        if !is_item_model && !is_item_definition {
+            continue;
+        }
+
+        let Some(json) = validate::read_json(&pack_dir.join(pack_path)) else {
+            continue;
+        };
+
+        if is_item_model {
+            let item = format!("minecraft:{}", &resource_path[ITEM_MODELS_DIR.len()..]);
+
+            collect_overrides(pack_path, item, &json, &mut custom_model_data, validation);
+        } else {
+            let item = format!("{}:{}", components[1], &resource_path[format!("assets/{}/items/", components[1]).len()..]);
+
+            collect_range_dispatch(pack_path, item, &json, &mut custom_model_data, validation);
+        }
+    }
+
+    let mut first_models: collections::HashMap<(&str, u64), &CustomModelData> = collections::HashMap::new();
+
+    for entry in &custom_model_data {
+        let Some(first_entry) = first_models.get(&(entry.item.as_str(), entry.value.to_bits())) else {
+            first_models.insert((entry.item.as_str(), entry.value.to_bits()), entry);
+
+            continue;
+        };
+
+        if first_entry.model != entry.model {
+            validation.error(
+                &entry.source,
+                format!(
+                    "`{}` gives custom_model_data {} of `{}` the model `{}`, but `{}` in {} gives it `{}`",
+                    entry.json_path,
+                    entry.value,
+                    entry.item,
+                    entry.model,
+                    first_entry.json_path,
+                    first_entry.source,
+                    first_entry.model
+                )
+            );
+        }
+    }
+
+    return custom_model_data;
+}
+
+/**
+ * Reports `custom_model_data` values that pick different models, or that a file has twice.
+ */
+pub fn check_custom_model_data(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+    validation: &mut validate::Validation,
+) {
+    collect_custom_model_data(pack_dir, pack_paths, validation);
+}
+
+/**
+ * Builds the registry of a pack: each item with its `custom_model_data` values, sorted, and the models they pick.
+ */
+pub fn registry_to_json(
+    custom_model_data: &[CustomModelData],
+) -> serde_json::Value {
+    let mut items: collections::BTreeMap<&str, Vec<&CustomModelData>> = collections::BTreeMap::new();
+
+    for entry in custom_model_data {
+        items.entry(entry.item.as_str()).or_default().push(entry);
+    }
+
+    let mut registry = serde_json::Map::new();
+
+    for (item, mut entries) in items {
+        entries.sort_by(|a, b| return a.value.total_cmp(&b.value).then(a.source.cmp(&b.source)));
+
+        let mut values: Vec<serde_json::Value> = Vec::new();
+
+        for (index, entry) in entries.iter().enumerate() {
+            // the files that give a value the same model are listed together
+            if index > 0 && entries[index - 1].value == entry.value {
+                continue;
+            }
+
+            let value = match entry.value.fract() == 0.0 && entry.value >= 0.0 {
+                true => serde_json::json!(entry.value as u64),
+                false => serde_json::json!(entry.value),
+            };
+
+            values.push(
+                serde_json::json!({
+                    "custom_model_data": value,
+                    "model": entry.model,
+                    "sources": entries[index..].iter().take_while(
+                        |other_entry| return other_entry.value == entry.value
+                    ).map(|other_entry| return other_entry.source.as_str()).collect::<Vec<&str>>(),
+                })
+            );
+        }
+
+        registry.insert(item.to_string(), serde_json::Value::Array(values));
+    }
+
+    return serde_json::Value::Object(registry);
+}
+
+//------------------------------------------------------------//
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("registry")
+        .about("Exports the custom model data values of the item models of a pack, for server plugins")
+        .arg(
+            clap::Arg::new("path")
+                .help("The pack directory or zip file to read")
+                .value_name("PATH")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("output")
+                .short('o')
+                .long("output")
+                .help("Write the registry to a .json, .yml or .yaml file instead of printing it as json")
+                .value_name("FILE")
+                .value_hint(clap::ValueHint::FilePath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+        );
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let pack_path = matched_args.get_one::<path::PathBuf>("path").expect("Failed to get path");
+    let output_path = matched_args.get_one::<path::PathBuf>("output");
+
+    if !pack_path.exists() {
+        crate::fail_program("Path to read does not exist");
+    }
+
+    let is_yaml = match output_path.and_then(|output_path| return output_path.extension()).and_then(|extension| return extension.to_str()) {
+        None if output_path.is_none() => false,
+        Some("json") => false,
+        Some("yml") | Some("yaml") => true,
+        _ => crate::fail_program("Unknown registry format (use .json, .yml or .yaml)"),
+    };
+
+    let pack_source = source::open_pack(pack_path);
+
+    let pack_paths = hash::list_pack_paths(pack_source.dir());
+
+    let mut validation = validate::Validation::default();
+    let custom_model_data = collect_custom_model_data(pack_source.dir(), &pack_paths, &mut validation);
+
+    validate::log_issues(&validation);
+
+    let error_count = validation.count(validate::Severity::Error);
+    if error_count > 0 {
+        crate::fail_program(format!("The pack has {} custom model data errors, fix them before exporting the registry", error_count).as_str());
+    }
+
+    let registry = registry_to_json(&custom_model_data);
+
+    let Some(output_path) = output_path else {
+        println!("{}", serde_json::to_string_pretty(&registry).expect("Failed to convert registry to string"));
+
+        return;
+    };
+
+    let registry_contents = match is_yaml {
+        true => serde_yaml::to_string(&registry).expect("Failed to convert registry to string"),
+        false => serde_json::to_string_pretty(&registry).expect("Failed to convert registry to string"),
+    };
+
+    fs::write(output_path, registry_contents).expect("Failed to write registry");
+
+    info!(
+        "Wrote {} custom model data values of {} items to {}",
+        registry.as_object().map_or(0, |items| return items.values().filter_map(|values| return values.as_array()).map(Vec::len).sum()),
+        registry.as_object().map_or(0, |items| return items.len()),
+        output_path.to_str().expect("Failed to convert output_path to string")
+    );
+}
+
+//------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempdir::TempDir;
+
+    fn to_entries(
+        custom_model_data: &[CustomModelData],
+    ) -> Vec<(String, f64, String, String)> {
+        return custom_model_data.iter().map(
+            |entry| return (entry.item.clone(), entry.value, entry.model.clone(), entry.json_path.clone())
+        ).collect();
+    }
+
+    fn to_messages(
+        validation: &validate::Validation,
+    ) -> Vec<(String, String)> {
+        return validation.issues.iter().map(|issue| return (issue.path.clone(), issue.message.clone())).collect();
+    }
+
+    #[test]
+    fn collect_overrides_reads_custom_model_data_only_predicates() {
+        let model = serde_json::json!({
+            "parent": "item/generated",
+            "overrides": [
+                { "predicate": { "custom_model_data": 1 }, "model": "demo:item/ruby" },
+                { "predicate": { "custom_model_data": 2, "pulling": 1 }, "model": "demo:item/ruby_pulling" },
+                { "predicate": { "custom_model_data": 2.5 }, "model": "item/sapphire" },
+                { "predicate": { "pulling": 1 }, "model": "item/bow_pulling_0" },
+                { "predicate": { "custom_model_data": 1 }, "model": "demo:item/emerald" },
+                { "predicate": { "custom_model_data": 3 } },
+                { "model": "demo:item/amethyst" },
+            ],
+        });
+
+        let mut custom_model_data: Vec<CustomModelData> = Vec::new();
+        let mut validation = validate::Validation::default();
+
+        collect_overrides("assets/minecraft/models/item/paper.json", "minecraft:paper".to_string(), &model, &mut custom_model_data, &mut validation);
+
+        assert_eq!(
+            to_entries(&custom_model_data),
+            [
+                ("minecraft:paper".to_string(), 1.0, "demo:item/ruby".to_string(), "overrides[0]".to_string()),
+                ("minecraft:paper".to_string(), 2.5, "minecraft:item/sapphire".to_string(), "overrides[2]".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            to_messages(&validation),
+            [(
+                "assets/minecraft/models/item/paper.json".to_string(),
+                "`overrides[4]` has the same predicate as `overrides[0]`, so one of them is never used".to_string(),
+            )]
+        );
+    }
+
+    #[test]
+    fn collect_range_dispatch_reads_the_first_range_dispatch() {
+        let item_definition = serde_json::json!({
+            "model": {
+                "type": "minecraft:condition",
+                "property": "minecraft:using_item",
+                // the keys of a json object are sorted, so `on_false` is the first range_dispatch
+                "on_false": {
+                    "type": "range_dispatch",
+                    "property": "custom_model_data",
+                    "entries": [
+                        { "threshold": 1, "model": { "type": "model", "model": "demo:item/ruby" } },
+                        { "threshold": 2, "model": { "type": "composite", "models": [{ "type": "minecraft:model", "model": "item/sapphire" }] } },
+                        { "threshold": "3", "model": { "type": "model", "model": "demo:item/emerald" } },
+                        { "threshold": 1, "model": { "type": "model", "model": "demo:item/amethyst" } },
+                        { "threshold": 4, "model": { "type": "empty" } },
+                    ],
+                },
+                "on_true": {
+                    "type": "range_dispatch",
+                    "property": "custom_model_data",
+                    "entries": [
+                        { "threshold": 5, "model": { "type": "model", "model": "demo:item/diamond" } },
+                    ],
+                },
+            },
+        });
+
+        let mut custom_model_data: Vec<CustomModelData> = Vec::new();
+        let mut validation = validate::Validation::default();
+
+        collect_range_dispatch("assets/demo/items/wand.json", "demo:wand".to_string(), &item_definition, &mut custom_model_data, &mut validation);
+
+        assert_eq!(
+            to_entries(&custom_model_data),
+            [
+                ("demo:wand".to_string(), 1.0, "demo:item/ruby".to_string(), "model.on_false.entries[0]".to_string()),
+                ("demo:wand".to_string(), 2.0, "minecraft:item/sapphire".to_string(), "model.on_false.entries[1]".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            to_messages(&validation),
+            [
+                (
+                    "assets/demo/items/wand.json".to_string(),
+                    "`model.on_false.entries[2].threshold` has to be a number".to_string(),
+                ),
+                (
+                    "assets/demo/items/wand.json".to_string(),
+                    "`model.on_false.entries[3]` has the same threshold 1 as `model.on_false.entries[0]`, so one of them is never used".to_string(),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_range_dispatch_skips_other_indexes_and_properties() {
+        let entries = serde_json::json!([{ "threshold": 1, "model": { "type": "model", "model": "item/paper" } }]);
+
+        let item_definitions = [
+            serde_json::json!({ "model": { "type": "range_dispatch", "property": "custom_model_data", "index": 1, "entries": entries } }),
+            serde_json::json!({ "model": { "type": "range_dispatch", "property": "damage", "entries": entries } }),
+            serde_json::json!({ "model": { "type": "select", "property": "custom_model_data", "entries": entries } }),
+        ];
+
+        for item_definition in item_definitions {
+            let mut custom_model_data: Vec<CustomModelData> = Vec::new();
+            let mut validation = validate::Validation::default();
+
+            collect_range_dispatch("assets/minecraft/items/paper.json", "minecraft:paper".to_string(), &item_definition, &mut custom_model_data, &mut validation);
+
+            assert!(custom_model_data.is_empty(), "Reading {}", item_definition);
+            assert!(validation.issues.is_empty(), "Reading {}", item_definition);
+        }
+    }
+
+    #[test]
+    fn collect_custom_model_data_reports_models_that_differ_between_files() {
+        let pack_dir = TempDir::new("ms-rpo-test").expect("Failed to create temporary directory");
+
+        let pack_files = [
+            (
+                "assets/minecraft/models/item/paper.json",
+                serde_json::json!({ "overrides": [
+                    { "predicate": { "custom_model_data": 1 }, "model": "demo:item/ruby" },
+                    { "predicate": { "custom_model_data": 2 }, "model": "demo:item/sapphire" },
+                ] }),
+            ),
+            (
+                "assets/minecraft/items/paper.json",
+                serde_json::json!({ "model": { "type": "range_dispatch", "property": "custom_model_data", "entries": [
+                    { "threshold": 1, "model": { "type": "model", "model": "demo:item/ruby" } },
+                    { "threshold": 2, "model": { "type": "model", "model": "demo:item/emerald" } },
+                ] } }),
+            ),
+            // a different item can give the same value another model
+            (
+                "overlay_1/assets/minecraft/models/item/stick.json",
+                serde_json::json!({ "overrides": [
+                    { "predicate": { "custom_model_data": 2 }, "model": "demo:item/emerald" },
+                ] }),
+            ),
+        ];
+
+        for (pack_path, json) in &pack_files {
+            let file_path = pack_dir.path().join(pack_path);
+
+            fs::create_dir_all(file_path.parent().expect("Failed to get parent directory")).expect("Failed to create directory");
+            fs::write(file_path, json.to_string()).expect("Failed to write pack file");
+        }
+
+        let pack_paths = pack_files.iter().map(|(pack_path, _)| return pack_path.to_string()).collect::<Vec<String>>();
+
+        let mut validation = validate::Validation::default();
+        let custom_model_data = collect_custom_model_data(pack_dir.path(), &pack_paths, &mut validation);
+
+        assert_eq!(
+            to_entries(&custom_model_data),
+            [
+                ("minecraft:paper".to_string(), 1.0, "demo:item/ruby".to_string(), "overrides[0]".to_string()),
+                ("minecraft:paper".to_string(), 2.0, "demo:item/sapphire".to_string(), "overrides[1]".to_string()),
+                ("minecraft:paper".to_string(), 1.0, "demo:item/ruby".to_string(), "model.entries[0]".to_string()),
+                ("minecraft:paper".to_string(), 2.0, "demo:item/emerald".to_string(), "model.entries[1]".to_string()),
+                ("minecraft:stick".to_string(), 2.0, "demo:item/emerald".to_string(), "overrides[0]".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            to_messages(&validation),
+            [(
+                "assets/minecraft/items/paper.json".to_string(),
+                "`model.entries[1]` gives custom_model_data 2 of `minecraft:paper` the model `demo:item/emerald`, \
+                but `overrides[1]` in assets/minecraft/models/item/paper.json gives it `demo:item/sapphire`".to_string(),
+            )]
+        );
+    }
+}
@@ -0,0 +1,558 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use rayon::prelude::*; // parallel iterators
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::hash;
+use crate::report;
+use crate::source;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/**
+ * A value that was added, removed or changed in a json file, `path` points to it (e.g. `textures.layer0`).
+ */
+pub struct JsonChange {
+    pub path: String,
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+}
+
+/**
+ * How the pixels of a png file changed, `bounds` is the area holding every changed pixel (x, y, width, height).
+ */
+pub struct PixelChange {
+    pub size_before: (u32, u32),
+    pub size_after: (u32, u32),
+    pub changed_pixels: u64,
+    pub bounds: Option<(u32, u32, u32, u32)>,
+}
+
+/**
+ * What changed inside of a modified file, for the file types that can be compared by their meaning.
+ */
+pub enum ChangeDetails {
+    Json(Vec<JsonChange>),
+    Pixels(PixelChange),
+}
+
+/**
+ * A file that differs between two packs, with its size on each side (if it exists there).
+ */
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub size_before: Option<u64>,
+    pub size_after: Option<u64>,
+    pub details: Option<ChangeDetails>,
+}
+
+/**
+ * The files that differ between two packs, sorted by path.
+ */
+pub struct PackDiff {
+    pub changes: Vec<FileChange>,
+    pub unchanged_files: usize,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+// the number of json changes printed per file, the json output always has all of them
+const PRINTED_JSON_CHANGES: usize = 20;
+
+// longer json values are cut off when printed
+const PRINTED_JSON_VALUE_LENGTH: usize = 60;
+
+//------------------------------------------------------------//
+
+/**
+ * Formats a size change with its sign (e.g. `+1.50 KiB`).
+ */
+pub fn format_size_delta(
+    size_before: u64,
+    size_after: u64,
+) -> String {
+    return match size_after >= size_before {
+        true => format!("+{}", report::format_bytes(size_after - size_before)),
+        false => format!("-{}", report::format_bytes(size_before - size_after)),
+    };
+}
+
+fn diff_json_values(
+    value_path: &str,
+    old_value: &serde_json::Value,
+    new_value: &serde_json::Value,
+    json_changes: &mut Vec<JsonChange>,
+) {
+    let join_key = |key: &str| {
+        return match value_path.is_empty() {
+            true => key.to_string(),
+            false => format!("{}.{}", value_path, key),
+        };
+    };
+
+    match (old_value, new_value) {
+        (serde_json::Value::Object(old_object), serde_json::Value::Object(new_object)) => {
+            let keys = old_object.keys().chain(new_object.keys()).collect::<collections::BTreeSet<&String>>();
+
+            for key in keys {
+                match (old_object.get(key), new_object.get(key)) {
+                    (Some(old_value), Some(new_value)) => diff_json_values(&join_key(key), old_value, new_value, json_changes),
+                    (old_value, new_value) => json_changes.push(
+                        JsonChange {
+                            path: join_key(key),
+                            old_value: old_value.cloned(),
+                            new_value: new_value.cloned(),
+                        }
+                    ),
+                }
+            }
+        },
+        (serde_json::Value::Array(old_array), serde_json::Value::Array(new_array)) => {
+            for index in 0..old_array.len().max(new_array.len()) {
+                let index_path = format!("{}[{}]", value_path, index);
+
+                match (old_array.get(index), new_array.get(index)) {
+                    (Some(old_value), Some(new_value)) => diff_json_values(&index_path, old_value, new_value, json_changes),
+                    (old_value, new_value) => json_changes.push(
+                        JsonChange {
+                            path: index_path,
+                            old_value: old_value.cloned(),
+                            new_value: new_value.cloned(),
+                        }
+                    ),
+                }
+            }
+        },
+        (old_value, new_value) => {
+            if old_value != new_value {
+                json_changes.push(
+                    JsonChange {
+                        path: value_path.to_string(),
+                        old_value: Some(old_value.clone()),
+                        new_value: Some(new_value.clone()),
+                    }
+                );
+            }
+        },
+    }
+}
+
+fn diff_json_files(
+    old_file: &path::Path,
+    new_file: &path::Path,
+) -> Option<Vec<JsonChange>> {
+    let (old_json, new_json) = (validate::read_json(old_file)?, validate::read_json(new_file)?);
+
+    let mut json_changes: Vec<JsonChange> = Vec::new();
+    diff_json_values("", &old_json, &new_json, &mut json_changes);
+
+    return Some(json_changes);
+}
+
+fn diff_png_files(
+    old_file: &path::Path,
+    new_file: &path::Path,
+) -> Option<PixelChange> {
+    let (old_width, old_height, old_pixels) = validate::read_png_pixels(old_file).ok()?;
+    let (new_width, new_height, new_pixels) = validate::read_png_pixels(new_file).ok()?;
+
+    if (old_width, old_height) != (new_width, new_height) {
+        return Some(
+            PixelChange {
+                size_before: (old_width, old_height),
+                size_after: (new_width, new_height),
+                changed_pixels: u64::from(new_width) * u64::from(new_height),
+                bounds: Some((0, 0, new_width, new_height)),
+            }
+        );
+    }
+
+    let mut changed_pixels = 0;
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+
+    for (pixel_index, (old_pixel, new_pixel)) in old_pixels.chunks(4).zip(new_pixels.chunks(4)).enumerate() {
+        // fully transparent pixels look the same whatever their color
+        if old_pixel == new_pixel || (old_pixel[3] == 0 && new_pixel[3] == 0) {
+            continue;
+        }
+
+        changed_pixels += 1;
+
+        let x = pixel_index as u32 % old_width;
+        let y = pixel_index as u32 / old_width;
+
+        bounds = Some(
+            match bounds {
+                Some((min_x, min_y, max_x, max_y)) => (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)),
+                None => (x, y, x, y),
+            }
+        );
+    }
+
+    return Some(
+        PixelChange {
+            size_before: (old_width, old_height),
+            size_after: (new_width, new_height),
+            changed_pixels,
+            bounds: bounds.map(|(min_x, min_y, max_x, max_y)| return (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1)),
+        }
+    );
+}
+
+/**
+ * Compares a modified file by its meaning, if it is a json or png file that both sides can read.
+ */
+fn diff_file_details(
+    old_file: &path::Path,
+    new_file: &path::Path,
+) -> Option<ChangeDetails> {
+    if crate::is_json_like_file(new_file) {
+        return diff_json_files(old_file, new_file).map(ChangeDetails::Json);
+    }
+
+    if crate::is_png_like_file(new_file) {
+        return diff_png_files(old_file, new_file).map(ChangeDetails::Pixels);
+    }
+
+    return None;
+}
+
+/**
+ * Compares the files of two pack directories by their contents.
+ */
+pub fn diff_packs(
+    old_dir: &path::Path,
+    new_dir: &path::Path,
+) -> PackDiff {
+    let hash_pack_files = |dir: &path::Path| {
+        return hash::hash_dir_files(dir, Some(&|file| return !hash::is_metadata_file(file))).into_iter().map(
+            |hashed_file| return (hashed_file.path, hashed_file.hashes)
+        ).collect::<collections::BTreeMap<String, hash::Hashes>>();
+    };
+
+    let old_files = hash_pack_files(old_dir);
+    let new_files = hash_pack_files(new_dir);
+
+    let all_paths = old_files.keys().chain(new_files.keys()).cloned().collect::<collections::BTreeSet<String>>();
+
+    let mut changes: Vec<FileChange> = Vec::new();
+    let mut unchanged_files = 0;
+
+    for pack_path in all_paths {
+        let old_hashes = old_files.get(&pack_path);
+        let new_hashes = new_files.get(&pack_path);
+
+        let kind = match (old_hashes, new_hashes) {
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            (Some(old_hashes), Some(new_hashes)) if old_hashes.sha256 != new_hashes.sha256 => ChangeKind::Modified,
+            _ => {
+                unchanged_files += 1;
+
+                continue;
+            },
+        };
+
+        changes.push(
+            FileChange {
+                path: pack_path,
+                kind,
+                size_before: old_hashes.map(|hashes| return hashes.size),
+                size_after: new_hashes.map(|hashes| return hashes.size),
+                details: None,
+            }
+        );
+    }
+
+    changes.par_iter_mut().filter(|change| return change.kind == ChangeKind::Modified).for_each(
+        |change| change.details = diff_file_details(&old_dir.join(&change.path), &new_dir.join(&change.path))
+    );
+
+    return PackDiff {
+        changes,
+        unchanged_files,
+        size_before: old_files.values().map(|hashes| return hashes.size).sum(),
+        size_after: new_files.values().map(|hashes| return hashes.size).sum(),
+    };
+}
+
+fn format_json_value(
+    value: &serde_json::Value,
+) -> String {
+    let text = value.to_string();
+
+    if text.chars().count() <= PRINTED_JSON_VALUE_LENGTH {
+        return text;
+    }
+
+    return format!("{}...", text.chars().take(PRINTED_JSON_VALUE_LENGTH - 3).collect::<String>());
+}
+
+fn print_change_details(
+    details: &ChangeDetails,
+) {
+    match details {
+        ChangeDetails::Json(json_changes) => {
+            if json_changes.is_empty() {
+                println!("    same json values, only the formatting changed");
+            }
+
+            for json_change in json_changes.iter().take(PRINTED_JSON_CHANGES) {
+                let value_path = match json_change.path.is_empty() {
+                    true => "(root)",
+                    false => json_change.path.as_str(),
+                };
+
+                match (&json_change.old_value, &json_change.new_value) {
+                    (Some(old_value), Some(new_value)) => println!(
+                        "    ~ {}: {} -> {}",
+                        value_path,
+                        format_json_value(old_value),
+                        format_json_value(new_value)
+                    ),
+                    (None, Some(new_value)) => println!("    + {}: {}", value_path, format_json_value(new_value)),
+                    (Some(old_value), None) => println!("    - {}: {}", value_path, format_json_value(old_value)),
+                    (None, None) => {},
+                }
+            }
+
+            if json_changes.len() > PRINTED_JSON_CHANGES {
+                println!("    ... and {} more changes", json_changes.len() - PRINTED_JSON_CHANGES);
+            }
+        },
+        ChangeDetails::Pixels(pixel_change) => {
+            let (width_before, height_before) = pixel_change.size_before;
+            let (width_after, height_after) = pixel_change.size_after;
+
+            if pixel_change.size_before != pixel_change.size_after {
+                println!("    resized from {}x{} to {}x{}", width_before, height_before, width_after, height_after);
+
+                return;
+            }
+
+            match pixel_change.bounds {
+                Some((x, y, width, height)) => {
+                    let pixel_count = u64::from(width_after) * u64::from(height_after);
+
+                    println!(
+                        "    {} of {} pixels changed ({:.1}%), within {}x{} at {},{}",
+                        pixel_change.changed_pixels,
+                        pixel_count,
+                        pixel_change.changed_pixels as f64 / pixel_count as f64 * 100.0,
+                        width,
+                        height,
+                        x,
+                        y
+                    );
+                },
+                None => println!("    same pixels, only the encoding changed"),
+            }
+        },
+    }
+}
+
+fn count_changes(
+    pack_diff: &PackDiff,
+    kind: ChangeKind,
+) -> usize {
+    return pack_diff.changes.iter().filter(|change| return change.kind == kind).count();
+}
+
+fn print_pack_diff(
+    pack_diff: &PackDiff,
+) {
+    for change in &pack_diff.changes {
+        match change.kind {
+            ChangeKind::Added => println!(
+                "A {} ({})",
+                change.path,
+                report::format_bytes(change.size_after.unwrap_or_default())
+            ),
+            ChangeKind::Removed => println!(
+                "D {} ({})",
+                change.path,
+                report::format_bytes(change.size_before.unwrap_or_default())
+            ),
+            ChangeKind::Modified => println!(
+                "M {} ({} -> {}, {})",
+                change.path,
+                report::format_bytes(change.size_before.unwrap_or_default()),
+                report::format_bytes(change.size_after.unwrap_or_default()),
+                format_size_delta(change.size_before.unwrap_or_default(), change.size_after.unwrap_or_default())
+            ),
+        }
+
+        if let Some(details) = &change.details {
+            print_change_details(details);
+        }
+    }
+
+    println!("");
+    println!(
+        "{} added, {} removed, {} modified, {} unchanged",
+        count_changes(pack_diff, ChangeKind::Added),
+        count_changes(pack_diff, ChangeKind::Removed),
+        count_changes(pack_diff, ChangeKind::Modified),
+        pack_diff.unchanged_files
+    );
+    println!(
+        "Size: {} -> {} ({})",
+        report::format_bytes(pack_diff.size_before),
+        report::format_bytes(pack_diff.size_after),
+        format_size_delta(pack_diff.size_before, pack_diff.size_after)
+    );
+}
+
+fn change_details_to_json(
+    details: &ChangeDetails,
+) -> serde_json::Value {
+    return match details {
+        ChangeDetails::Json(json_changes) => serde_json::json!({
+            "json_changes": json_changes.iter().map(
+                |json_change| return serde_json::json!({
+                    "path": json_change.path,
+                    "old_value": json_change.old_value,
+                    "new_value": json_change.new_value,
+                })
+            ).collect::<Vec<serde_json::Value>>(),
+        }),
+        ChangeDetails::Pixels(pixel_change) => serde_json::json!({
+            "pixels": {
+                "size_before": [pixel_change.size_before.0, pixel_change.size_before.1],
+                "size_after": [pixel_change.size_after.0, pixel_change.size_after.1],
+                "changed_pixels": pixel_change.changed_pixels,
+                "bounds": pixel_change.bounds.map(
+                    |(x, y, width, height)| return serde_json::json!({ "x": x, "y": y, "width": width, "height": height })
+                ),
+            },
+        }),
+    };
+}
+
+pub fn pack_diff_to_json(
+    old_path: &path::Path,
+    new_path: &path::Path,
+    pack_diff: &PackDiff,
+) -> serde_json::Value {
+    let changes = pack_diff.changes.iter().map(
+        |change| {
+            let mut change_json = serde_json::json!({
+                "path": change.path,
+                "change": match change.kind {
+                    ChangeKind::Added => "added",
+                    ChangeKind::Removed => "removed",
+                    ChangeKind::Modified => "modified",
+                },
+                "size_before": change.size_before,
+                "size_after": change.size_after,
+                "size_delta": change.size_after.unwrap_or_default() as i64 - change.size_before.unwrap_or_default() as i64,
+            });
+
+            if let Some(serde_json::Value::Object(details)) = change.details.as_ref().map(change_details_to_json) {
+                for (key, value) in details {
+                    change_json[key] = value;
+                }
+            }
+
+            return change_json;
+        }
+    ).collect::<Vec<serde_json::Value>>();
+
+    return serde_json::json!({
+        "old": old_path.to_str().expect("Failed to convert old_path to string"),
+        "new": new_path.to_str().expect("Failed to convert new_path to string"),
+        "summary": {
+            "added": count_changes(pack_diff, ChangeKind::Added),
+            "removed": count_changes(pack_diff, ChangeKind::Removed),
+            "modified": count_changes(pack_diff, ChangeKind::Modified),
+            "unchanged": pack_diff.unchanged_files,
+            "size_before": pack_diff.size_before,
+            "size_after": pack_diff.size_after,
+            "size_delta": pack_diff.size_after as i64 - pack_diff.size_before as i64,
+        },
+        "changes": changes,
+    });
+}
+
+//------------------------------------------------------------//
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("diff")
+        .about("Compares two pack directories or zip files")
+        .arg(
+            clap::Arg::new("old_path")
+                .help("The previous pack directory or zip file")
+                .value_name("OLD")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("new_path")
+                .help("The new pack directory or zip file")
+                .value_name("NEW")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("json")
+                .long("json")
+                .help("Print the differences as json")
+                .action(clap::ArgAction::SetTrue)
+        );
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let old_path = matched_args.get_one::<path::PathBuf>("old_path").expect("Failed to get old_path");
+    let new_path = matched_args.get_one::<path::PathBuf>("new_path").expect("Failed to get new_path");
+
+    for pack_path in [old_path, new_path] {
+        if !pack_path.exists() {
+            crate::fail_program(
+                format!("{} does not exist", pack_path.to_str().expect("Failed to convert pack_path to string")).as_str()
+            );
+        }
+    }
+
+    let old_source = source::open_pack(old_path);
+    let new_source = source::open_pack(new_path);
+
+    let pack_diff = diff_packs(old_source.dir(), new_source.dir());
+
+    if matched_args.get_flag("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&pack_diff_to_json(old_path, new_path, &pack_diff)).expect("Failed to convert diff to string")
+        );
+
+        return;
+    }
+
+    print_pack_diff(&pack_diff);
+}
@@ -0,0 +1,478 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::client_jar;
+use crate::pack_mcmeta;
+use crate::references;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+// the font of chat, signs, books, ...
+const DEFAULT_FONT: &str = "minecraft:default";
+
+// the `height` of a bitmap provider without one
+const DEFAULT_BITMAP_HEIGHT: i64 = 8;
+
+// the provider types Minecraft knows
+const PROVIDER_TYPES: [&str; 5] = ["bitmap", "space", "reference", "ttf", "unihex"];
+
+// the code points listed in a message before the rest are only counted
+const PRINTED_CODE_POINTS: usize = 5;
+
+//------------------------------------------------------------//
+
+/**
+ * Returns the id of a font file, e.g. `demo:icons` for `assets/demo/font/icons.json`.
+ */
+fn font_id_of(
+    pack_path: &str,
+) -> Option<String> {
+    let (namespace, font_path) = pack_mcmeta::strip_overlay_dir(pack_path).strip_prefix("assets/")?.split_once("/font/")?;
+
+    if namespace.contains('/') {
+        return None;
+    }
+
+    return Some(format!("{}:{}", namespace, font_path.strip_suffix(".json")?));
+}
+
+/**
+ * Adds the `minecraft` namespace to a font id that has none.
+ */
+fn to_full_font_id(
+    font_id: &str,
+) -> String {
+    return match font_id.contains(':') {
+        true => font_id.to_string(),
+        false => format!("minecraft:{}", font_id),
+    };
+}
+
+/**
+ * Returns the file of an asset for a file in a layer of the pack, which is the file in the same overlay or in the pack root.
+ */
+fn find_asset_file(
+    pack_dir: &path::Path,
+    layer: &str,
+    asset_path: &str,
+) -> Option<path::PathBuf> {
+    return [format!("{}{}", layer, asset_path), asset_path.to_string()].into_iter().map(
+        |pack_path| return pack_dir.join(pack_path)
+    ).find(|file| return file.is_file());
+}
+
+fn read_png_size(
+    file: &path::Path,
+) -> Option<(u32, u32)> {
+    let png_reader = png::Decoder::new(fs::File::open(file).ok()?).read_info().ok()?;
+
+    return Some((png_reader.info().width, png_reader.info().height));
+}
+
+fn is_private_use(
+    code_point: u32,
+) -> bool {
+    return matches!(code_point, 0xE000..=0xF8FF | 0xF0000..=0xFFFFD | 0x100000..=0x10FFFD);
+}
+
+/**
+ * Lists some code points, e.g. `U+0041, U+0042 and 3 more`.
+ */
+fn format_code_points(
+    code_points: &[u32],
+) -> String {
+    let printed_code_points = code_points.iter().take(PRINTED_CODE_POINTS).map(
+        |code_point| return format!("U+{:04X}", code_point)
+    ).collect::<Vec<String>>().join(", ");
+
+    return match code_points.len() > PRINTED_CODE_POINTS {
+        true => format!("{} and {} more", printed_code_points, code_points.len() - PRINTED_CODE_POINTS),
+        false => printed_code_points,
+    };
+}
+
+fn provider_type(
+    provider: &serde_json::Value,
+) -> &str {
+    return provider.get("type").and_then(|provider_type| return provider_type.as_str()).unwrap_or_default().trim_start_matches("minecraft:");
+}
+
+/**
+ * The code points a `bitmap` or `space` provider draws, leaving out the `\u0000` of empty cells.
+ */
+fn provider_code_points(
+    provider: &serde_json::Value,
+) -> Vec<u32> {
+    return match provider_type(provider) {
+        "bitmap" => provider.get("chars").and_then(|chars| return chars.as_array()).into_iter().flatten()
+            .filter_map(|row| return row.as_str())
+            .flat_map(|row| return row.chars())
+            .filter(|character| return *character != '\0')
+            .map(|character| return character as u32)
+            .collect(),
+        "space" => provider.get("advances").and_then(|advances| return advances.as_object()).into_iter().flatten()
+            .filter_map(|(character, _)| return character.chars().next())
+            .map(|character| return character as u32)
+            .collect(),
+        _ => Vec::new(),
+    };
+}
+
+/**
+ * The code points of a font, following its `reference` providers to the other fonts `read_font` can read by id.
+ */
+fn collect_font_code_points(
+    font_id: &str,
+    read_font: &dyn Fn(&str) -> Option<serde_json::Value>,
+    visited_fonts: &mut collections::HashSet<String>,
+) -> Vec<u32> {
+    if !visited_fonts.insert(to_full_font_id(font_id)) {
+        return Vec::new();
+    }
+
+    let Some(font) = read_font(font_id) else {
+        return Vec::new();
+    };
+
+    let mut code_points: Vec<u32> = Vec::new();
+
+    for provider in font.get("providers").and_then(|providers| return providers.as_array()).into_iter().flatten() {
+        match provider.get("id").and_then(|id| return id.as_str()) {
+            Some(id) if provider_type(provider) == "reference" => {
+                code_points.extend(collect_font_code_points(id, read_font, visited_fonts));
+            },
+            _ => code_points.extend(provider_code_points(provider)),
+        }
+    }
+
+    return code_points;
+}
+
+/**
+ * Reads the code points of the vanilla default font (and the fonts it references) from a client jar.
+ *
+ * `unihex` providers are left out, the unifont has a fallback glyph for nearly every code point.
+ */
+fn read_vanilla_code_points(
+    client_jar: &client_jar::ClientJar,
+) -> collections::HashSet<u32> {
+    let read_font = |font_id: &str| {
+        let font_path = references::to_asset_path(font_id, "font", ".json");
+
+        return client_jar.read_files(&[font_path.as_str()]).remove(&font_path).and_then(
+            |contents| return serde_json::from_slice::<serde_json::Value>(&contents).ok()
+        );
+    };
+
+    return collect_font_code_points(DEFAULT_FONT, &read_font, &mut collections::HashSet::new()).into_iter().collect();
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Checks a `bitmap` provider: its `ascent`, that its rows are the same length, and that its sheet splits into them.
+ */
+fn check_bitmap_provider(
+    pack_dir: &path::Path,
+    pack_path: &str,
+    json_path: &str,
+    provider: &serde_json::Value,
+    validation: &mut validate::Validation,
+) {
+    let height = provider.get("height").map_or(Some(DEFAULT_BITMAP_HEIGHT), |height| return height.as_i64());
+
+    match (provider.get("ascent").map(|ascent| return ascent.as_i64()), height) {
+        (None, _) => validation.error(pack_path, format!("`{}.ascent` is missing", json_path)),
+        (Some(None), _) => validation.error(pack_path, format!("`{}.ascent` has to be a whole number", json_path)),
+        (Some(Some(ascent)), Some(height)) if ascent > height => validation.error(
+            pack_path,
+            format!("`{}.ascent` {} is larger than `height` {}, Minecraft will not load the font", json_path, ascent, height)
+        ),
+        _ => {},
+    }
+
+    if height.is_none() {
+        validation.error(pack_path, format!("`{}.height` has to be a whole number", json_path));
+    }
+
+    let Some(chars) = provider.get("chars").and_then(|chars| return chars.as_array()) else {
+        validation.error(pack_path, format!("`{}.chars` has to be a list of strings", json_path));
+
+        return;
+    };
+
+    let rows = chars.iter().filter_map(|row| return row.as_str()).map(|row| return row.chars().collect::<Vec<char>>()).collect::<Vec<Vec<char>>>();
+
+    if rows.len() != chars.len() || rows.first().is_none_or(|row| return row.is_empty()) {
+        validation.error(pack_path, format!("`{}.chars` has to be a list of strings that are not empty", json_path));
+
+        return;
+    }
+
+    let columns = rows[0].len();
+
+    if let Some((index, row)) = rows.iter().enumerate().find(|(_, row)| return row.len() != columns) {
+        validation.error(
+            pack_path,
+            format!(
+                "`{}.chars[{}]` has {} characters, but `chars[0]` has {}, every row has to be as long",
+                json_path,
+                index,
+                row.len(),
+                columns
+            )
+        );
+
+        return;
+    }
+
+    let mut seen_code_points: collections::HashSet<u32> = collections::HashSet::new();
+    let mut repeated_code_points = provider_code_points(provider).into_iter().filter(
+        |code_point| return !seen_code_points.insert(*code_point)
+    ).collect::<Vec<u32>>();
+
+    repeated_code_points.sort_unstable();
+    repeated_code_points.dedup();
+
+    if !repeated_code_points.is_empty() {
+        validation.error(
+            pack_path,
+            format!("`{}.chars` has {} more than once, only the first is shown", json_path, format_code_points(&repeated_code_points))
+        );
+    }
+
+    // sheets that are not in the pack are reported with the other references
+    let Some(file) = provider.get("file").and_then(|file| return file.as_str()) else {
+        return;
+    };
+
+    let Some((width, height)) = find_asset_file(pack_dir, references::layer_of(pack_path), &references::to_asset_path(file, "textures", "")).as_deref().and_then(read_png_size) else {
+        return;
+    };
+
+    if width % columns as u32 != 0 || height % rows.len() as u32 != 0 {
+        validation.error(
+            pack_path,
+            format!(
+                "`{}.file` {} is {}x{}, which does not split into {} rows of {} glyphs",
+                json_path,
+                file,
+                width,
+                height,
+                rows.len(),
+                columns
+            )
+        );
+    }
+}
+
+/**
+ * Checks a `space` provider, whose `advances` give single characters a width.
+ */
+fn check_space_provider(
+    pack_path: &str,
+    json_path: &str,
+    provider: &serde_json::Value,
+    validation: &mut validate::Validation,
+) {
+    let Some(advances) = provider.get("advances").and_then(|advances| return advances.as_object()) else {
+        validation.error(pack_path, format!("`{}.advances` has to be an object of characters and widths", json_path));
+
+        return;
+    };
+
+    for (character, advance) in advances {
+        if character.chars().count() != 1 {
+            validation.error(pack_path, format!("`{}.advances` key `{}` has to be a single character", json_path, character.escape_debug()));
+        }
+
+        if !advance.is_number() {
+            validation.error(pack_path, format!("`{}.advances` `{}` has to be a number", json_path, character.escape_debug()));
+        }
+    }
+}
+
+fn check_reference_provider(
+    pack_path: &str,
+    json_path: &str,
+    font_id: &str,
+    provider: &serde_json::Value,
+    validation: &mut validate::Validation,
+) {
+    match provider.get("id").and_then(|id| return id.as_str()) {
+        Some(id) if to_full_font_id(id) == font_id => validation.error(
+            pack_path,
+            format!("`{}.id` references the font itself", json_path)
+        ),
+        Some(_) => {},
+        None => validation.error(pack_path, format!("`{}.id` has to be a font id", json_path)),
+    }
+}
+
+/**
+ * Checks the providers of a font file, and that no code point is drawn by two of them.
+ *
+ * Providers with a `filter` (e.g. only for `uniform`) are meant to overlap with others, so they are not compared.
+ * `vanilla_code_points` are the code points of the vanilla default font, if a client jar was given.
+ */
+fn check_font(
+    pack_dir: &path::Path,
+    pack_path: &str,
+    font_id: &str,
+    font: &serde_json::Value,
+    vanilla_code_points: Option<&collections::HashSet<u32>>,
+    validation: &mut validate::Validation,
+) {
+    let Some(providers) = font.get("providers").and_then(|providers| return providers.as_array()) else {
+        validation.error(pack_path, "`providers` has to be a list".to_string());
+
+        return;
+    };
+
+    let layer = references::layer_of(pack_path);
+
+    // fonts of the pack that are referenced are read from the same overlay or the pack root
+    let read_pack_font = |font_id: &str| {
+        return find_asset_file(pack_dir, layer, &references::to_asset_path(font_id, "font", ".json")).and_then(|file| return validate::read_json(&file));
+    };
+
+    let mut first_providers: collections::HashMap<u32, usize> = collections::HashMap::new();
+
+    for (index, provider) in providers.iter().enumerate() {
+        let json_path = format!("providers[{}]", index);
+
+        let code_points = match provider_type(provider) {
+            "bitmap" => {
+                check_bitmap_provider(pack_dir, pack_path, &json_path, provider, validation);
+
+                provider_code_points(provider)
+            },
+            "space" => {
+                check_space_provider(pack_path, &json_path, provider, validation);
+
+                provider_code_points(provider)
+            },
+            "reference" => {
+                check_reference_provider(pack_path, &json_path, font_id, provider, validation);
+
+                let mut visited_fonts = collections::HashSet::from([font_id.to_string()]);
+
+                provider.get("id").and_then(|id| return id.as_str()).map(
+                    |id| return collect_font_code_points(id, &read_pack_font, &mut visited_fonts)
+                ).unwrap_or_default()
+            },
+            "ttf" | "unihex" => Vec::new(),
+            _ => {
+                validation.error(pack_path, format!("`{}.type` has to be one of: {}", json_path, PROVIDER_TYPES.join(", ")));
+
+                Vec::new()
+            },
+        };
+
+        if provider.get("filter").is_some() {
+            continue;
+        }
+
+        let mut shadowed_code_points: collections::BTreeMap<usize, Vec<u32>> = collections::BTreeMap::new();
+
+        for code_point in &code_points {
+            match first_providers.get(code_point) {
+                Some(first_index) if *first_index != index => shadowed_code_points.entry(*first_index).or_default().push(*code_point),
+                Some(_) => {},
+                None => {
+                    first_providers.insert(*code_point, index);
+                },
+            }
+        }
+
+        for (first_index, code_points) in shadowed_code_points {
+            validation.error(
+                pack_path,
+                format!(
+                    "`{}` draws {} that `providers[{}]` already draws, they are never shown",
+                    json_path,
+                    format_code_points(&code_points),
+                    first_index
+                )
+            );
+        }
+
+        if font_id != DEFAULT_FONT {
+            continue;
+        }
+
+        // the providers of the pack come before the vanilla ones, so they replace the characters of chat
+        match vanilla_code_points {
+            Some(vanilla_code_points) => {
+                let replaced_code_points = code_points.iter().filter(
+                    |code_point| return vanilla_code_points.contains(code_point)
+                ).copied().collect::<Vec<u32>>();
+
+                // retexturing the default font is done on purpose, so it is only pointed out
+                if !replaced_code_points.is_empty() {
+                    validation.warning(
+                        pack_path,
+                        format!(
+                            "`{}` replaces the vanilla characters {} of the default font, which changes how chat looks",
+                            json_path,
+                            format_code_points(&replaced_code_points)
+                        )
+                    );
+                }
+            },
+            None => {
+                let public_code_points = code_points.iter().filter(|code_point| return !is_private_use(**code_point)).copied().collect::<Vec<u32>>();
+
+                if !public_code_points.is_empty() {
+                    validation.warning(
+                        pack_path,
+                        format!(
+                            "`{}` draws {}, which are not in a private use area and can replace vanilla characters of chat (check them with --client-jar)",
+                            json_path,
+                            format_code_points(&public_code_points)
+                        )
+                    );
+                }
+            },
+        }
+    }
+}
+
+/**
+ * Checks the font files of a pack (and its overlays).
+ */
+pub fn check_fonts(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+    client_jar: Option<&client_jar::ClientJar>,
+    validation: &mut validate::Validation,
+) {
+    let font_paths = pack_paths.iter().filter_map(
+        |pack_path| return font_id_of(pack_path).map(|font_id| return (pack_path, font_id))
+    ).collect::<Vec<(&String, String)>>();
+
+    let vanilla_code_points = match font_paths.iter().any(|(_, font_id)| return font_id == DEFAULT_FONT) {
+        true => client_jar.map(read_vanilla_code_points),
+        false => None,
+    };
+
+    for (pack_path, font_id) in font_paths {
+        let Some(font) = validate::read_json(&pack_dir.join(pack_path)) else {
+            continue;
+        };
+
+        check_font(pack_dir, pack_path, &font_id, &font, vanilla_code_points.as_ref(), validation);
+    }
+}
@@ -0,0 +1,401 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::io;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use sha1::Sha1; // sha1 hashing
+
+use sha2::Digest; // sha256 hashing
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::TraverseDirLookFor;
+use crate::traverse_dir;
+use crate::source;
+use crate::logging::info;
+
+//------------------------------------------------------------//
+
+// the file name of the manifest written into directory outputs
+pub const DIR_MANIFEST_FILE_NAME: &str = "ms-rpo.manifest.json";
+
+// bumped whenever the layout of the manifest changes
+const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+//------------------------------------------------------------//
+
+#[derive(Clone)]
+pub struct Hashes {
+    pub size: u64,
+    pub sha1: String,
+    pub sha256: String,
+}
+
+#[derive(Clone)]
+pub struct HashedFile {
+    pub path: String,
+    pub hashes: Hashes,
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Returns true for the files ms-rpo writes into a directory output to describe it (which are not part of the pack).
+ */
+pub fn is_metadata_file(
+    file: &path::Path,
+) -> bool {
+    let file_name = file.file_name();
+
+    return file_name == Some(std::ffi::OsStr::new(DIR_MANIFEST_FILE_NAME))
+        || file_name == Some(std::ffi::OsStr::new(crate::output::OWNERSHIP_MARKER_FILE_NAME));
+}
+
+/**
+ * Hashes everything readable from `reader` with both SHA-1 and SHA-256.
+ */
+pub fn hash_reader(
+    reader: &mut dyn io::Read,
+) -> Hashes {
+    let mut sha1_hasher = Sha1::new();
+    let mut sha256_hasher = sha2::Sha256::new();
+    let mut size: u64 = 0;
+
+    loop {
+        let mut buffer = [0; 8192];
+        let bytes_read = reader.read(&mut buffer).expect("Failed to read data to hash");
+
+        if bytes_read == 0 {
+            break;
+        }
+
+        sha1_hasher.update(&buffer[..bytes_read]);
+        sha256_hasher.update(&buffer[..bytes_read]);
+        size += bytes_read as u64;
+    }
+
+    return Hashes {
+        size,
+        sha1: sha1_hasher.digest().to_string(),
+        sha256: format!("{:x}", sha256_hasher.finalize()),
+    };
+}
+
+pub fn hash_file(
+    file: &path::Path,
+) -> Hashes {
+    let mut file = fs::File::open(file).expect("Failed to open file to hash");
+
+    return hash_reader(&mut file);
+}
+
+/**
+ * Converts a path relative to a pack root into the `/` separated form used inside zip archives.
+ */
+pub fn to_pack_path(
+    relative_path: &path::Path,
+) -> String {
+    return relative_path.components().map(
+        |component| return component.as_os_str().to_str().expect("Failed to convert path component to string")
+    ).collect::<Vec<&str>>().join("/");
+}
+
+/**
+ * Lists the files of a pack by their sorted pack paths, leaving out the files that are not part of the pack.
+ */
+pub fn list_pack_paths(
+    pack_dir: &path::Path,
+) -> Vec<String> {
+    let mut pack_paths = traverse_dir(pack_dir, &TraverseDirLookFor::Files, Some(&|file| return crate::is_pack_file(file))).iter().map(
+        |file| return to_pack_path(file.strip_prefix(pack_dir).expect("Failed to strip prefix"))
+    ).collect::<Vec<String>>();
+
+    pack_paths.sort();
+
+    return pack_paths;
+}
+
+/**
+ * Hashes every file in a directory, sorted by their path relative to the directory.
+ */
+pub fn hash_dir_files(
+    dir: &path::Path,
+    predicate: Option<&dyn Fn(&path::Path) -> bool>,
+) -> Vec<HashedFile> {
+    let traversed_items = traverse_dir(dir, &TraverseDirLookFor::Files, predicate);
+
+    let mut hashed_files = traversed_items.iter().map(
+        |file| {
+            return HashedFile {
+                path: to_pack_path(file.strip_prefix(dir).expect("Failed to strip prefix")),
+                hashes: hash_file(file),
+            };
+        }
+    ).collect::<Vec<HashedFile>>();
+
+    hashed_files.sort_by(|a, b| return a.path.cmp(&b.path));
+
+    return hashed_files;
+}
+
+/**
+ * Hashes the uncompressed contents of every file in a zip archive, sorted by their path.
+ */
+pub fn hash_zip_files(
+    zip_file_path: &path::Path,
+) -> Vec<HashedFile> {
+    let zip_file = fs::File::open(zip_file_path).expect("Failed to open zip file");
+    let mut zip_archive = zip::ZipArchive::new(zip_file).expect("Failed to read zip file");
+
+    let mut hashed_files: Vec<HashedFile> = Vec::new();
+
+    for index in 0..zip_archive.len() {
+        let mut zip_entry = zip_archive.by_index(index).expect("Failed to read zip entry");
+
+        if zip_entry.is_dir() {
+            continue;
+        }
+
+        hashed_files.push(
+            HashedFile {
+                path: zip_entry.name().to_string(),
+                hashes: hash_reader(&mut zip_entry),
+            }
+        );
+    }
+
+    hashed_files.sort_by(|a, b| return a.path.cmp(&b.path));
+
+    return hashed_files;
+}
+
+/**
+ * Computes a single hash for a set of files.
+ *
+ * Directories have no bytes of their own to hash, so the total is the hash of
+ * one `<sha256>  <path>` line per file (the same layout as `sha256sum`).
+ */
+pub fn hash_file_list(
+    hashed_files: &[HashedFile],
+) -> Hashes {
+    let listing = hashed_files.iter().map(
+        |hashed_file| return format!("{}  {}\n", hashed_file.hashes.sha256, hashed_file.path)
+    ).collect::<String>();
+
+    let mut hashes = hash_reader(&mut listing.as_bytes());
+    hashes.size = hashed_files.iter().map(|hashed_file| return hashed_file.hashes.size).sum();
+
+    return hashes;
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Returns `path` with `suffix` appended to its file name (e.g. `pack.zip` -> `pack.zip.sha1`).
+ */
+pub fn append_to_file_name(
+    path: &path::Path,
+    suffix: &str,
+) -> path::PathBuf {
+    let mut file_name = path.file_name().expect("Failed to get file name").to_os_string();
+    file_name.push(suffix);
+
+    return path.with_file_name(file_name);
+}
+
+/**
+ * Writes `.sha1` and `.sha256` files next to `file` in the format understood by `sha1sum -c`.
+ */
+pub fn write_sidecar_files(
+    file: &path::Path,
+    hashes: &Hashes,
+) {
+    let file_name = file.file_name().expect("Failed to get file name").to_str().expect("Failed to convert file name to string");
+
+    fs::write(
+        append_to_file_name(file, ".sha1"),
+        format!("{}  {}\n", hashes.sha1, file_name)
+    ).expect("Failed to write sha1 file");
+
+    fs::write(
+        append_to_file_name(file, ".sha256"),
+        format!("{}  {}\n", hashes.sha256, file_name)
+    ).expect("Failed to write sha256 file");
+}
+
+fn hashes_to_json(
+    hashes: &Hashes,
+) -> serde_json::Value {
+    return serde_json::json!({
+        "size": hashes.size,
+        "sha1": hashes.sha1,
+        "sha256": hashes.sha256,
+    });
+}
+
+/**
+ * Builds the manifest describing a built (or existing) pack.
+ *
+ * `kind` is either `"zip"` or `"directory"`.
+ */
+pub fn build_manifest(
+    kind: &str,
+    source: &path::Path,
+    hashed_files: &[HashedFile],
+    total: &Hashes,
+) -> serde_json::Value {
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH).expect("Failed to get current time")
+        .as_secs();
+
+    let files = hashed_files.iter().map(
+        |hashed_file| {
+            let mut file_json = hashes_to_json(&hashed_file.hashes);
+            file_json["path"] = serde_json::Value::from(hashed_file.path.as_str());
+
+            return file_json;
+        }
+    ).collect::<Vec<serde_json::Value>>();
+
+    return serde_json::json!({
+        "format_version": MANIFEST_FORMAT_VERSION,
+        "kind": kind,
+        "total": hashes_to_json(total),
+        "files": files,
+        "metadata": {
+            "tool": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "created_at_unix": created_at,
+            "source": source.to_str().expect("Failed to convert source to string"),
+        },
+    });
+}
+
+pub fn write_manifest(
+    manifest_file_path: &path::Path,
+    manifest: &serde_json::Value,
+) {
+    let manifest_contents = serde_json::to_string_pretty(manifest).expect("Failed to convert manifest to string");
+
+    fs::write(manifest_file_path, manifest_contents).expect("Failed to write manifest file");
+}
+
+/**
+ * Writes the checksum files and the manifest for a built zip file.
+ */
+pub fn write_zip_checksums(
+    source: &path::Path,
+    zip_file_path: &path::Path,
+    zip_hashes: &Hashes,
+) {
+    write_sidecar_files(zip_file_path, zip_hashes);
+    write_manifest(
+        &append_to_file_name(zip_file_path, ".manifest.json"),
+        &build_manifest("zip", source, &hash_zip_files(zip_file_path), zip_hashes)
+    );
+}
+
+/**
+ * Hashes a built output directory and writes the manifest into it.
+ */
+pub fn write_dir_checksums(
+    source: &path::Path,
+    output_dir: &path::Path,
+) -> Hashes {
+    let hashed_files = hash_dir_files(
+        output_dir,
+        Some(&|file| return !is_metadata_file(file))
+    );
+    let total = hash_file_list(&hashed_files);
+
+    info!("Output directory SHA-1 hash: {}", total.sha1);
+    info!("Output directory SHA-256 hash: {}", total.sha256);
+
+    write_manifest(
+        &output_dir.join(DIR_MANIFEST_FILE_NAME),
+        &build_manifest("directory", source, &hashed_files, &total)
+    );
+
+    return total;
+}
+
+/**
+ * Hashes an existing zip file or directory, returning the per-file hashes and the total.
+ */
+pub fn hash_pack(
+    pack_path: &path::Path,
+) -> (&'static str, Vec<HashedFile>, Hashes) {
+    if pack_path.is_dir() {
+        let hashed_files = hash_dir_files(
+            pack_path,
+            Some(&|file| return !is_metadata_file(file))
+        );
+        let total = hash_file_list(&hashed_files);
+
+        return ("directory", hashed_files, total);
+    }
+
+    let hashed_files = hash_zip_files(pack_path);
+    let total = hash_file(pack_path);
+
+    return ("zip", hashed_files, total);
+}
+
+//------------------------------------------------------------//
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("hash")
+        .about("Computes the checksums of an existing zip file or directory")
+        .arg(
+            clap::Arg::new("path")
+                .help("The zip file or directory to hash")
+                .value_name("PATH")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("json")
+                .long("json")
+                .help("Print the full manifest as json instead of a summary")
+                .action(clap::ArgAction::SetTrue)
+        );
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let pack_path = matched_args.get_one::<path::PathBuf>("path").expect("Failed to get path");
+
+    if !pack_path.exists() {
+        crate::fail_program("Path to hash does not exist");
+    }
+
+    if !pack_path.is_dir() && !source::is_zip_file(pack_path) {
+        crate::fail_program("Path to hash is not a directory or zip file");
+    }
+
+    let (kind, hashed_files, total) = hash_pack(pack_path);
+
+    if matched_args.get_flag("json") {
+        let manifest = build_manifest(kind, pack_path, &hashed_files, &total);
+        println!("{}", serde_json::to_string_pretty(&manifest).expect("Failed to convert manifest to string"));
+
+        return;
+    }
+
+    println!("Hashed {} files in {} ({} bytes)", hashed_files.len(), kind, total.size);
+    println!("SHA-1: {}", total.sha1);
+    println!("SHA-256: {}", total.sha256);
+}
@@ -0,0 +1,243 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::client_jar;
+use crate::pack_mcmeta;
+use crate::report;
+use crate::source;
+use crate::unused;
+use crate::validate;
+use crate::vanilla;
+
+//------------------------------------------------------------//
+
+/**
+ * Reads `pack.pack_format` and `pack.description` from the `pack.mcmeta` of a pack, if it can be read.
+ */
+fn read_pack_info(
+    pack_dir: &path::Path,
+) -> (Option<serde_json::Value>, Option<serde_json::Value>) {
+    let pack_mcmeta = pack_mcmeta::read_pack_mcmeta(pack_dir);
+
+    let pack = pack_mcmeta.as_ref().and_then(|pack_mcmeta| return pack_mcmeta.get("pack"));
+
+    return (
+        pack.and_then(|pack| return pack.get("pack_format")).cloned(),
+        pack.and_then(|pack| return pack.get("description")).cloned(),
+    );
+}
+
+fn format_json_value(
+    value: &Option<serde_json::Value>,
+) -> String {
+    return match value {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => "(missing)".to_string(),
+    };
+}
+
+fn format_formats(
+    formats: Option<(u64, u64)>,
+) -> String {
+    return match formats {
+        Some((min_format, max_format)) if min_format == max_format => min_format.to_string(),
+        Some((min_format, max_format)) => format!("{} to {}", min_format, max_format),
+        None => "(invalid)".to_string(),
+    };
+}
+
+/**
+ * Prints a titled list of files with their sizes, or that there are none.
+ */
+fn print_file_list(
+    title: &str,
+    files: &[(String, u64)],
+) {
+    if files.is_empty() {
+        println!("{}: none", title);
+
+        return;
+    }
+
+    println!("{} ({}, {}):", title, files.len(), report::format_bytes(files.iter().map(|(_, size)| return size).sum()));
+
+    for (pack_path, size) in files {
+        println!("  {:>12}  {}", report::format_bytes(*size), pack_path);
+    }
+}
+
+fn print_group_table(
+    title: &str,
+    groups: &[report::GroupReport],
+) {
+    println!("{:<24} {:>8} {:>14}", title, "Files", "Size");
+
+    for group in groups {
+        println!("{:<24} {:>8} {:>14}", group.name, group.files, report::format_bytes(group.size_after));
+    }
+}
+
+//------------------------------------------------------------//
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("inspect")
+        .about("Prints statistics about a pack directory or zip file")
+        .arg(
+            clap::Arg::new("path")
+                .help("The pack directory or zip file to inspect")
+                .value_name("PATH")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("top")
+                .long("top")
+                .help("The number of largest files listed")
+                .value_name("COUNT")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+        )
+        .arg(
+            clap::Arg::new("json")
+                .long("json")
+                .help("Print the statistics as json")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(validate::get_client_jar_arg())
+        .arg(unused::get_keep_unused_arg());
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let pack_path = matched_args.get_one::<path::PathBuf>("path").expect("Failed to get path");
+    let top_files = *matched_args.get_one::<usize>("top").expect("Failed to get top");
+
+    if !pack_path.exists() {
+        crate::fail_program("Path to inspect does not exist");
+    }
+
+    let pack_source = source::open_pack(pack_path);
+
+    let zip_size = match pack_source.is_zip() {
+        true => Some(fs::metadata(pack_path).expect("Failed to read metadata").len()),
+        false => None,
+    };
+
+    // nothing was processed, so the sizes before and after are the same
+    let pack_report = report::build_report(pack_source.dir(), &report::list_file_sizes(pack_source.dir()), &[], zip_size, top_files);
+
+    let (pack_format, description) = read_pack_info(pack_source.dir());
+    let overlays = pack_mcmeta::read_overlays(pack_source.dir());
+
+    let client_jar = matched_args.get_one::<path::PathBuf>("client_jar").map(
+        |jar_path| return client_jar::open_client_jar(jar_path)
+    );
+    let keep_patterns = matched_args.get_many::<regex::Regex>("keep_unused").unwrap_or_default().cloned().collect::<Vec<regex::Regex>>();
+
+    let unused_assets = unused::find_unused_assets(pack_source.dir(), client_jar.as_ref(), &keep_patterns).into_iter().map(
+        |pack_path| {
+            let size = fs::metadata(pack_source.dir().join(&pack_path)).expect("Failed to read metadata").len();
+
+            return (pack_path, size);
+        }
+    ).collect::<Vec<(String, u64)>>();
+
+    // without a client jar there is nothing to compare to
+    let vanilla_copies = client_jar.as_ref().map(
+        |client_jar| return vanilla::find_vanilla_copies(pack_source.dir(), client_jar).into_iter().map(
+            |pack_path| {
+                let size = fs::metadata(pack_source.dir().join(&pack_path)).expect("Failed to read metadata").len();
+
+                return (pack_path, size);
+            }
+        ).collect::<Vec<(String, u64)>>()
+    );
+
+    let kind = match pack_source.is_zip() {
+        true => "zip",
+        false => "directory",
+    };
+
+    if matched_args.get_flag("json") {
+        let inspection = serde_json::json!({
+            "path": pack_path.to_str().expect("Failed to convert pack_path to string"),
+            "kind": kind,
+            "pack_format": pack_format,
+            "description": description,
+            "overlays": overlays.iter().map(
+                |overlay| return serde_json::json!({
+                    "directory": overlay.directory,
+                    "formats": overlay.formats.map(|(min_format, max_format)| return [min_format, max_format]),
+                })
+            ).collect::<Vec<serde_json::Value>>(),
+            "files": pack_report.files.len(),
+            "size": pack_report.size_after,
+            "zip_size": pack_report.zip_size,
+            "namespaces": pack_report.namespaces.iter().map(report::group_to_json).collect::<Vec<serde_json::Value>>(),
+            "categories": pack_report.categories.iter().map(report::group_to_json).collect::<Vec<serde_json::Value>>(),
+            "largest_files": report::largest_files(&pack_report).iter().map(
+                |file| return serde_json::json!({ "path": file.path, "size": file.size_after })
+            ).collect::<Vec<serde_json::Value>>(),
+            "unused_assets": unused_assets.iter().map(
+                |(pack_path, size)| return serde_json::json!({ "path": pack_path, "size": size })
+            ).collect::<Vec<serde_json::Value>>(),
+            "vanilla_copies": vanilla_copies.as_ref().map(
+                |vanilla_copies| return vanilla_copies.iter().map(
+                    |(pack_path, size)| return serde_json::json!({ "path": pack_path, "size": size })
+                ).collect::<Vec<serde_json::Value>>()
+            ),
+        });
+
+        println!("{}", serde_json::to_string_pretty(&inspection).expect("Failed to convert inspection to string"));
+
+        return;
+    }
+
+    println!("Pack: {} ({})", pack_path.to_str().expect("Failed to convert pack_path to string"), kind);
+    println!("Pack format: {}", format_json_value(&pack_format));
+    println!("Description: {}", format_json_value(&description));
+
+    for overlay in &overlays {
+        println!("Overlay: {} (formats {})", overlay.directory, format_formats(overlay.formats));
+    }
+    println!("Files: {} ({})", pack_report.files.len(), report::format_bytes(pack_report.size_after));
+
+    if let Some(zip_size) = pack_report.zip_size {
+        println!("Zip file size: {}", report::format_bytes(zip_size));
+    }
+
+    println!("");
+    print_group_table("Namespace", &pack_report.namespaces);
+
+    println!("");
+    print_group_table("Category", &pack_report.categories);
+
+    println!("");
+    println!("Largest files:");
+
+    for file in report::largest_files(&pack_report) {
+        println!("  {:>12}  {}", report::format_bytes(file.size_after), file.path);
+    }
+
+    println!("");
+
+    print_file_list("Unused assets", &unused_assets);
+
+    if let Some(vanilla_copies) = &vanilla_copies {
+        println!("");
+        print_file_list("Same as vanilla", vanilla_copies);
+    }
+}
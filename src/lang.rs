@@ -0,0 +1,493 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::client_jar;
+use crate::hash;
+use crate::source;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+// the locale the other locales are compared to by default
+const DEFAULT_REFERENCE_LOCALE: &str = "en_us";
+
+// the locale the game reads a key from when the selected locale does not have it
+const FALLBACK_LOCALE: &str = "en_us";
+
+// the vanilla lang file of the fallback locale, the only vanilla lang file in the client jar
+const VANILLA_FALLBACK_LANG: &str = "assets/minecraft/lang/en_us.json";
+
+//------------------------------------------------------------//
+
+/**
+ * A value of a lang file, with the pack path of the file it is in.
+ */
+pub struct LangValue {
+    pub value: String,
+    pub pack_path: String,
+}
+
+/**
+ * How a locale differs from the reference locale, each list holding translation keys.
+ */
+pub struct LocaleReport {
+    pub locale: String,
+    pub key_count: usize,
+    pub missing_keys: Vec<String>,
+    pub extra_keys: Vec<String>,
+    // the keys with the placeholders in the reference locale and in this one
+    pub placeholder_mismatches: Vec<(String, collections::BTreeSet<usize>, collections::BTreeSet<usize>)>,
+    pub empty_keys: Vec<String>,
+    // the keys with the same value as in the fallback locale, which the game would show without them
+    pub fallback_duplicates: Vec<String>,
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Reads the arguments a translation formats, as 0-based indices.
+ *
+ * Like the game, `%s` takes the next argument, `%2$s` the second one and `%%` is a literal `%`.
+ */
+fn read_placeholders(
+    value: &str,
+) -> collections::BTreeSet<usize> {
+    let mut placeholders = collections::BTreeSet::new();
+    let mut next_index = 0;
+    let mut characters = value.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        if character != '%' {
+            continue;
+        }
+
+        if characters.next_if_eq(&'%').is_some() {
+            continue;
+        }
+
+        let mut digits = String::new();
+
+        while let Some(digit) = characters.next_if(|character| return character.is_ascii_digit()) {
+            digits.push(digit);
+        }
+
+        // digits without a `$` are text, e.g. the `50` of `%50`
+        let is_positional = !digits.is_empty() && characters.next_if_eq(&'$').is_some();
+
+        if !digits.is_empty() && !is_positional || characters.next_if(|character| return character.is_ascii_alphabetic()).is_none() {
+            continue;
+        }
+
+        if !is_positional {
+            placeholders.insert(next_index);
+            next_index += 1;
+        } else if let Some(index) = digits.parse::<usize>().ok().and_then(|position| return position.checked_sub(1)) {
+            placeholders.insert(index);
+        }
+    }
+
+    return placeholders;
+}
+
+/**
+ * Formats placeholders the way a translation can write them, e.g. `%1$s, %2$s`.
+ */
+pub fn format_placeholders(
+    placeholders: &collections::BTreeSet<usize>,
+) -> String {
+    if placeholders.is_empty() {
+        return "none".to_string();
+    }
+
+    return placeholders.iter().map(|index| return format!("%{}$s", index + 1)).collect::<Vec<String>>().join(", ");
+}
+
+/**
+ * Reads the json lang files in the pack root by locale, the keys of all namespaces of a locale being merged like in game.
+ *
+ * Values that are not strings are left out, the syntax checks report lang files that cannot be read.
+ */
+fn read_locales(
+    pack_dir: &path::Path,
+) -> collections::BTreeMap<String, collections::BTreeMap<String, LangValue>> {
+    let pack_paths = hash::list_pack_paths(pack_dir);
+
+    let mut locales = collections::BTreeMap::<String, collections::BTreeMap<String, LangValue>>::new();
+
+    for pack_path in pack_paths {
+        let components = pack_path.split('/').collect::<Vec<&str>>();
+
+        if components.len() != 4 || components[0] != "assets" || components[2] != "lang" || !components[3].ends_with(".json") {
+            continue;
+        }
+
+        let locale = components[3].trim_end_matches(".json").to_lowercase();
+
+        let Some(serde_json::Value::Object(lang)) = validate::read_json(&pack_dir.join(&pack_path)) else {
+            continue;
+        };
+
+        let values = locales.entry(locale).or_default();
+
+        for (key, value) in lang {
+            if let serde_json::Value::String(value) = value {
+                values.insert(key, LangValue { value, pack_path: pack_path.clone() });
+            }
+        }
+    }
+
+    return locales;
+}
+
+/**
+ * Compares every locale of a pack to the reference locale, which is reported first.
+ *
+ * Returns `None` if the pack has no lang file of the reference locale.
+ */
+pub fn check_locales(
+    pack_dir: &path::Path,
+    reference_locale: &str,
+) -> Option<Vec<LocaleReport>> {
+    let locales = read_locales(pack_dir);
+
+    let reference = locales.get(reference_locale)?;
+    let fallback = locales.get(FALLBACK_LOCALE);
+
+    let mut locale_names = locales.keys().filter(|locale| return *locale != reference_locale).collect::<Vec<&String>>();
+    locale_names.insert(0, locales.get_key_value(reference_locale)?.0);
+
+    return Some(
+        locale_names.into_iter().map(
+            |locale| {
+                let values = &locales[locale];
+                let is_reference = locale == reference_locale;
+
+                let placeholder_mismatches = values.iter().filter_map(
+                    |(key, lang_value)| {
+                        let reference_placeholders = read_placeholders(&reference.get(key)?.value);
+                        let placeholders = read_placeholders(&lang_value.value);
+
+                        if reference_placeholders == placeholders {
+                            return None;
+                        }
+
+                        return Some((key.clone(), reference_placeholders, placeholders));
+                    }
+                ).collect();
+
+                let fallback_duplicates = match (locale.as_str(), fallback) {
+                    (FALLBACK_LOCALE, _) | (_, None) => Vec::new(),
+                    (_, Some(fallback)) => values.iter().filter(
+                        |(key, lang_value)| return fallback.get(*key).is_some_and(|fallback_value| return fallback_value.value == lang_value.value)
+                    ).map(|(key, _)| return key.clone()).collect(),
+                };
+
+                return LocaleReport {
+                    locale: locale.clone(),
+                    key_count: values.len(),
+                    missing_keys: reference.keys().filter(|key| return !is_reference && !values.contains_key(*key)).cloned().collect(),
+                    extra_keys: values.keys().filter(|key| return !reference.contains_key(*key)).cloned().collect(),
+                    placeholder_mismatches,
+                    empty_keys: values.iter().filter(|(_, lang_value)| return lang_value.value.trim().is_empty()).map(|(key, _)| return key.clone()).collect(),
+                    fallback_duplicates,
+                };
+            }
+        ).collect()
+    );
+}
+
+/**
+ * Finds the values of lang files that are the same as the value of the fallback locale, by pack path.
+ *
+ * The game shows the fallback value for a key the selected locale does not have, so these can be left out. Keys of the
+ * vanilla fallback lang file are kept, since vanilla has a translation of its own for them in the other locales.
+ */
+pub fn find_fallback_duplicates(
+    pack_dir: &path::Path,
+    client_jar: &client_jar::ClientJar,
+) -> collections::HashMap<String, Vec<String>> {
+    let locales = read_locales(pack_dir);
+
+    let Some(fallback) = locales.get(FALLBACK_LOCALE) else {
+        return collections::HashMap::new();
+    };
+
+    let vanilla_keys = client_jar.read_files(&[VANILLA_FALLBACK_LANG]).remove(VANILLA_FALLBACK_LANG).and_then(
+        |file_contents| return serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&file_contents).ok()
+    ).unwrap_or_default();
+
+    let mut fallback_duplicates = collections::HashMap::<String, Vec<String>>::new();
+
+    for (locale, values) in &locales {
+        if locale == FALLBACK_LOCALE {
+            continue;
+        }
+
+        for (key, lang_value) in values {
+            if vanilla_keys.contains_key(key) || fallback.get(key).is_none_or(|fallback_value| return fallback_value.value != lang_value.value) {
+                continue;
+            }
+
+            fallback_duplicates.entry(lang_value.pack_path.clone()).or_default().push(key.clone());
+        }
+    }
+
+    return fallback_duplicates;
+}
+
+/**
+ * Removes keys from the lang files of the pack files, before the json stage minifies them.
+ */
+pub fn prune_fallback_duplicates(
+    pack_files: &mut crate::PackFiles,
+    fallback_duplicates: &collections::HashMap<String, Vec<String>>,
+) -> crate::StageResult {
+    const STAGE_NAME: &str = "lang";
+
+    let operation_start = std::time::Instant::now();
+
+    let mut processed_files = Vec::new();
+
+    for (pack_path, keys) in fallback_duplicates {
+        // pruned files are not in the pack to begin with
+        let Some(lang_contents) = pack_files.get_mut(pack_path) else {
+            continue;
+        };
+
+        let file_operation_start = std::time::Instant::now();
+        let size_before = lang_contents.len() as u64;
+
+        let mut lang = serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(lang_contents).expect("Failed to parse lang file");
+
+        for key in keys {
+            lang.remove(key);
+        }
+
+        *lang_contents = serde_json::to_vec(&lang).expect("Failed to convert lang file to string");
+
+        processed_files.push(
+            crate::ProcessedFile {
+                path: path::PathBuf::from(pack_path),
+                size_before,
+                size_after: lang_contents.len() as u64,
+                duration: file_operation_start.elapsed(),
+            }
+        );
+    }
+
+    for processed_file in &processed_files {
+        crate::log_processed_file(STAGE_NAME, processed_file);
+    }
+
+    let operation_end = operation_start.elapsed();
+
+    let stage_result = crate::StageResult {
+        name: STAGE_NAME,
+        files: processed_files,
+        duration: operation_end,
+    };
+
+    crate::log_stage_result(
+        &stage_result,
+        format!(
+            "Left out {} lang values that are the same as {} in {:.2?}",
+            fallback_duplicates.values().map(|keys| return keys.len()).sum::<usize>(),
+            FALLBACK_LOCALE,
+            operation_end
+        ).as_str(),
+    );
+
+    return stage_result;
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Prints a titled list of translation keys, or nothing if there are none.
+ */
+fn print_key_list(
+    title: &str,
+    keys: &[String],
+) {
+    if keys.is_empty() {
+        return;
+    }
+
+    println!("  {} ({}):", title, keys.len());
+
+    for key in keys {
+        println!("    {}", key);
+    }
+}
+
+fn locale_report_to_json(
+    locale_report: &LocaleReport,
+) -> serde_json::Value {
+    return serde_json::json!({
+        "locale": locale_report.locale,
+        "keys": locale_report.key_count,
+        "missing_keys": locale_report.missing_keys,
+        "extra_keys": locale_report.extra_keys,
+        "placeholder_mismatches": locale_report.placeholder_mismatches.iter().map(
+            |(key, reference_placeholders, placeholders)| return serde_json::json!({
+                "key": key,
+                "reference": format_placeholders(reference_placeholders),
+                "placeholders": format_placeholders(placeholders),
+            })
+        ).collect::<Vec<serde_json::Value>>(),
+        "empty_keys": locale_report.empty_keys,
+        "fallback_duplicates": locale_report.fallback_duplicates,
+    });
+}
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("lang")
+        .about("Compares the lang files of every locale of a pack to a reference locale")
+        .arg(
+            clap::Arg::new("path")
+                .help("The pack directory or zip file to check")
+                .value_name("PATH")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("reference_locale")
+                .long("reference-locale")
+                .help("The locale the other locales are compared to")
+                .value_name("LOCALE")
+                .default_value(DEFAULT_REFERENCE_LOCALE)
+        )
+        .arg(
+            clap::Arg::new("json")
+                .long("json")
+                .help("Print the differences as json")
+                .action(clap::ArgAction::SetTrue)
+        );
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let pack_path = matched_args.get_one::<path::PathBuf>("path").expect("Failed to get path");
+    let reference_locale = matched_args.get_one::<String>("reference_locale").expect("Failed to get reference_locale").to_lowercase();
+
+    if !pack_path.exists() {
+        crate::fail_program("Path to check does not exist");
+    }
+
+    let pack_source = source::open_pack(pack_path);
+
+    let Some(locale_reports) = check_locales(pack_source.dir(), &reference_locale) else {
+        crate::fail_program(format!("The pack has no {} lang files", reference_locale).as_str());
+    };
+
+    if matched_args.get_flag("json") {
+        let locales = serde_json::json!({
+            "reference_locale": reference_locale,
+            "locales": locale_reports.iter().map(locale_report_to_json).collect::<Vec<serde_json::Value>>(),
+        });
+
+        println!("{}", serde_json::to_string_pretty(&locales).expect("Failed to convert locales to string"));
+
+        return;
+    }
+
+    for (index, locale_report) in locale_reports.iter().enumerate() {
+        if index > 0 {
+            println!("");
+        }
+
+        match index {
+            0 => println!("{} ({} keys, the reference locale):", locale_report.locale, locale_report.key_count),
+            _ => println!("{} ({} keys):", locale_report.locale, locale_report.key_count),
+        }
+
+        print_key_list("Missing keys", &locale_report.missing_keys);
+        print_key_list(format!("Keys not in {}", reference_locale).as_str(), &locale_report.extra_keys);
+
+        if !locale_report.placeholder_mismatches.is_empty() {
+            println!("  Placeholders that do not match {} ({}):", reference_locale, locale_report.placeholder_mismatches.len());
+
+            for (key, reference_placeholders, placeholders) in &locale_report.placeholder_mismatches {
+                println!(
+                    "    {}: {} in {}, {} in {}",
+                    key,
+                    format_placeholders(reference_placeholders),
+                    reference_locale,
+                    format_placeholders(placeholders),
+                    locale_report.locale
+                );
+            }
+        }
+
+        print_key_list("Empty values", &locale_report.empty_keys);
+        print_key_list(format!("Same as {}", FALLBACK_LOCALE).as_str(), &locale_report.fallback_duplicates);
+    }
+}
+
+//------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_placeholders_reads_arguments_like_the_game() {
+        let cases: [(&str, &[usize]); 24] = [
+            ("", &[]),
+            ("Ruby Sword", &[]),
+            ("%s", &[0]),
+            ("%s hit %s", &[0, 1]),
+            ("%d levels", &[0]),
+            // `%%` is a literal `%`, which can be followed by a placeholder
+            ("100%%", &[]),
+            ("%%s", &[]),
+            ("%%%s", &[0]),
+            ("%s%%", &[0]),
+            // digits without a `$` are text
+            ("%50", &[]),
+            ("%50 off for %s", &[0]),
+            ("%", &[]),
+            ("% s", &[]),
+            ("%$s", &[]),
+            ("%1$", &[]),
+            ("%1$s", &[0]),
+            ("%2$s and %1$s", &[0, 1]),
+            ("%3$s", &[2]),
+            ("%0$s", &[]),
+            ("%10$s", &[9]),
+            // positional placeholders do not move the next sequential one
+            ("%s %1$s %s", &[0, 1]),
+            ("%2$s %s", &[0, 1]),
+            ("%3$s %s %s", &[0, 1, 2]),
+            ("%1$s %1$s", &[0]),
+        ];
+
+        for (value, placeholders) in cases {
+            assert_eq!(
+                read_placeholders(value),
+                placeholders.iter().copied().collect::<collections::BTreeSet<usize>>(),
+                "Reading {:?}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn format_placeholders_writes_positional_placeholders() {
+        assert_eq!(format_placeholders(&collections::BTreeSet::new()), "none");
+        assert_eq!(format_placeholders(&read_placeholders("%s %s")), "%1$s, %2$s");
+        assert_eq!(format_placeholders(&read_placeholders("%3$s")), "%3$s");
+    }
+}
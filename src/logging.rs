@@ -0,0 +1,217 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::io::Write;
+use std::io;
+use std::panic;
+use std::sync::atomic;
+
+//------------------------------------------------------------//
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+    Trace = 4,
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        return match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+    }
+}
+
+//------------------------------------------------------------//
+
+static LOG_LEVEL: atomic::AtomicU8 = atomic::AtomicU8::new(Level::Info as u8);
+static LOG_JSON: atomic::AtomicBool = atomic::AtomicBool::new(false);
+
+//------------------------------------------------------------//
+
+/**
+ * Sets up logging for the rest of the program. Everything is written to stderr,
+ * stdout is left for the results of a command (e.g. the output of `hash`).
+ */
+pub fn init(
+    level: Level,
+    json: bool,
+) {
+    LOG_LEVEL.store(level as u8, atomic::Ordering::Relaxed);
+    LOG_JSON.store(json, atomic::Ordering::Relaxed);
+
+    if json {
+        // failures (which are panics in this program) become events too
+        panic::set_hook(
+            Box::new(
+                |panic_info| {
+                    let message = match panic_info.payload().downcast_ref::<&str>() {
+                        Some(message) => message.to_string(),
+                        None => match panic_info.payload().downcast_ref::<String>() {
+                            Some(message) => message.clone(),
+                            None => "Unknown error".to_string(),
+                        },
+                    };
+
+                    event(
+                        Level::Error,
+                        "error",
+                        serde_json::json!({
+                            "message": message,
+                            "location": panic_info.location().map(|location| return location.to_string()),
+                        })
+                    );
+                }
+            )
+        );
+    }
+}
+
+pub fn is_enabled(
+    level: Level,
+) -> bool {
+    return level as u8 <= LOG_LEVEL.load(atomic::Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    return LOG_JSON.load(atomic::Ordering::Relaxed);
+}
+
+/**
+ * Writes a plain message. In json mode it becomes a `log` event (empty spacer lines are dropped).
+ */
+pub fn log(
+    level: Level,
+    message: &str,
+) {
+    if !is_enabled(level) {
+        return;
+    }
+
+    if is_json() {
+        if !message.trim().is_empty() {
+            event(level, "log", serde_json::json!({ "message": message }));
+        }
+
+        return;
+    }
+
+    match level {
+        Level::Error => eprintln!("Error: {}", message),
+        Level::Warn => eprintln!("Warning: {}", message),
+        _ => eprintln!("{}", message),
+    }
+}
+
+/**
+ * Writes a structured event as a single json line. Does nothing outside of json mode.
+ */
+pub fn event(
+    level: Level,
+    event_name: &str,
+    fields: serde_json::Value,
+) {
+    if !is_json() || !is_enabled(level) {
+        return;
+    }
+
+    let mut event_json = serde_json::json!({
+        "event": event_name,
+        "level": level.name(),
+    });
+
+    if let serde_json::Value::Object(fields) = fields {
+        for (key, value) in fields {
+            event_json[key] = value;
+        }
+    }
+
+    // write the whole line at once so events from parallel stages never interleave
+    let line = format!("{}\n", serde_json::to_string(&event_json).expect("Failed to convert event to string"));
+    let _ = io::stderr().lock().write_all(line.as_bytes());
+}
+
+/**
+ * Reports a file handled by a stage: a `file` event in json mode, otherwise a line at trace level (`-vv`).
+ */
+pub fn file_finished(
+    stage: &str,
+    fields: serde_json::Value,
+    message: &str,
+) {
+    if is_json() {
+        let mut fields = fields;
+        fields["stage"] = serde_json::Value::from(stage);
+
+        event(Level::Info, "file", fields);
+
+        return;
+    }
+
+    log(Level::Trace, message);
+}
+
+/**
+ * Reports a finished stage: a `stage` event in json mode, otherwise the summary line at info level.
+ */
+pub fn stage_finished(
+    stage: &str,
+    fields: serde_json::Value,
+    message: &str,
+) {
+    if is_json() {
+        let mut fields = fields;
+        fields["stage"] = serde_json::Value::from(stage);
+        fields["message"] = serde_json::Value::from(message);
+
+        event(Level::Info, "stage", fields);
+
+        return;
+    }
+
+    log(Level::Info, message);
+}
+
+//------------------------------------------------------------//
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::Level::Error, &format!($($arg)*))
+    };
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::Level::Warn, &format!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::Level::Info, &format!($($arg)*))
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::Level::Debug, &format!($($arg)*))
+    };
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::Level::Trace, &format!($($arg)*))
+    };
+}
+
+pub(crate) use {error, warning, info, debug, trace};
@@ -12,7 +12,9 @@
 
 // import standard library modules
 
+use std::collections;
 use std::io;
+use std::io::IsTerminal;
 use std::io::Write;
 use std::fs;
 use std::path;
@@ -22,58 +24,173 @@ use std::process;
 
 // import third-party modules
 
-use sha1::Sha1; // sha1 hashing
-
 use rayon::prelude::*; // parallel iterators
 
-use tempdir::TempDir; // temporary directories
+//------------------------------------------------------------//
+
+// import local modules
+
+use logging::{error, info, trace};
+
+mod logging;
+
+mod bitmap_font;
+mod budget;
+mod client_jar;
+mod custom_model_data;
+mod diff;
+mod font;
+mod hash;
+mod inspect;
+mod lang;
+mod ogg;
+mod optimize;
+mod output;
+mod pack_mcmeta;
+mod references;
+mod progress;
+mod report;
+mod serve;
+mod server_properties;
+mod sounds;
+mod source;
+mod target;
+mod unused;
+mod validate;
+mod vanilla;
+mod verify;
+mod watch;
 
 //------------------------------------------------------------//
 
-#[allow(dead_code)] // `Folders` is currently unused
 enum TraverseDirLookFor {
     All,
     Files,
     Folders,
 }
 
+/**
+ * A file that was changed by a stage, with its size before and after.
+ */
+struct ProcessedFile {
+    path: path::PathBuf,
+    size_before: u64,
+    size_after: u64,
+    duration: std::time::Duration,
+}
+
+struct StageResult {
+    name: &'static str,
+    files: Vec<ProcessedFile>,
+    duration: std::time::Duration,
+}
+
+/**
+ * The contents of the files of a pack by their pack path, which the stages process in memory.
+ */
+type PackFiles = collections::BTreeMap<String, Vec<u8>>;
+
+//------------------------------------------------------------//
+
+fn log_processed_file(
+    stage: &str,
+    processed_file: &ProcessedFile,
+) {
+    let path = processed_file.path.to_str().expect("Failed to convert path to string");
+
+    logging::file_finished(
+        stage,
+        serde_json::json!({
+            "path": path,
+            "size_before": processed_file.size_before,
+            "size_after": processed_file.size_after,
+            "duration_ms": processed_file.duration.as_secs_f64() * 1000.0,
+        }),
+        format!("{}: {} ({} -> {} bytes) in {:.2?}", stage, path, processed_file.size_before, processed_file.size_after, processed_file.duration).as_str(),
+    );
+}
+
+fn log_stage_result(
+    stage_result: &StageResult,
+    message: &str,
+) {
+    logging::stage_finished(
+        stage_result.name,
+        serde_json::json!({
+            "files": stage_result.files.len(),
+            "size_before": stage_result.files.iter().map(|file| return file.size_before).sum::<u64>(),
+            "size_after": stage_result.files.iter().map(|file| return file.size_after).sum::<u64>(),
+            "duration_ms": stage_result.duration.as_secs_f64() * 1000.0,
+        }),
+        message,
+    );
+}
+
 //------------------------------------------------------------//
 
 fn exit_program(
     exit_message: &str,
 ) -> ! {
-    println!("\nExiting Program...\n\n{}", exit_message);
+    info!("");
+    info!("Exiting Program...");
+    info!("");
+    error!("{}", exit_message);
     process::exit(0);
 }
 
-fn ask_user_to_confirm(
-    should_ask_user_to_confirm: bool,
-    prompt: &str,
-) -> bool {
-    if !should_ask_user_to_confirm {
-        return true;
+/**
+ * Like `exit_program`, but exits with a non-zero code so scripts and CI notice the failure.
+ */
+fn fail_program(
+    exit_message: &str,
+) -> ! {
+    info!("");
+    error!("{}", exit_message);
+    process::exit(1);
+}
+
+/**
+ * Prints the plan of a run and asks the user to confirm it once, exiting if they do not.
+ *
+ * Without a terminal to ask in, the run is stopped unless `assume_yes` is set.
+ */
+fn confirm_plan(
+    plan_steps: &[String],
+    assume_yes: bool,
+) {
+    if assume_yes {
+        return;
     }
 
-    let response: bool;
+    if !io::stdin().is_terminal() {
+        fail_program("Cannot ask to confirm the plan without a terminal, pass --yes to run it anyway");
+    }
+
+    // prompts go to stderr with the rest of the human output
+    eprintln!("Plan:");
+    for plan_step in plan_steps {
+        eprintln!("  - {}", plan_step);
+    }
+    eprintln!("");
 
     loop {
-        println!("{}\nContinue? (Y)es (N)o", prompt);
+        eprint!("Continue? (Y)es (N)o ");
+        io::stderr().flush().expect("Failed to flush stderr");
 
         let mut input = String::new();
-        io::stdin().read_line(&mut input).expect("Failed to read line");
 
-        response = match input.trim().to_lowercase().as_str() {
-            "y" | "yes" => true,
-            "n" | "no" => false,
-            _ => {
-                continue;
-            },
-        };
+        // end of input counts as a no
+        if io::stdin().read_line(&mut input).expect("Failed to read line") == 0 {
+            eprintln!("");
+            exit_program("User did not confirm to continue");
+        }
 
-        break;
+        match input.trim().to_lowercase().as_str() {
+            "y" | "yes" => return,
+            "n" | "no" => exit_program("User did not confirm to continue"),
+            _ => continue,
+        }
     }
-
-    return response;
 }
 
 fn traverse_dir(
@@ -155,7 +272,7 @@ fn clone_dir(
                 output_file_path.parent().expect("Failed to get parent directory")
             ).expect("Failed to create parent directory");
 
-            // println!("Cloning {}", file.to_str().expect("Failed to convert file to string"));
+            trace!("Cloning {}", file.to_str().expect("Failed to convert file to string"));
 
             fs::copy(file, output_file_path).expect("Failed to copy file");
         }
@@ -163,273 +280,384 @@ fn clone_dir(
 
     let operation_end = operation_start.elapsed();
 
-    println!("Cloned {} directory items in {:.2?}", traversed_items.len(), operation_end);
+    logging::stage_finished(
+        "clone",
+        serde_json::json!({
+            "files": traversed_items.len(),
+            "duration_ms": operation_end.as_secs_f64() * 1000.0,
+        }),
+        format!("Cloned {} directory items in {:.2?}", traversed_items.len(), operation_end).as_str(),
+    );
 }
 
-fn empty_dir(
-    dir: &path::Path,
-) {
-    let operation_start = std::time::Instant::now();
+//------------------------------------------------------------//
 
-    let dir_items = fs::read_dir(dir).expect("Failed to read directory");
+fn is_json_like_file(
+    file: &path::Path,
+) -> bool {
+    // check if the file ends with `.json` or `.mcmeta`
+    let s = file.to_str().expect("Failed to convert file to string");
+    return s.ends_with(".json") || s.ends_with(".mcmeta");
+}
 
-    let dir_items_vec = dir_items.map(
-        |dir_item| return dir_item.expect("Failed to read item").path()
-    ).collect::<Vec<path::PathBuf>>();
+fn is_yaml_like_file(
+    file: &path::Path,
+) -> bool {
+    // check if the file ends with `.yml` or `.yaml`
+    let s = file.to_str().expect("Failed to convert file to string");
+    return s.ends_with(".yml") || s.ends_with(".yaml");
+}
 
-    dir_items_vec.par_iter().for_each(
-        |file| {
-            if file.is_dir() {
-                fs::remove_dir_all(file).expect("Failed to remove directory");
-            }
+fn is_open_gl_sl_like_file(
+    file: &path::Path,
+) -> bool {
+    // check if the file ends with `.vsh` or `.fsh`
+    let s = file.to_str().expect("Failed to convert file to string");
+    return s.ends_with(".vsh") || s.ends_with(".fsh");
+}
 
-            if file.is_file() {
-                fs::remove_file(file).expect("Failed to remove file");
-            }
-        }
-    );
+fn is_png_like_file(
+    file: &path::Path,
+) -> bool {
+    // check if the file ends with `.png`
+    let s = file.to_str().expect("Failed to convert file to string");
+    return s.ends_with(".png");
+}
 
-    let operation_end = operation_start.elapsed();
+fn is_ogg_like_file(
+    file: &path::Path,
+) -> bool {
+    // check if the file ends with `.ogg`
+    let s = file.to_str().expect("Failed to convert file to string");
+    return s.ends_with(".ogg");
+}
 
-    println!("Emptied {} directory items in {:.2?}", dir_items_vec.len(), operation_end);
+/**
+ * Returns false for files that only matter in the source tree and are left out of the pack.
+ */
+fn is_pack_file(
+    file: &path::Path,
+) -> bool {
+    // remove `.md` and `.old` files
+    // TODO: make this configurable
+    let s = file.to_str().expect("Failed to convert file to string");
+    return !s.ends_with(".md") && !s.ends_with(".old");
 }
 
 //------------------------------------------------------------//
 
-fn minify_json_files(
-    input_dir: &path::Path,
-    output_dir: &path::Path,
-) {
-    let traversed_items = traverse_dir(
-        &path::PathBuf::from(&input_dir),
-        &TraverseDirLookFor::Files,
-        None,
-    );
+fn minify_json(
+    file_contents: &[u8],
+) -> Vec<u8> {
+    let parsed_json = serde_json::from_slice::<serde_json::Value>(file_contents).expect("Failed to parse json");
 
-    let json_like_files = traversed_items.par_iter().filter(
-        // check if the file ends with `.json` or `.mcmeta`
-        |file| {
-            let s = file.to_str().expect("Failed to convert file to string");
-            return s.ends_with(".json") || s.ends_with(".mcmeta");
-        }
-    ).collect::<Vec<&path::PathBuf>>();
+    return serde_json::to_vec(&parsed_json).expect("Failed to convert json to string");
+}
 
-    let operation_start = std::time::Instant::now();
+fn minify_yaml(
+    file_contents: &[u8],
+) -> Vec<u8> {
+    let parsed_yaml_as_json = serde_yaml::from_slice::<serde_json::Value>(file_contents).expect("Failed to parse yaml");
 
-    json_like_files.par_iter().for_each(
-        |file| {
-            if file.is_dir() {
-                return;
+    return serde_json::to_vec(&parsed_yaml_as_json).expect("Failed to convert yaml to json");
+}
+
+fn minify_open_gl_sl(
+    file_contents: &[u8],
+) -> Vec<u8> {
+    let file_contents = std::str::from_utf8(file_contents).expect("Failed to read file as utf-8");
+
+    let minified_file_contents: String = file_contents.lines().map(
+        |mut line| {
+            // remove comments from lines (including comments at the end of lines)
+            if let Some(index) = line.find("//") {
+                line = line[..index].trim();
+            } else {
+                line = line.trim();
             }
 
-            let file_contents = fs::read_to_string(file).expect("Failed to read file");
+            return line;
+        }
+    ).filter(
+        |line| {
+            // remove empty lines
+            if line.is_empty() {
+                return false;
+            }
 
-            let parsed_json = serde_json::from_str::<serde_json::Value>(&file_contents).expect("Failed to parse json");
+            return true;
+        }
+    ).collect::<Vec<&str>>().join("\n");
 
-            let minified_file_contents = serde_json::to_string(&parsed_json).expect("Failed to convert json to string");
+    return minified_file_contents.into_bytes();
+}
 
-            let relative_file_path_from_input_dir = file
-                .strip_prefix(input_dir).expect("Failed to strip prefix")
-                .to_str().expect("Failed to convert relative file path to string");
+fn compress_png(
+    file_contents: &[u8],
+) -> Vec<u8> {
+    return oxipng::optimize_from_memory(file_contents, &oxipng::Options::max_compression()).expect("Failed to optimize png");
+}
 
-            let output_file_path = output_dir.join(relative_file_path_from_input_dir);
+fn strip_ogg(
+    file_contents: &[u8],
+) -> Vec<u8> {
+    // files that are not a single vorbis stream are copied as they are, validation reports the broken ones
+    return match ogg::strip_comments(file_contents) {
+        Ok(stripped_file_contents) if stripped_file_contents.len() < file_contents.len() => stripped_file_contents,
+        _ => file_contents.to_vec(),
+    };
+}
 
-            // println!("Minifying {}", file.to_str().expect("Failed to convert file to string"));
+//------------------------------------------------------------//
 
-            // if it does not exist, create it
-            fs::create_dir_all(
-                output_file_path.parent().expect("Failed to get parent directory")
-            ).expect("Failed to create parent directory");
+/**
+ * A stage that runs every file of one file type through `process_contents` on its own.
+ */
+struct FileStage {
+    name: &'static str,
+    // the summary of the stage reads "{verb} {count} {file_type} files"
+    verb: &'static str,
+    file_type: &'static str,
+    is_stage_file: fn(&path::Path) -> bool,
+    process_contents: fn(&[u8]) -> Vec<u8>,
+    in_parallel: bool,
+}
 
-            fs::write(output_file_path, minified_file_contents).expect("Failed to write file");
-        }
-    );
+// in the order they run, after the stages that generate files
+const FILE_STAGES: [FileStage; 5] = [
+    FileStage {
+        name: "json",
+        verb: "Minified",
+        file_type: "json-like",
+        is_stage_file: is_json_like_file,
+        process_contents: minify_json,
+        in_parallel: true,
+    },
+    FileStage {
+        name: "yaml",
+        verb: "Minified",
+        file_type: "yaml-like",
+        is_stage_file: is_yaml_like_file,
+        process_contents: minify_yaml,
+        in_parallel: true,
+    },
+    FileStage {
+        name: "shaders",
+        verb: "Minified",
+        file_type: "open_gl_sl-like",
+        is_stage_file: is_open_gl_sl_like_file,
+        process_contents: minify_open_gl_sl,
+        in_parallel: true,
+    },
+    FileStage {
+        name: "png",
+        verb: "Compressed",
+        file_type: "png-like",
+        is_stage_file: is_png_like_file,
+        process_contents: compress_png,
+        // this cannot run in parallel because `oxipng` can spawn too many threads and lock up the master process
+        in_parallel: false,
+    },
+    FileStage {
+        name: "ogg",
+        verb: "Stripped",
+        file_type: "ogg-like",
+        is_stage_file: is_ogg_like_file,
+        process_contents: strip_ogg,
+        in_parallel: true,
+    },
+];
 
-    let operation_end = operation_start.elapsed();
+/**
+ * Runs a single file through the stage matching its file type, or copies it as-is.
+ */
+fn process_file(
+    file: &path::Path,
+    output_file_path: &path::Path,
+) {
+    // if it does not exist, create it
+    fs::create_dir_all(
+        output_file_path.parent().expect("Failed to get parent directory")
+    ).expect("Failed to create parent directory");
 
-    println!("Minified {} json-like files in {:.2?}", json_like_files.len(), operation_end);
-}
+    let Some(file_stage) = FILE_STAGES.iter().find(|file_stage| return (file_stage.is_stage_file)(file)) else {
+        fs::copy(file, output_file_path).expect("Failed to copy file");
+        return;
+    };
 
-fn minify_yaml_files(
-    input_dir: &path::Path,
-    output_dir: &path::Path,
-) {
-    let traversed_items = traverse_dir(
-        &path::PathBuf::from(&input_dir),
-        &TraverseDirLookFor::Files,
-        None,
-    );
+    let file_contents = fs::read(file).expect("Failed to read file");
 
-    let yaml_like_files = traversed_items.par_iter().filter(
-        // check if the file ends with `.yml` or `.yaml`
-        |file| {
-            let s = file.to_str().expect("Failed to convert file to string");
-            return s.ends_with(".yml") || s.ends_with(".yaml");
-        }
-    ).collect::<Vec<&path::PathBuf>>();
+    fs::write(output_file_path, (file_stage.process_contents)(&file_contents)).expect("Failed to write file");
+}
+
+/**
+ * Runs the pack files of a stage through it, replacing their contents with the processed ones.
+ */
+fn run_stage(
+    file_stage: &FileStage,
+    pack_files: &mut PackFiles,
+) -> StageResult {
+    let mut files = pack_files.iter_mut().filter(
+        |(pack_path, _)| return (file_stage.is_stage_file)(path::Path::new(pack_path))
+    ).collect::<Vec<(&String, &mut Vec<u8>)>>();
 
     let operation_start = std::time::Instant::now();
 
-    yaml_like_files.par_iter().for_each(
-        |file| {
-            if file.is_dir() {
-                return;
-            }
+    let stage_progress = progress::StageProgress::new(
+        file_stage.name,
+        files.len(),
+        files.iter().map(|(_, file_contents)| return file_contents.len() as u64).sum(),
+    );
 
-            let file_contents = fs::read_to_string(file).expect("Failed to read file");
+    let process = |(pack_path, file_contents): &mut (&String, &mut Vec<u8>)| {
+        stage_progress.start_file(pack_path);
 
-            let parsed_yaml_as_json = serde_yaml::from_str::<serde_json::Value>(&file_contents).expect("Failed to parse yaml");
+        let size_before = file_contents.len() as u64;
+        let file_operation_start = std::time::Instant::now();
 
-            let minified_file_contents = serde_json::to_string(&parsed_yaml_as_json).expect("Failed to convert yaml to json");
+        **file_contents = (file_stage.process_contents)(file_contents);
 
-            let relative_file_path_from_input_dir = file
-                .strip_prefix(input_dir).expect("Failed to strip prefix")
-                .to_str().expect("Failed to convert relative file path to string");
+        let processed_file = ProcessedFile {
+            path: path::PathBuf::from(pack_path.as_str()),
+            size_before,
+            size_after: file_contents.len() as u64,
+            duration: file_operation_start.elapsed(),
+        };
 
-            let output_file_path = output_dir.join(relative_file_path_from_input_dir);
+        log_processed_file(file_stage.name, &processed_file);
+        stage_progress.finish_file(&processed_file);
 
-            // println!("Minifying {}", file.to_str().expect("Failed to convert file to string"));
+        return processed_file;
+    };
 
-            // if it does not exist, create it
-            fs::create_dir_all(
-                output_file_path.parent().expect("Failed to get parent directory")
-            ).expect("Failed to create parent directory");
+    let processed_files = match file_stage.in_parallel {
+        true => files.par_iter_mut().map(process).collect::<Vec<ProcessedFile>>(),
+        false => files.iter_mut().map(process).collect::<Vec<ProcessedFile>>(),
+    };
 
-            fs::write(output_file_path, minified_file_contents).expect("Failed to write file");
-        }
-    );
+    stage_progress.finish();
 
     let operation_end = operation_start.elapsed();
 
-    println!("Minified {} yaml-like files in {:.2?}", yaml_like_files.len(), operation_end);
+    let stage_result = StageResult {
+        name: file_stage.name,
+        files: processed_files,
+        duration: operation_end,
+    };
+
+    log_stage_result(
+        &stage_result,
+        format!("{} {} {} files in {:.2?}", file_stage.verb, files.len(), file_stage.file_type, operation_end).as_str(),
+    );
+
+    return stage_result;
 }
 
-fn minify_open_gl_sl_files(
+/**
+ * Reads the files of a directory into memory by their pack path.
+ */
+fn read_pack_files(
     input_dir: &path::Path,
-    output_dir: &path::Path,
-) {
+    predicate: Option<&dyn Fn(&path::Path) -> bool>,
+) -> PackFiles {
     let traversed_items = traverse_dir(
         &path::PathBuf::from(&input_dir),
         &TraverseDirLookFor::Files,
-        None,
+        predicate,
     );
 
-    let open_gl_sl_like_files = traversed_items.par_iter().filter(
-        // check if the file ends with `.vsh` or `.fsh`
-        |file| {
-            let s = file.to_str().expect("Failed to convert file to string");
-            return s.ends_with(".vsh") || s.ends_with(".fsh");
-        }
-    ).collect::<Vec<&path::PathBuf>>();
-
     let operation_start = std::time::Instant::now();
 
-    open_gl_sl_like_files.par_iter().for_each(
+    let pack_files = traversed_items.par_iter().map(
         |file| {
-            if file.is_dir() {
-                return;
-            }
+            let pack_path = hash::to_pack_path(file.strip_prefix(input_dir).expect("Failed to strip prefix"));
 
-            let file_contents = fs::read_to_string(file).expect("Failed to read file");
+            trace!("Reading {}", pack_path);
 
-            let minified_file_contents: String = file_contents.lines().map(
-                |mut line| {
-                    // remove comments from lines (including comments at the end of lines)
-                    if let Some(index) = line.find("//") {
-                        line = line[..index].trim();
-                    } else {
-                        line = line.trim();
-                    }
+            return (pack_path, fs::read(file).expect("Failed to read file"));
+        }
+    ).collect::<PackFiles>();
 
-                    return line;
-                }
-            ).filter(
-                |line| {
-                    // remove empty lines
-                    if line.is_empty() {
-                        return false;
-                    }
-
-                    return true;
-                }
-            ).collect::<Vec<&str>>().join("\n");
+    let operation_end = operation_start.elapsed();
 
-            let relative_file_path_from_input_dir = file
-                .strip_prefix(input_dir).expect("Failed to strip prefix")
-                .to_str().expect("Failed to convert relative file path to string");
+    logging::stage_finished(
+        "read",
+        serde_json::json!({
+            "files": pack_files.len(),
+            "duration_ms": operation_end.as_secs_f64() * 1000.0,
+        }),
+        format!("Read {} files in {:.2?}", pack_files.len(), operation_end).as_str(),
+    );
 
-            let output_file_path = output_dir.join(relative_file_path_from_input_dir);
+    return pack_files;
+}
 
-            // println!("Minifying {}", file.to_str().expect("Failed to convert file to string"));
+/**
+ * Writes the pack files into a directory.
+ */
+fn write_pack_files(
+    pack_files: &PackFiles,
+    output_dir: &path::Path,
+) {
+    pack_files.par_iter().for_each(
+        |(pack_path, file_contents)| {
+            let output_file_path = output_dir.join(pack_path);
 
             // if it does not exist, create it
             fs::create_dir_all(
                 output_file_path.parent().expect("Failed to get parent directory")
             ).expect("Failed to create parent directory");
 
-            fs::write(output_file_path, minified_file_contents).expect("Failed to write file");
+            trace!("Writing {}", pack_path);
+
+            fs::write(output_file_path, file_contents).expect("Failed to write file");
         }
     );
-
-    let operation_end = operation_start.elapsed();
-
-    println!("Minified {} open_gl_sl-like files in {:.2?}", open_gl_sl_like_files.len(), operation_end);
 }
 
 /**
- * Compresses all png-like files in a directory and outputs them to another directory.
+ * Zips up the pack files in memory, returning the zip file and its hashes.
  */
-fn compress_png_files(
-    input_dir: &path::Path,
-    output_dir: &path::Path,
-) {
-    let traversed_items = traverse_dir(
-        &path::PathBuf::from(&input_dir),
-        &TraverseDirLookFor::Files,
-        None,
-    );
-
-    let png_like_files = traversed_items.par_iter().filter(
-        // check if the file ends with `.png`
-        |file| {
-            let s = file.to_str().expect("Failed to convert file to string");
-            return s.ends_with(".png");
-        }
-    ).collect::<Vec<&path::PathBuf>>();
+fn zip_pack_files(
+    pack_files: &PackFiles,
+) -> (Vec<u8>, hash::Hashes) {
+    let mut zip_writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
 
     let operation_start = std::time::Instant::now();
 
-    // this cannot be `par_iter` because `oxipng` can spawn too many threads and lock up the master process
-    png_like_files.iter().for_each(
-        |file| {
-            if file.is_dir() {
-                return;
-            }
+    for (pack_path, file_contents) in pack_files {
+        trace!("Zipping {}", pack_path);
 
-            let relative_file_path_from_input_dir = file
-                .strip_prefix(input_dir).expect("Failed to strip prefix")
-                .to_str().expect("Failed to convert relative file path to string");
+        zip_writer.start_file(
+            pack_path,
+            zip::write::FileOptions::default()
+        ).expect("Failed to start file");
 
-            let output_file_path = output_dir.join(relative_file_path_from_input_dir);
+        zip_writer.write_all(file_contents).expect("Failed to write file");
+    }
 
-            // if it does not exist, create it
-            fs::create_dir_all(
-                output_file_path.parent().expect("Failed to get parent directory")
-            ).expect("Failed to create parent directory");
+    let zip_contents = zip_writer.finish().expect("Failed to finish zip").into_inner();
 
-            // println!("Optimizing {}", file.to_str().expect("Failed to convert file to string"));
+    let operation_end = operation_start.elapsed();
 
-            oxipng::optimize(
-                &oxipng::InFile::Path(file.to_path_buf()),
-                &oxipng::OutFile::Path(Some(output_file_path.to_path_buf())),
-                &oxipng::Options::max_compression(),
-            ).expect("Failed to optimize png");
-        }
+    let zip_hashes = hash::hash_reader(&mut zip_contents.as_slice());
+
+    logging::stage_finished(
+        "zip",
+        serde_json::json!({
+            "files": pack_files.len(),
+            "size_after": zip_hashes.size,
+            "sha1": zip_hashes.sha1,
+            "sha256": zip_hashes.sha256,
+            "duration_ms": operation_end.as_secs_f64() * 1000.0,
+        }),
+        format!("Zipped {} files in {:.2?}", pack_files.len(), operation_end).as_str(),
     );
 
-    let operation_end = operation_start.elapsed();
+    info!("Zip file SHA-1 hash: {}", zip_hashes.sha1);
+    info!("Zip file SHA-256 hash: {}", zip_hashes.sha256);
 
-    println!("Compressed {} png-like files in {:.2?}", png_like_files.len(), operation_end);
+    return (zip_contents, zip_hashes);
 }
 
 /**
@@ -438,63 +666,104 @@ fn compress_png_files(
 fn zip_dir(
     input_dir: &path::Path,
     output_zip_file_path: &path::Path,
-) {
-    let zip_file = std::fs::File::create(output_zip_file_path).expect("Failed to create zip file");
-    let mut zip_writer = zip::ZipWriter::new(zip_file);
+) -> hash::Hashes {
+    let pack_files = read_pack_files(input_dir, Some(&|file| return file != output_zip_file_path));
 
-    let traversed_items = traverse_dir(
-        &path::PathBuf::from(&input_dir),
-        &TraverseDirLookFor::All,
-        Some(&|file| return file != output_zip_file_path)
-    );
-
-    let operation_start = std::time::Instant::now();
+    let (zip_contents, zip_hashes) = zip_pack_files(&pack_files);
 
-    traversed_items.iter().for_each(
-        |item| {
-            if !item.is_file() {
-                return;
-            }
-
-            let relative_file_path_from_input_dir = item
-                .strip_prefix(input_dir).expect("Failed to strip prefix")
-                .to_str().expect("Failed to convert relative file path to string");
+    fs::write(output_zip_file_path, zip_contents).expect("Failed to write zip file");
 
-            // println!("Zipping {}", file.to_str().expect("Failed to convert file to string"));
+    return zip_hashes;
+}
 
-            zip_writer.start_file(
-                relative_file_path_from_input_dir,
-                zip::write::FileOptions::default()
-            ).expect("Failed to start file");
+/**
+ * Exits the program if the input and output directories cannot be used together.
+ */
+fn validate_input_and_output_dirs(
+    input_dir: &path::Path,
+    output_dir: &path::Path,
+) {
+    if !input_dir.is_dir() && !source::is_zip_file(input_dir) {
+        fail_program("Input directory does not exist or is not a directory or zip file");
+    }
 
-            let file_contents = fs::read(item).expect("Failed to read file");
-            zip_writer.write_all(&file_contents).expect("Failed to write file");
-        }
-    );
+    if !output_dir.exists() || !output_dir.is_dir() {
+        fail_program("Output directory does not exist");
+    }
 
-    zip_writer.finish().expect("Failed to finish zip");
+    // ensure that input_dir is not the same as output_dir
+    if input_dir == output_dir {
+        fail_program("Input directory is the same as output directory");
+    }
 
-    let operation_end = operation_start.elapsed();
+    // ensure output_dir is not a subdirectory or a descendant of input_dir
+    if output_dir.starts_with(input_dir) {
+        fail_program("Output directory is a subdirectory or a descendant of input directory");
+    }
+}
 
-    println!("Zipped {} files in {:.2?}", traversed_items.len(), operation_end);
+/**
+ * Runs every optimization stage on the pack files in memory.
+ *
+ * `pruned_lang_keys` are the keys left out of lang files by their pack path (see `lang::find_fallback_duplicates`).
+ * `previous_code_points` are the glyph code points of the glyph map, if one is written (see `bitmap_font::read_glyph_map`).
+ * Returns the result of each stage in the order they ran.
+ */
+fn process_pack_files(
+    pack_files: &mut PackFiles,
+    pruned_lang_keys: &collections::HashMap<String, Vec<String>>,
+    previous_code_points: Option<&bitmap_font::AssignedCodePoints>,
+) -> Vec<StageResult> {
+    let mut stage_results = vec![
+        // generated fonts and sound events are minified and compressed by the stages after it
+        bitmap_font::generate_bitmap_fonts(pack_files, previous_code_points),
+        sounds::generate_sound_events(pack_files),
+        lang::prune_fallback_duplicates(pack_files, pruned_lang_keys),
+    ];
+
+    stage_results.extend(
+        FILE_STAGES.iter().map(|file_stage| return run_stage(file_stage, pack_files))
+    );
 
-    let mut sha1_hasher = Sha1::new();
-    let zip_file = std::fs::File::open(output_zip_file_path).expect("Failed to open zip file");
+    return stage_results;
+}
 
-    loop {
-        let mut buffer = [0; 1024];
-        let bytes_read = io::Read::read(&mut &zip_file, &mut buffer).expect("Failed to read zip file");
+/**
+ * Reads the pack files of the input directory, leaving out `pruned_paths` (see `unused::find_unused_assets`).
+ */
+fn read_input_pack_files(
+    input_dir: &path::Path,
+    pruned_paths: &collections::HashSet<String>,
+) -> PackFiles {
+    return read_pack_files(
+        input_dir,
+        Some(
+            &|file| return is_pack_file(file)
+                && !pruned_paths.contains(&hash::to_pack_path(file.strip_prefix(input_dir).expect("Failed to strip prefix")))
+        )
+    );
+}
 
-        if bytes_read == 0 {
-            break;
-        }
+/**
+ * Runs every optimization stage on the pack files of the input directory and writes them into the working directory.
+ *
+ * See `read_input_pack_files` and `process_pack_files` for the other arguments.
+ * Returns the result of each stage in the order they ran.
+ */
+fn process_pack(
+    input_dir: &path::Path,
+    temp_dir_path: &path::Path,
+    pruned_paths: &collections::HashSet<String>,
+    pruned_lang_keys: &collections::HashMap<String, Vec<String>>,
+    previous_code_points: Option<&bitmap_font::AssignedCodePoints>,
+) -> Vec<StageResult> {
+    let mut pack_files = read_input_pack_files(input_dir, pruned_paths);
 
-        sha1_hasher.update(&buffer[..bytes_read]);
-    }
+    let stage_results = process_pack_files(&mut pack_files, pruned_lang_keys, previous_code_points);
 
-    let hash = sha1_hasher.digest().to_string();
+    write_pack_files(&pack_files, temp_dir_path);
 
-    println!("Zip file SHA-1 hash: {}", hash);
+    return stage_results;
 }
 
 //------------------------------------------------------------//
@@ -502,49 +771,54 @@ fn zip_dir(
 fn get_command_line_args() -> clap::ArgMatches {
     let matched_args = clap::Command::new("ms-rpo")
         .author("MineSuperior")
+        .subcommand_negates_reqs(true)
         .arg(
-            clap::Arg::new("input_path")
-                .short('i')
-                .long("input-path")
-                .help("The directory to read from")
-                .value_name("INPUT_PATH")
-                .value_hint(clap::ValueHint::DirPath)
-                .value_parser(clap::value_parser!(path::PathBuf))
-                .required(true)
+            clap::Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .visible_alias("no-confirm")
+                .help("Run without asking to confirm the plan (required without a terminal)")
+                .action(clap::ArgAction::SetTrue)
+                .global(true)
         )
         .arg(
-            clap::Arg::new("output_path")
-                .short('o')
-                .long("output-path")
-                .help("The directory to output to")
-                .value_name("OUTPUT_PATH")
-                .value_hint(clap::ValueHint::DirPath)
-                .value_parser(clap::value_parser!(path::PathBuf))
-                .required(true)
+            clap::Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Only print warnings and errors")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("verbose")
+                .global(true)
         )
         .arg(
-            clap::Arg::new("zip")
-                .short('z')
-                .long("zip")
-                .help("Compresses the output files into a .zip file with an optionally specified name")
-                .value_name("ZIP_NAME")
-                .value_hint(clap::ValueHint::FilePath)
-                .value_parser(clap::value_parser!(path::PathBuf))
-                .default_missing_value("output.zip")
-                .num_args(0..=1)
-                .required(false)
+            clap::Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Print more details (-v), or every processed file (-vv)")
+                .action(clap::ArgAction::Count)
+                .global(true)
         )
         .arg(
-            clap::Arg::new("no_confirm")
-                .long("no-confirm")
-                .help("Bypass confirmation prompts")
-                .action(clap::ArgAction::Set)
-                .default_missing_value("false")
-                .default_value("true")
-                .value_parser(clap::value_parser!(bool))
-                .num_args(0)
-                .required(false)
+            clap::Arg::new("log_format")
+                .long("log-format")
+                .help("Print human readable messages, or one json event per line (per file and stage)")
+                .value_name("FORMAT")
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .global(true)
         )
+        .args_conflicts_with_subcommands(true)
+        .args(optimize::get_args())
+        .subcommand(optimize::get_command())
+        .subcommand(validate::get_command())
+        .subcommand(inspect::get_command())
+        .subcommand(hash::get_command())
+        .subcommand(diff::get_command())
+        .subcommand(verify::get_command())
+        .subcommand(custom_model_data::get_command())
+        .subcommand(lang::get_command())
+        .subcommand(serve::get_command())
+        .subcommand(watch::get_command())
         .get_matches();
 
     return matched_args;
@@ -555,196 +829,50 @@ fn get_command_line_args() -> clap::ArgMatches {
 fn main() {
     let matched_args = get_command_line_args();
 
-    println!(""); // empty line
-
-    let input_dir = matched_args.get_one::<path::PathBuf>("input_path").expect("Failed to get input_path");
-    println!("input_dir: {}", input_dir.to_str().expect("Failed to convert input_dir to string"));
+    let log_level = match (matched_args.get_flag("quiet"), matched_args.get_count("verbose")) {
+        (true, _) => logging::Level::Warn,
+        (false, 0) => logging::Level::Info,
+        (false, 1) => logging::Level::Debug,
+        (false, _) => logging::Level::Trace,
+    };
 
-    let output_dir = matched_args.get_one::<path::PathBuf>("output_path").expect("Failed to get output_path");
-    println!("output_dir: {}", output_dir.to_str().expect("Failed to convert output_dir to string"));
+    logging::init(log_level, matched_args.get_one::<String>("log_format").map(|log_format| return log_format == "json").unwrap_or(false));
+    progress::init();
 
-    let zip_name = matched_args.get_one::<path::PathBuf>("zip");
-    match zip_name {
-        Some(zip_name) => {
-            println!("zip_name: {}", zip_name.to_str().expect("Failed to convert zip_name to string"));
+    match matched_args.subcommand() {
+        Some(("optimize", optimize_args)) => {
+            optimize::run_command(optimize_args);
         },
-        None => {
-            println!("zip_name: None");
+        Some(("validate", validate_args)) => {
+            validate::run_command(validate_args);
         },
-    }
-
-    let should_ask_user_to_confirm = !matched_args.contains_id("no_confirm");
-    println!("should_ask_user_to_confirm: {}", should_ask_user_to_confirm);
-
-    println!(""); // empty line
-
-    if !input_dir.exists() || !input_dir.is_dir() {
-        exit_program("Input directory does not exist or is not a directory");
-    }
-
-    if !output_dir.exists() || !output_dir.is_dir() {
-        exit_program("Output directory does not exist");
-    }
-
-    // ensure that input_dir is not the same as output_dir
-    if input_dir == output_dir {
-        exit_program("Input directory is the same as output directory");
-    }
-
-    // ensure output_dir is not a subdirectory or a descendant of input_dir
-    if output_dir.starts_with(input_dir) {
-        exit_program("Output directory is a subdirectory or a descendant of input directory");
-    }
-
-    // ensure that the output directory is empty
-    let output_dir_contains_items = output_dir.read_dir().expect("Failed to read output directory").next().is_some();
-    if output_dir_contains_items {
-        let user_confirmed = ask_user_to_confirm(
-            should_ask_user_to_confirm,
-            format!(
-                "Output directory is not empty.\nContinuing will delete all files in:\n{}",
-                output_dir.to_str().expect("Failed to convert output_dir to string")
-            ).as_str()
-        );
-
-        if !user_confirmed {
-            exit_program("User did not confirm to continue");
-        }
-
-        empty_dir(output_dir);
-    }
-
-    // create a temporary directory to work on the files inside of the programs running directory
-    let temp_dir = TempDir::new("ms-rpo").expect("Failed to create temporary directory");
-    let temp_dir_path = temp_dir.path();
-
-    {
-        let user_confirmed = ask_user_to_confirm(
-            should_ask_user_to_confirm,
-            format!(
-                "Clone all files in {} into {}",
-                input_dir.to_str().expect("Failed to convert input_dir to string"),
-                temp_dir_path.to_str().expect("Failed to convert temp_dir to string")
-            ).as_str()
-        );
-
-        if !user_confirmed {
-            exit_program("User did not confirm to continue");
-        }
-
-        // clone all input_dir files into the temporary directory
-        clone_dir(
-            input_dir,
-            temp_dir_path,
-            // remove `.md` and `.old` files
-            // TODO: make this configurable
-            Some(
-                &|file| {
-                    let s = file.to_str().expect("Failed to convert file to string");
-                    return !s.ends_with(".md") && !s.ends_with(".old");
-                }
-            )
-        );
-    }
-
-    {
-        let user_confirmed = ask_user_to_confirm(
-            should_ask_user_to_confirm,
-            "Minify all .json and .mcmeta files"
-        );
-
-        if !user_confirmed {
-            exit_program("User did not confirm to continue");
-        }
-
-        // modify files in-place (output_dir is the same as input_dir)
-        minify_json_files(temp_dir_path, temp_dir_path);
-    }
-
-    {
-        let user_confirmed = ask_user_to_confirm(
-            should_ask_user_to_confirm,
-            "Minify all .yml and .yaml files"
-        );
-
-        if !user_confirmed {
-            exit_program("User did not confirm to continue");
-        }
-
-        // modify files in-place (output_dir is the same as input_dir)
-        minify_yaml_files(temp_dir_path, temp_dir_path);
-    }
-
-    {
-        let user_confirmed = ask_user_to_confirm(
-            should_ask_user_to_confirm,
-            "Minify all .vsh and .fsh files"
-        );
-
-        if !user_confirmed {
-            exit_program("User did not confirm to continue");
-        }
-
-        // modify files in-place (output_dir is the same as input_dir)
-        minify_open_gl_sl_files(temp_dir_path, temp_dir_path);
-    }
-
-    {
-        let user_confirmed = ask_user_to_confirm(
-            should_ask_user_to_confirm,
-            "Compress all .png files"
-        );
-
-        if !user_confirmed {
-            exit_program("User did not confirm to continue");
-        }
-
-        // modify files in-place (output_dir is the same as input_dir)
-        compress_png_files(temp_dir_path, temp_dir_path);
-    }
-
-    match zip_name {
-        Some(zip_name) => {
-            let zip_file_path = output_dir.join(zip_name);
-
-            let user_confirmed = ask_user_to_confirm(
-                should_ask_user_to_confirm,
-                format!(
-                    "Zip all files and output to {}",
-                    &zip_file_path.to_str().expect("Failed to convert zip_file_path to string")
-                ).as_str()
-            );
-
-            if !user_confirmed {
-                exit_program("User did not confirm to continue");
-            }
-
-            zip_dir(temp_dir_path, &zip_file_path);
+        Some(("inspect", inspect_args)) => {
+            inspect::run_command(inspect_args);
         },
-        None => {
-            // copy all files from the temporary directory to the output directory
-            clone_dir(temp_dir_path, output_dir, None);
+        Some(("hash", hash_args)) => {
+            hash::run_command(hash_args);
+        },
+        Some(("diff", diff_args)) => {
+            diff::run_command(diff_args);
+        },
+        Some(("verify", verify_args)) => {
+            verify::run_command(verify_args);
+        },
+        Some(("registry", registry_args)) => {
+            custom_model_data::run_command(registry_args);
+        },
+        Some(("lang", lang_args)) => {
+            lang::run_command(lang_args);
+        },
+        Some(("serve", serve_args)) => {
+            serve::run_command(serve_args);
+        },
+        Some(("watch", watch_args)) => {
+            watch::run_command(watch_args);
+        },
+        _ => {
+            // without a subcommand, optimize like before subcommands existed
+            optimize::run_command(&matched_args);
         },
     }
-
-    {
-        let user_confirmed = ask_user_to_confirm(
-            should_ask_user_to_confirm,
-            format!(
-                "Delete temporary directory {}",
-                temp_dir_path.to_str().expect("Failed to convert temp_dir to string")
-            ).as_str()
-        );
-
-        if !user_confirmed {
-            exit_program("User did not confirm to continue");
-        }
-
-        println!("Deleting temporary directory {}...", temp_dir_path.to_str().expect("Failed to convert temp_dir to string"));
-        temp_dir.close().expect("Failed to remove temporary directory");
-    }
-
-    println!(""); // empty line
-
-    println!("Exiting...");
 }
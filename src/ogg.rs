@@ -0,0 +1,551 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::fs;
+use std::io::Read;
+use std::path;
+
+//------------------------------------------------------------//
+
+// the magic bytes every ogg page starts with
+const OGG_PAGE_MAGIC: &[u8; 4] = b"OggS";
+
+// the size of an ogg page header before its segment table
+const OGG_PAGE_HEADER_SIZE: usize = 27;
+
+// the bytes the vorbis identification header starts with, its packet type and codec name
+const VORBIS_IDENTIFICATION_MAGIC: &[u8; 7] = b"\x01vorbis";
+
+// the bytes the vorbis comment header starts with
+const VORBIS_COMMENT_MAGIC: &[u8; 7] = b"\x03vorbis";
+
+// the bytes the vorbis setup header starts with
+const VORBIS_SETUP_MAGIC: &[u8; 7] = b"\x05vorbis";
+
+// the header type flags of an ogg page
+const CONTINUED_PACKET_FLAG: u8 = 0x01;
+const BEGINNING_OF_STREAM_FLAG: u8 = 0x02;
+
+// the granule position of a page on which no packet ends
+const NO_GRANULE_POSITION: u64 = u64::MAX;
+
+// the most segments a page can have, each of up to 255 bytes
+const MAX_PAGE_SEGMENTS: usize = 255;
+
+// the crc-32 lookup table of ogg pages (polynomial 0x04c11db7, not reflected)
+const OGG_CRC_TABLE: [u32; 256] = build_crc_table();
+
+//------------------------------------------------------------//
+
+/**
+ * A page of an ogg file, without its checksum, which is worked out again when it is written.
+ */
+#[derive(PartialEq)]
+pub struct OggPage {
+    header_type: u8,
+    granule_position: u64,
+    serial_number: u32,
+    // the lacing values of the segments of the page, a value under 255 ends a packet
+    segment_table: Vec<u8>,
+    body: Vec<u8>,
+}
+
+/**
+ * The logical vorbis stream of an ogg file, split into the three header packets and the pages of audio after them.
+ */
+pub struct VorbisStream {
+    serial_number: u32,
+    identification_header: Vec<u8>,
+    comment_header: Vec<u8>,
+    setup_header: Vec<u8>,
+    audio_pages: Vec<OggPage>,
+}
+
+//------------------------------------------------------------//
+
+const fn build_crc_table() -> [u32; 256] {
+    let mut crc_table = [0; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let mut crc = (index as u32) << 24;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 0x80000000 != 0 { (crc << 1) ^ 0x04c11db7 } else { crc << 1 };
+            bit += 1;
+        }
+
+        crc_table[index] = crc;
+        index += 1;
+    }
+
+    return crc_table;
+}
+
+fn crc32(
+    data: &[u8],
+) -> u32 {
+    return data.iter().fold(
+        0,
+        |crc, byte| return (crc << 8) ^ OGG_CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    );
+}
+
+/**
+ * Splits an ogg file into its pages, checking the checksum of each.
+ */
+fn read_pages(
+    data: &[u8],
+) -> Result<Vec<OggPage>, String> {
+    let mut pages = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let page = &data[offset..];
+
+        if !page.starts_with(OGG_PAGE_MAGIC) || page.len() < OGG_PAGE_HEADER_SIZE {
+            return Err(format!("Invalid ogg: no page at byte {}", offset));
+        }
+
+        let segment_count = page[OGG_PAGE_HEADER_SIZE - 1] as usize;
+        let header_size = OGG_PAGE_HEADER_SIZE + segment_count;
+        let segment_table = page.get(OGG_PAGE_HEADER_SIZE..header_size).ok_or(format!("Invalid ogg: the page at byte {} is cut off", offset))?;
+        let page_size = header_size + segment_table.iter().map(|lacing_value| return *lacing_value as usize).sum::<usize>();
+        let page = page.get(..page_size).ok_or(format!("Invalid ogg: the page at byte {} is cut off", offset))?;
+
+        let mut unchecked_page = page.to_vec();
+        unchecked_page[22..26].fill(0);
+
+        if crc32(&unchecked_page).to_le_bytes() != page[22..26] {
+            return Err(format!("Invalid ogg: the checksum of the page at byte {} does not match", offset));
+        }
+
+        pages.push(
+            OggPage {
+                header_type: page[5],
+                granule_position: u64::from_le_bytes(page[6..14].try_into().expect("Failed to read granule position")),
+                serial_number: u32::from_le_bytes(page[14..18].try_into().expect("Failed to read serial number")),
+                segment_table: segment_table.to_vec(),
+                body: page[header_size..].to_vec(),
+            }
+        );
+
+        offset += page_size;
+    }
+
+    return Ok(pages);
+}
+
+fn write_page(
+    page: &OggPage,
+    sequence_number: u32,
+    data: &mut Vec<u8>,
+) {
+    let page_start = data.len();
+
+    data.extend_from_slice(OGG_PAGE_MAGIC);
+    // the version of the ogg format, which is always 0
+    data.push(0);
+    data.push(page.header_type);
+    data.extend_from_slice(&page.granule_position.to_le_bytes());
+    data.extend_from_slice(&page.serial_number.to_le_bytes());
+    data.extend_from_slice(&sequence_number.to_le_bytes());
+    // the checksum, filled in once the page is complete
+    data.extend_from_slice(&[0; 4]);
+    data.push(page.segment_table.len() as u8);
+    data.extend_from_slice(&page.segment_table);
+    data.extend_from_slice(&page.body);
+
+    let crc = crc32(&data[page_start..]);
+    data[page_start + 22..page_start + 26].copy_from_slice(&crc.to_le_bytes());
+}
+
+/**
+ * Lays header packets out on pages, a packet spanning as many pages as it needs.
+ */
+fn paginate_headers(
+    serial_number: u32,
+    packets: &[&[u8]],
+    header_type: u8,
+) -> Vec<OggPage> {
+    // the segments of all packets, each with the packet bytes it holds
+    let mut segments = Vec::<(u8, &[u8])>::new();
+
+    for packet in packets {
+        let mut chunks = packet.chunks(255).map(|chunk| return (chunk.len() as u8, chunk)).collect::<Vec<(u8, &[u8])>>();
+
+        // a packet that is a multiple of 255 bytes long ends with an empty segment
+        if packet.len() % 255 == 0 {
+            chunks.push((0, &[]));
+        }
+
+        segments.extend(chunks);
+    }
+
+    let mut pages = Vec::new();
+    let mut is_continued = false;
+
+    for page_segments in segments.chunks(MAX_PAGE_SEGMENTS) {
+        let segment_table = page_segments.iter().map(|(lacing_value, _)| return *lacing_value).collect::<Vec<u8>>();
+        let is_packet_ended = segment_table.iter().any(|lacing_value| return *lacing_value < 255);
+
+        pages.push(
+            OggPage {
+                header_type: header_type | if is_continued { CONTINUED_PACKET_FLAG } else { 0 },
+                // header pages are before the first sample
+                granule_position: if is_packet_ended { 0 } else { NO_GRANULE_POSITION },
+                serial_number,
+                segment_table,
+                body: page_segments.iter().flat_map(|(_, chunk)| return chunk.iter().copied()).collect(),
+            }
+        );
+
+        is_continued = segment_table_ends_mid_packet(&pages[pages.len() - 1].segment_table);
+    }
+
+    return pages;
+}
+
+fn segment_table_ends_mid_packet(
+    segment_table: &[u8],
+) -> bool {
+    return segment_table.last().is_some_and(|lacing_value| return *lacing_value == 255);
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Reads the vorbis stream of an ogg file.
+ *
+ * Pages of other logical streams (e.g. a skeleton track) are left out, but a file with more than one vorbis stream
+ * is an error, since the streams after the first could be played after it.
+ */
+pub fn read_vorbis_stream(
+    data: &[u8],
+) -> Result<VorbisStream, String> {
+    let pages = read_pages(data)?;
+
+    let first_page = pages.first().ok_or("Invalid ogg: the file is empty".to_string())?;
+    let serial_number = first_page.serial_number;
+
+    let vorbis_stream_count = pages.iter().filter(
+        |page| return page.header_type & BEGINNING_OF_STREAM_FLAG != 0 && page.body.starts_with(VORBIS_IDENTIFICATION_MAGIC)
+    ).count();
+
+    if vorbis_stream_count != 1 || !first_page.body.starts_with(VORBIS_IDENTIFICATION_MAGIC) {
+        return Err(format!("Expected one vorbis stream at the start of the file, found {}", vorbis_stream_count));
+    }
+
+    let mut stream_pages = pages.into_iter().filter(|page| return page.serial_number == serial_number);
+
+    let mut header_packets = Vec::<Vec<u8>>::new();
+    let mut packet = Vec::new();
+
+    while header_packets.len() < 3 {
+        let page = stream_pages.next().ok_or("Invalid ogg: the vorbis headers are cut off".to_string())?;
+        let mut page_offset = 0;
+
+        for (index, lacing_value) in page.segment_table.iter().enumerate() {
+            packet.extend_from_slice(&page.body[page_offset..page_offset + *lacing_value as usize]);
+            page_offset += *lacing_value as usize;
+
+            if *lacing_value < 255 {
+                header_packets.push(std::mem::take(&mut packet));
+
+                // the audio has to start on a page of its own
+                if header_packets.len() == 3 && index != page.segment_table.len() - 1 {
+                    return Err("Invalid ogg: the audio starts on the page of the setup header".to_string());
+                }
+            }
+        }
+    }
+
+    let [identification_header, comment_header, setup_header] = <[Vec<u8>; 3]>::try_from(header_packets).expect("Failed to split header packets");
+
+    if !comment_header.starts_with(VORBIS_COMMENT_MAGIC) || !setup_header.starts_with(VORBIS_SETUP_MAGIC) {
+        return Err("Invalid ogg: the vorbis headers are not in order".to_string());
+    }
+
+    return Ok(
+        VorbisStream {
+            serial_number,
+            identification_header,
+            comment_header,
+            setup_header,
+            audio_pages: stream_pages.collect(),
+        }
+    );
+}
+
+/**
+ * Whether two vorbis streams decode to the same audio, which they do when everything but their comments is the same.
+ */
+pub fn is_same_audio(
+    stream: &VorbisStream,
+    other_stream: &VorbisStream,
+) -> bool {
+    return stream.identification_header == other_stream.identification_header
+        && stream.setup_header == other_stream.setup_header
+        && stream.audio_pages == other_stream.audio_pages;
+}
+
+/**
+ * Rewrites an ogg vorbis file with an empty comment header and only its vorbis stream, without re-encoding the audio.
+ *
+ * The comment header keeps the vendor string, which every vorbis stream has to have. The audio pages are copied
+ * as they are, only their sequence numbers and checksums change.
+ */
+pub fn strip_comments(
+    data: &[u8],
+) -> Result<Vec<u8>, String> {
+    let stream = read_vorbis_stream(data)?;
+
+    let vendor_length = stream.comment_header.get(7..11).map(
+        |vendor_length| return u32::from_le_bytes(vendor_length.try_into().expect("Failed to read vendor length")) as usize
+    ).ok_or("Invalid ogg: the comment header is cut off".to_string())?;
+
+    let vendor = stream.comment_header.get(11..11 + vendor_length).ok_or("Invalid ogg: the comment header is cut off".to_string())?;
+
+    let mut comment_header = VORBIS_COMMENT_MAGIC.to_vec();
+    comment_header.extend_from_slice(&(vendor_length as u32).to_le_bytes());
+    comment_header.extend_from_slice(vendor);
+    // no user comments, then the framing bit
+    comment_header.extend_from_slice(&0u32.to_le_bytes());
+    comment_header.push(1);
+
+    let mut pages = paginate_headers(stream.serial_number, &[&stream.identification_header], BEGINNING_OF_STREAM_FLAG);
+    pages.extend(paginate_headers(stream.serial_number, &[&comment_header, &stream.setup_header], 0));
+    pages.extend(stream.audio_pages);
+
+    let mut stripped_data = Vec::with_capacity(data.len());
+
+    for (sequence_number, page) in pages.iter().enumerate() {
+        write_page(page, sequence_number as u32, &mut stripped_data);
+    }
+
+    return Ok(stripped_data);
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Reads the number of audio channels of an ogg vorbis file from its identification header,
+ * which is the first packet of the first page.
+ */
+pub fn read_vorbis_channels(
+    file: &path::Path,
+) -> Result<u8, String> {
+    let mut header = Vec::new();
+
+    // the first page has only the identification header, which is 30 bytes
+    fs::File::open(file).map_err(|error| return format!("Failed to read file: {}", error))?
+        .take(512).read_to_end(&mut header).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+    if !header.starts_with(OGG_PAGE_MAGIC) || header.len() < OGG_PAGE_HEADER_SIZE {
+        return Err("Invalid ogg: not an ogg file".to_string());
+    }
+
+    let packet_start = OGG_PAGE_HEADER_SIZE + header[OGG_PAGE_HEADER_SIZE - 1] as usize;
+
+    if !header.get(packet_start..).is_some_and(|packet| return packet.starts_with(VORBIS_IDENTIFICATION_MAGIC)) {
+        return Err("Invalid ogg: not a vorbis stream, Minecraft only plays vorbis".to_string());
+    }
+
+    // the identification header continues with a 4 byte version and then the channels
+    return header.get(packet_start + VORBIS_IDENTIFICATION_MAGIC.len() + 4).copied().ok_or(
+        "Invalid ogg: the vorbis identification header is cut off".to_string()
+    );
+}
+
+//------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a mono vorbis file of silence, with 40 audio packets and a comment header of about 4 kilobytes
+    const TAGGED_OGG: &[u8] = include_bytes!("../tests/fixtures/tagged.ogg");
+
+    // the vendor string of the fixture
+    const TAGGED_OGG_VENDOR: &[u8] = b"Xiph.Org libVorbis I 20200704 (Reducing Environment)";
+
+    fn write_pages(
+        pages: &[OggPage],
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        for (sequence_number, page) in pages.iter().enumerate() {
+            write_page(page, sequence_number as u32, &mut data);
+        }
+
+        return data;
+    }
+
+    fn build_comment_header(
+        vendor: &[u8],
+        comments: &[&[u8]],
+    ) -> Vec<u8> {
+        let mut comment_header = VORBIS_COMMENT_MAGIC.to_vec();
+        comment_header.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        comment_header.extend_from_slice(vendor);
+        comment_header.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+
+        for comment in comments {
+            comment_header.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            comment_header.extend_from_slice(comment);
+        }
+
+        comment_header.push(1);
+
+        return comment_header;
+    }
+
+    /**
+     * Lays the fixture out on pages again, with the given comment header.
+     */
+    fn build_tagged_ogg_pages(
+        comment_header: &[u8],
+    ) -> Vec<OggPage> {
+        let stream = read_vorbis_stream(TAGGED_OGG).expect("Failed to read fixture");
+
+        let mut pages = paginate_headers(stream.serial_number, &[&stream.identification_header], BEGINNING_OF_STREAM_FLAG);
+        pages.extend(paginate_headers(stream.serial_number, &[comment_header, &stream.setup_header], 0));
+        pages.extend(stream.audio_pages);
+
+        return pages;
+    }
+
+    fn read_sequence_numbers(
+        data: &[u8],
+    ) -> Vec<u32> {
+        let mut sequence_numbers = Vec::new();
+        let mut offset = 0;
+
+        for page in read_pages(data).expect("Failed to read pages") {
+            sequence_numbers.push(u32::from_le_bytes(data[offset + 18..offset + 22].try_into().expect("Failed to read sequence number")));
+            offset += OGG_PAGE_HEADER_SIZE + page.segment_table.len() + page.body.len();
+        }
+
+        return sequence_numbers;
+    }
+
+    #[test]
+    fn strip_comments_keeps_audio_of_fixture() {
+        let source_stream = read_vorbis_stream(TAGGED_OGG).expect("Failed to read fixture");
+        assert!(source_stream.comment_header.len() > 4000);
+
+        let stripped_data = strip_comments(TAGGED_OGG).expect("Failed to strip comments");
+        assert!(stripped_data.len() < TAGGED_OGG.len() - 4000);
+
+        let page_count = read_pages(&stripped_data).expect("Failed to read stripped pages").len();
+        assert_eq!(read_sequence_numbers(&stripped_data), (0..page_count as u32).collect::<Vec<u32>>());
+
+        let stripped_stream = read_vorbis_stream(&stripped_data).expect("Failed to read stripped stream");
+        assert!(is_same_audio(&source_stream, &stripped_stream));
+        assert_eq!(stripped_stream.comment_header, build_comment_header(TAGGED_OGG_VENDOR, &[]));
+
+        // stripping a stripped file changes nothing
+        assert_eq!(strip_comments(&stripped_data).expect("Failed to strip comments again"), stripped_data);
+    }
+
+    #[test]
+    fn strip_comments_reads_comment_header_over_several_pages() {
+        // a comment header of exactly 300 segments of 255 bytes, which ends with an empty segment on the second page
+        let padding = vec![b'a'; 300 * 255 - build_comment_header(TAGGED_OGG_VENDOR, &[b""]).len()];
+        let comment_header = build_comment_header(TAGGED_OGG_VENDOR, &[&padding]);
+        assert_eq!(comment_header.len(), 300 * 255);
+
+        let pages = build_tagged_ogg_pages(&comment_header);
+
+        assert_eq!(pages[1].segment_table, vec![255; MAX_PAGE_SEGMENTS]);
+        assert_eq!(pages[1].granule_position, NO_GRANULE_POSITION);
+        assert_eq!(pages[2].header_type, CONTINUED_PACKET_FLAG);
+        assert_eq!(pages[2].segment_table[..46], [vec![255; 45], vec![0]].concat());
+        assert_eq!(pages[2].granule_position, 0);
+
+        let data = write_pages(&pages);
+        let source_stream = read_vorbis_stream(&data).expect("Failed to read stream");
+        assert_eq!(source_stream.comment_header, comment_header);
+
+        let stripped_data = strip_comments(&data).expect("Failed to strip comments");
+        assert_eq!(stripped_data, strip_comments(TAGGED_OGG).expect("Failed to strip comments"));
+
+        let stripped_stream = read_vorbis_stream(&stripped_data).expect("Failed to read stripped stream");
+        assert!(is_same_audio(&source_stream, &stripped_stream));
+    }
+
+    #[test]
+    fn strip_comments_drops_other_logical_streams() {
+        let mut pages = build_tagged_ogg_pages(&build_comment_header(TAGGED_OGG_VENDOR, &[b"TITLE=test"]));
+
+        // a skeleton track, with its first page after the vorbis one and another page among the audio pages
+        let skeleton_page = |header_type| return OggPage {
+            header_type,
+            granule_position: 0,
+            serial_number: 7,
+            segment_table: vec![8],
+            body: b"fishead\0".to_vec(),
+        };
+
+        pages.insert(1, skeleton_page(BEGINNING_OF_STREAM_FLAG));
+        pages.insert(4, skeleton_page(0));
+
+        let data = write_pages(&pages);
+        let stripped_data = strip_comments(&data).expect("Failed to strip comments");
+        assert!(read_pages(&stripped_data).expect("Failed to read stripped pages").iter().all(|page| return page.serial_number != 7));
+        assert_eq!(stripped_data, strip_comments(TAGGED_OGG).expect("Failed to strip comments"));
+
+        // a file that starts with the other stream is not one to rewrite
+        pages.swap(0, 1);
+        assert!(strip_comments(&write_pages(&pages)).is_err());
+    }
+
+    #[test]
+    fn strip_comments_rejects_second_vorbis_stream() {
+        let mut pages = build_tagged_ogg_pages(&build_comment_header(TAGGED_OGG_VENDOR, &[]));
+        let mut chained_pages = build_tagged_ogg_pages(&build_comment_header(TAGGED_OGG_VENDOR, &[]));
+
+        for page in chained_pages.iter_mut() {
+            page.serial_number += 1;
+        }
+
+        pages.extend(chained_pages);
+
+        assert!(read_vorbis_stream(&write_pages(&pages)).is_err());
+        assert!(strip_comments(&write_pages(&pages)).is_err());
+    }
+
+    #[test]
+    fn strip_comments_rejects_broken_files() {
+        assert!(strip_comments(b"").is_err());
+        assert!(strip_comments(b"not an ogg file").is_err());
+
+        // every cut that does not fall between two pages leaves a page cut off
+        let page_ends = read_pages(TAGGED_OGG).expect("Failed to read fixture").iter().scan(
+            0,
+            |offset, page| {
+                *offset += OGG_PAGE_HEADER_SIZE + page.segment_table.len() + page.body.len();
+                return Some(*offset);
+            }
+        ).collect::<Vec<usize>>();
+
+        for length in 0..TAGGED_OGG.len() {
+            if !page_ends.contains(&length) {
+                assert!(strip_comments(&TAGGED_OGG[..length]).is_err(), "Cut at byte {} was accepted", length);
+            }
+        }
+
+        // a file cut after its first page has no comment or setup header
+        assert!(strip_comments(&TAGGED_OGG[..page_ends[0]]).is_err());
+
+        for index in [5, 30, TAGGED_OGG.len() / 2, TAGGED_OGG.len() - 1] {
+            let mut data = TAGGED_OGG.to_vec();
+            data[index] ^= 0x10;
+            assert!(strip_comments(&data).is_err(), "Changed byte {} was accepted", index);
+        }
+    }
+}
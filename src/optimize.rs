@@ -0,0 +1,547 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use tempdir::TempDir; // temporary directories
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::bitmap_font;
+use crate::budget;
+use crate::client_jar;
+use crate::hash;
+use crate::lang;
+use crate::logging::{debug, info, warning};
+use crate::output;
+use crate::progress;
+use crate::report;
+use crate::server_properties;
+use crate::source;
+use crate::unused;
+use crate::target;
+use crate::validate;
+use crate::vanilla;
+use crate::verify;
+
+//------------------------------------------------------------//
+
+/**
+ * The total size of some files of the input pack.
+ */
+fn files_size(
+    input_dir: &path::Path,
+    pack_paths: &[String],
+) -> u64 {
+    return pack_paths.iter().map(
+        |pack_path| return fs::metadata(input_dir.join(pack_path)).expect("Failed to read metadata").len()
+    ).sum();
+}
+
+//------------------------------------------------------------//
+
+/**
+ * The arguments of the `optimize` subcommand, which the root command accepts too.
+ */
+pub fn get_args() -> Vec<clap::Arg> {
+    return vec![
+        clap::Arg::new("input_path")
+            .short('i')
+            .long("input-path")
+            .help("The pack directory or zip file to read from")
+            .value_name("INPUT_PATH")
+            .value_hint(clap::ValueHint::AnyPath)
+            .value_parser(clap::value_parser!(path::PathBuf))
+            .required(true),
+        clap::Arg::new("output_path")
+            .short('o')
+            .long("output-path")
+            .help("The directory to output to")
+            .value_name("OUTPUT_PATH")
+            .value_hint(clap::ValueHint::DirPath)
+            .value_parser(clap::value_parser!(path::PathBuf))
+            .required(true),
+        clap::Arg::new("zip")
+            .short('z')
+            .long("zip")
+            .help("Compresses the output files into a .zip file with an optionally specified name")
+            .value_name("ZIP_NAME")
+            .value_hint(clap::ValueHint::FilePath)
+            .value_parser(clap::value_parser!(path::PathBuf))
+            .default_missing_value("output.zip")
+            .num_args(0..=1)
+            .required(false),
+        clap::Arg::new("server_properties")
+            .long("server-properties")
+            .help("Updates the resource pack settings of this server.properties file to point at the zip file")
+            .value_name("SERVER_PROPERTIES_PATH")
+            .value_hint(clap::ValueHint::FilePath)
+            .value_parser(clap::value_parser!(path::PathBuf))
+            .requires("zip")
+            .requires("public_url")
+            .required(false),
+        clap::Arg::new("public_url")
+            .long("public-url")
+            .help("The public base url the zip file will be hosted at")
+            .value_name("BASE_URL")
+            .value_hint(clap::ValueHint::Url)
+            .requires("server_properties")
+            .required(false),
+        clap::Arg::new("server_properties_dry_run")
+            .long("server-properties-dry-run")
+            .help("Print the changes to server.properties instead of writing them")
+            .action(clap::ArgAction::SetTrue)
+            .requires("server_properties"),
+        clap::Arg::new("dry_run")
+            .long("dry-run")
+            .help("Run every stage in a temporary directory without touching the output directory and print the projected savings")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("report")
+            .long("report")
+            .help("Writes a detailed report of the run, formatted by the file extension (.json, .md or .html)")
+            .value_name("REPORT_PATH")
+            .value_hint(clap::ValueHint::FilePath)
+            .value_parser(clap::value_parser!(path::PathBuf))
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("glyph_map")
+            .long("glyph-map")
+            .help("Writes the characters of the generated font glyphs by name to a .json, .yml or .yaml file for plugins")
+            .value_name("GLYPH_MAP_PATH")
+            .value_hint(clap::ValueHint::FilePath)
+            .value_parser(bitmap_font::parse_glyph_map_path),
+        clap::Arg::new("report_top")
+            .long("report-top")
+            .help("The number of largest files listed in the report")
+            .value_name("COUNT")
+            .value_parser(clap::value_parser!(usize))
+            .default_value("10"),
+        clap::Arg::new("max_size")
+            .long("max-size")
+            .help("Fail if the zip file (or the optimized files without --zip) is larger than this (e.g. 250MB or 100MiB)")
+            .value_name("SIZE")
+            .value_parser(budget::parse_size),
+        clap::Arg::new("max_namespace_size")
+            .long("max-namespace-size")
+            .help("Fail if the optimized files of a namespace are larger than this (e.g. minecraft=50MB), can be repeated")
+            .value_name("NAMESPACE=SIZE")
+            .value_parser(budget::parse_group_budget)
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("max_category_size")
+            .long("max-category-size")
+            .help("Fail if the optimized files of a category (textures, models, sounds, shaders, lang or other) are larger than this (e.g. sounds=80MB), can be repeated")
+            .value_name("CATEGORY=SIZE")
+            .value_parser(budget::parse_group_budget)
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("budget_warning")
+            .long("budget-warning")
+            .help("Warn when a size reaches this percentage of its budget")
+            .value_name("PERCENTAGE")
+            .value_parser(clap::value_parser!(f64))
+            .default_value("90"),
+        clap::Arg::new("slowest")
+            .long("slowest")
+            .help("The number of slowest files listed after processing (0 to hide the list)")
+            .value_name("COUNT")
+            .value_parser(clap::value_parser!(usize))
+            .default_value("5"),
+        clap::Arg::new("backup")
+            .long("backup")
+            .help("Keep the previous output in a backup directory next to the output directory")
+            .action(clap::ArgAction::SetTrue),
+        validate::get_target_arg(),
+        validate::get_client_jar_arg(),
+        clap::Arg::new("prune_unused")
+            .long("prune-unused")
+            .help("Leave out the models, textures and sounds nothing in the pack references")
+            .action(clap::ArgAction::SetTrue),
+        unused::get_keep_unused_arg(),
+        clap::Arg::new("prune_vanilla")
+            .long("prune-vanilla")
+            .help("Leave out the files that are the same as the vanilla files of the client jar")
+            .requires("client_jar")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("prune_lang_duplicates")
+            .long("prune-lang-duplicates")
+            .help("Leave out the lang values that are the same as en_us and not vanilla keys of the client jar")
+            .requires("client_jar")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("strict")
+            .long("strict")
+            .help("Stop before optimizing if the pack has any errors, not only files that cannot be parsed")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("verify")
+            .long("verify")
+            .help("Check that every optimized file means the same as its source file before writing the output")
+            .action(clap::ArgAction::SetTrue),
+    ];
+}
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("optimize")
+        .about("Optimizes a pack directory or zip file (the default when no subcommand is given)")
+        .args(get_args());
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    info!(""); // empty line
+
+    let input_path = matched_args.get_one::<path::PathBuf>("input_path").expect("Failed to get input_path");
+    debug!("input_path: {}", input_path.to_str().expect("Failed to convert input_path to string"));
+
+    let output_dir = matched_args.get_one::<path::PathBuf>("output_path").expect("Failed to get output_path");
+    debug!("output_dir: {}", output_dir.to_str().expect("Failed to convert output_dir to string"));
+
+    let zip_name = matched_args.get_one::<path::PathBuf>("zip");
+    match zip_name {
+        Some(zip_name) => {
+            debug!("zip_name: {}", zip_name.to_str().expect("Failed to convert zip_name to string"));
+        },
+        None => {
+            debug!("zip_name: None");
+        },
+    }
+
+    let server_properties_path = matched_args.get_one::<path::PathBuf>("server_properties");
+    let public_url = matched_args.get_one::<String>("public_url");
+    let server_properties_dry_run = matched_args.get_flag("server_properties_dry_run");
+
+    let keep_backup = matched_args.get_flag("backup");
+    let dry_run = matched_args.get_flag("dry_run");
+    let verify_output = matched_args.get_flag("verify");
+    let strict = matched_args.get_flag("strict");
+    let prune_unused = matched_args.get_flag("prune_unused");
+    let prune_vanilla = matched_args.get_flag("prune_vanilla");
+    let prune_lang_duplicates = matched_args.get_flag("prune_lang_duplicates");
+    let keep_patterns = matched_args.get_many::<regex::Regex>("keep_unused").unwrap_or_default().cloned().collect::<Vec<regex::Regex>>();
+
+    let report_paths = matched_args.get_many::<path::PathBuf>("report").unwrap_or_default().collect::<Vec<&path::PathBuf>>();
+    let glyph_map_path = matched_args.get_one::<path::PathBuf>("glyph_map");
+    let report_top = *matched_args.get_one::<usize>("report_top").expect("Failed to get report_top");
+    let slowest_count = *matched_args.get_one::<usize>("slowest").expect("Failed to get slowest");
+
+    let budgets = budget::Budgets {
+        max_size: matched_args.get_one::<u64>("max_size").copied(),
+        max_namespace_sizes: matched_args.get_many::<(String, u64)>("max_namespace_size").unwrap_or_default().cloned().collect(),
+        max_category_sizes: matched_args.get_many::<(String, u64)>("max_category_size").unwrap_or_default().cloned().collect(),
+        warning_percentage: *matched_args.get_one::<f64>("budget_warning").expect("Failed to get budget_warning"),
+    };
+
+    let assume_yes = matched_args.get_flag("yes");
+
+    debug!(""); // empty line
+
+    crate::validate_input_and_output_dirs(input_path, output_dir);
+
+    report::validate_report_paths(&report_paths);
+
+    budget::validate_budgets(&budgets);
+
+    let pack_source = source::open_pack(input_path);
+    let input_dir = pack_source.dir();
+
+    // stop before any work is done, instead of failing halfway through a stage
+    let target = matched_args.get_one::<target::Target>("target");
+
+    if let Some(target) = target {
+        debug!("Checking the pack for {} (pack_format {})", target.name(), target.formats());
+    }
+
+    let client_jar = matched_args.get_one::<path::PathBuf>("client_jar").map(
+        |jar_path| return client_jar::open_client_jar(jar_path)
+    );
+
+    let validation = validate::validate_pack(input_dir, target, client_jar.as_ref());
+    validate::log_issues(&validation);
+
+    // the stages cannot process files that cannot be parsed, the other errors only show up in game
+    if !validation.unreadable_paths.is_empty() {
+        crate::fail_program(
+            format!("The pack has {} files that cannot be parsed, fix them before optimizing it", validation.unreadable_paths.len()).as_str()
+        );
+    }
+
+    let error_count = validation.count(validate::Severity::Error);
+    if error_count > 0 {
+        match strict {
+            true => crate::fail_program(format!("The pack has {} errors, fix them before optimizing it", error_count).as_str()),
+            false => warning!("The pack has {} errors, add --strict to stop before optimizing it", error_count),
+        }
+    }
+
+    let unused_assets = unused::find_unused_assets(input_dir, client_jar.as_ref(), &keep_patterns);
+
+    if !unused_assets.is_empty() {
+        let unused_size = files_size(input_dir, &unused_assets);
+
+        for pack_path in &unused_assets {
+            debug!("Unused: {}", pack_path);
+        }
+
+        match prune_unused {
+            true => info!("Leaving out {} unused assets ({})", unused_assets.len(), report::format_bytes(unused_size)),
+            false => info!(
+                "Found {} unused assets ({}), add --prune-unused to leave them out",
+                unused_assets.len(),
+                report::format_bytes(unused_size)
+            ),
+        }
+    }
+
+    let vanilla_copies = client_jar.as_ref().map(
+        |client_jar| return vanilla::find_vanilla_copies(input_dir, client_jar)
+    ).unwrap_or_default();
+
+    if !vanilla_copies.is_empty() {
+        let vanilla_size = files_size(input_dir, &vanilla_copies);
+
+        for pack_path in &vanilla_copies {
+            debug!("Same as vanilla: {}", pack_path);
+        }
+
+        match prune_vanilla {
+            true => info!("Leaving out {} files that are the same as vanilla ({})", vanilla_copies.len(), report::format_bytes(vanilla_size)),
+            false => info!(
+                "Found {} files that are the same as vanilla ({}), add --prune-vanilla to leave them out",
+                vanilla_copies.len(),
+                report::format_bytes(vanilla_size)
+            ),
+        }
+    }
+
+    let lang_duplicates = client_jar.as_ref().map(
+        |client_jar| return lang::find_fallback_duplicates(input_dir, client_jar)
+    ).unwrap_or_default();
+
+    let lang_duplicate_count = lang_duplicates.values().map(|keys| return keys.len()).sum::<usize>();
+
+    if lang_duplicate_count > 0 {
+        for (pack_path, keys) in &lang_duplicates {
+            debug!("Same as en_us: {} in {}", keys.join(", "), pack_path);
+        }
+
+        match prune_lang_duplicates {
+            true => info!("Leaving out {} lang values that are the same as en_us", lang_duplicate_count),
+            false => info!(
+                "Found {} lang values that are the same as en_us, add --prune-lang-duplicates to leave them out",
+                lang_duplicate_count
+            ),
+        }
+    }
+
+    let mut pruned_paths: collections::HashSet<String> = collections::HashSet::new();
+
+    if prune_unused {
+        pruned_paths.extend(unused_assets.iter().cloned());
+    }
+
+    if prune_vanilla {
+        pruned_paths.extend(vanilla_copies.iter().cloned());
+    }
+
+    let pruned_lang_keys = match prune_lang_duplicates {
+        true => lang_duplicates,
+        false => collections::HashMap::new(),
+    };
+
+    // the glyphs keep the code points the glyph map gave them in the previous run
+    let previous_code_points = glyph_map_path.map(|glyph_map_path| return bitmap_font::read_glyph_map(glyph_map_path));
+
+    if dry_run {
+        let (report, zip_sha1) = report::run_dry_run(
+            input_dir,
+            zip_name.is_some(),
+            &report_paths,
+            report_top,
+            &pruned_paths,
+            &pruned_lang_keys,
+            previous_code_points.as_ref(),
+        );
+
+        if let (Some(zip_name), Some(zip_sha1)) = (zip_name, zip_sha1) {
+            if let (Some(server_properties_path), Some(public_url)) = (server_properties_path, public_url) {
+                info!("");
+
+                server_properties::write_resource_pack_properties(
+                    server_properties_path,
+                    public_url,
+                    &output_dir.join(zip_name),
+                    &zip_sha1,
+                    true,
+                );
+            }
+        }
+
+        if !budgets.is_empty() && budget::check_budgets(&budgets, &report) {
+            crate::fail_program("The pack would exceed its size budget");
+        }
+
+        return;
+    }
+
+    {
+        let mut plan_steps = vec![
+            format!(
+                "Optimize the pack files in {} in a temporary directory",
+                input_path.to_str().expect("Failed to convert input_path to string")
+            ),
+        ];
+
+        if prune_unused && !unused_assets.is_empty() {
+            plan_steps.push(format!("Leave out {} unused assets", unused_assets.len()));
+        }
+
+        if prune_vanilla && !vanilla_copies.is_empty() {
+            plan_steps.push(format!("Leave out {} files that are the same as vanilla", vanilla_copies.len()));
+        }
+
+        if lang_duplicate_count > 0 && prune_lang_duplicates {
+            plan_steps.push(format!("Leave out {} lang values that are the same as en_us", lang_duplicate_count));
+        }
+
+        if verify_output {
+            plan_steps.push("Verify that the optimized files mean the same as the pack files".to_string());
+        }
+
+        plan_steps.push(
+            match zip_name {
+                Some(zip_name) => format!(
+                    "Write {} with its checksums and manifest",
+                    output_dir.join(zip_name).to_str().expect("Failed to convert zip_file_path to string")
+                ),
+                None => format!(
+                    "Write the optimized files with their checksums and manifest to {}",
+                    output_dir.to_str().expect("Failed to convert output_dir to string")
+                ),
+            }
+        );
+
+        plan_steps.extend(output::describe_replacement(output_dir, keep_backup));
+
+        for report_path in &report_paths {
+            plan_steps.push(format!("Write a report to {}", report_path.to_str().expect("Failed to convert report path to string")));
+        }
+
+        if let Some(glyph_map_path) = glyph_map_path {
+            plan_steps.push(format!("Write the glyph map to {}", glyph_map_path.to_str().expect("Failed to convert glyph_map_path to string")));
+        }
+
+        if let (Some(server_properties_path), Some(_), Some(_)) = (server_properties_path, public_url, zip_name) {
+            if !server_properties_dry_run {
+                plan_steps.push(
+                    format!(
+                        "Update the resource pack settings in {}",
+                        server_properties_path.to_str().expect("Failed to convert server_properties_path to string")
+                    )
+                );
+            }
+        }
+
+        crate::confirm_plan(&plan_steps, assume_yes);
+    }
+
+    // create a temporary directory to work on the files inside of the programs running directory
+    let temp_dir = TempDir::new("ms-rpo").expect("Failed to create temporary directory");
+    let temp_dir_path = temp_dir.path();
+
+    let stage_results = crate::process_pack(input_dir, temp_dir_path, &pruned_paths, &pruned_lang_keys, previous_code_points.as_ref());
+
+    progress::log_slowest_files(&stage_results, slowest_count);
+
+    if verify_output {
+        let (verified_files, verification) = verify::verify_pack(input_dir, temp_dir_path, &pruned_paths, &pruned_lang_keys);
+        validate::log_issues(&verification);
+
+        if !verification.issues.is_empty() {
+            temp_dir.close().expect("Failed to remove temporary directory");
+
+            crate::fail_program(
+                format!("{} optimized files do not match their source files, the output was not changed", verification.issues.len()).as_str()
+            );
+        }
+
+        info!("Verified {} optimized files", verified_files);
+    }
+
+    // the output directory is only touched once everything has been built successfully
+    let staging_dir = output::create_staging_dir(output_dir);
+
+    let zip_hashes = match zip_name {
+        Some(zip_name) => {
+            let zip_file_path = staging_dir.join(zip_name);
+
+            // if it does not exist, create it
+            fs::create_dir_all(
+                zip_file_path.parent().expect("Failed to get parent directory")
+            ).expect("Failed to create parent directory");
+
+            let zip_hashes = crate::zip_dir(temp_dir_path, &zip_file_path);
+
+            // write checksums and a manifest next to the zip file for deploy scripts
+            hash::write_zip_checksums(input_path, &zip_file_path, &zip_hashes);
+
+            Some(zip_hashes)
+        },
+        None => {
+            // copy all files from the temporary directory to the staging directory
+            crate::clone_dir(temp_dir_path, &staging_dir, None);
+
+            hash::write_dir_checksums(input_path, &staging_dir);
+
+            None
+        },
+    };
+
+    let report = report::build_report(
+        input_dir,
+        &report::list_file_sizes(temp_dir_path),
+        &stage_results,
+        zip_hashes.as_ref().map(|zip_hashes| return zip_hashes.size),
+        report_top,
+    );
+
+    // never replace a working output with a pack that is too large
+    if !budgets.is_empty() && budget::check_budgets(&budgets, &report) {
+        fs::remove_dir_all(&staging_dir).expect("Failed to remove staging directory");
+        crate::fail_program("The pack exceeds its size budget, the output was not changed");
+    }
+
+    output::commit_staging_dir(output_dir, &staging_dir, keep_backup);
+
+    report::write_reports(&report, &report_paths);
+
+    if let (Some(glyph_map_path), Some(previous_code_points)) = (glyph_map_path, &previous_code_points) {
+        bitmap_font::write_glyph_map(input_dir, glyph_map_path, previous_code_points);
+    }
+
+    if let (Some(zip_name), Some(zip_hashes)) = (zip_name, zip_hashes) {
+        if let (Some(server_properties_path), Some(public_url)) = (server_properties_path, public_url) {
+            server_properties::write_resource_pack_properties(
+                server_properties_path,
+                public_url,
+                &output_dir.join(zip_name),
+                &zip_hashes.sha1,
+                server_properties_dry_run,
+            );
+        }
+    }
+
+    debug!("Deleting temporary directory {}...", temp_dir_path.to_str().expect("Failed to convert temp_dir to string"));
+    temp_dir.close().expect("Failed to remove temporary directory");
+
+    info!(""); // empty line
+
+    info!("Exiting...");
+}
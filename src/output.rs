@@ -0,0 +1,438 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::ffi;
+use std::fs;
+use std::io;
+use std::path;
+use std::collections;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::TraverseDirLookFor;
+use crate::traverse_dir;
+use crate::hash;
+use crate::logging::info;
+
+//------------------------------------------------------------//
+
+// lists the files in the output directory that were created by ms-rpo (and may be replaced or removed by it)
+pub const OWNERSHIP_MARKER_FILE_NAME: &str = ".ms-rpo-owned";
+
+//------------------------------------------------------------//
+
+fn sibling_dir_path(
+    output_dir: &path::Path,
+    suffix: &str,
+) -> path::PathBuf {
+    let output_dir = fs::canonicalize(output_dir).expect("Failed to resolve output directory");
+
+    return hash::append_to_file_name(&output_dir, suffix);
+}
+
+/**
+ * The directory the next output is built in, next to the output directory (so it is on the same file system).
+ */
+pub fn staging_dir_path(
+    output_dir: &path::Path,
+) -> path::PathBuf {
+    return sibling_dir_path(output_dir, ".ms-rpo-staging");
+}
+
+/**
+ * The directory the previous output is moved to when it is replaced.
+ */
+pub fn backup_dir_path(
+    output_dir: &path::Path,
+) -> path::PathBuf {
+    return sibling_dir_path(output_dir, ".ms-rpo-backup");
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Reads the files listed in the ownership marker of a directory, or an empty set if it has none.
+ */
+pub fn read_owned_files(
+    dir: &path::Path,
+) -> collections::BTreeSet<String> {
+    let marker_file_path = dir.join(OWNERSHIP_MARKER_FILE_NAME);
+
+    if !marker_file_path.is_file() {
+        return collections::BTreeSet::new();
+    }
+
+    let marker_contents = fs::read_to_string(marker_file_path).expect("Failed to read ownership marker");
+
+    return serde_json::from_str::<collections::BTreeSet<String>>(&marker_contents).expect("Failed to parse ownership marker");
+}
+
+pub fn write_ownership_marker(
+    dir: &path::Path,
+    owned_files: &collections::BTreeSet<String>,
+) {
+    let marker_contents = serde_json::to_string_pretty(owned_files).expect("Failed to convert ownership marker to string");
+
+    fs::write(dir.join(OWNERSHIP_MARKER_FILE_NAME), marker_contents).expect("Failed to write ownership marker");
+}
+
+/**
+ * Lists every file in a directory by its `/` separated path, leaving out the ownership marker.
+ */
+fn list_files(
+    dir: &path::Path,
+) -> collections::BTreeSet<String> {
+    return traverse_dir(dir, &TraverseDirLookFor::Files, None).iter().map(
+        |file| return hash::to_pack_path(file.strip_prefix(dir).expect("Failed to strip prefix"))
+    ).filter(
+        |pack_path| return pack_path != OWNERSHIP_MARKER_FILE_NAME
+    ).collect::<collections::BTreeSet<String>>();
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Creates an empty staging directory for the output directory.
+ *
+ * A staging directory left behind by a failed run is removed first, unless it lists what ms-rpo created in it
+ * and has other files as well, which older versions moved there from the output directory.
+ */
+pub fn create_staging_dir(
+    output_dir: &path::Path,
+) -> path::PathBuf {
+    let staging_dir = staging_dir_path(output_dir);
+
+    if staging_dir.exists() {
+        let staging_dir_string = staging_dir.to_str().expect("Failed to convert staging_dir to string");
+
+        if staging_dir.join(OWNERSHIP_MARKER_FILE_NAME).is_file() && !list_foreign_files(&staging_dir).is_empty() {
+            crate::fail_program(
+                format!(
+                    "The staging directory {} contains files that were not created by ms-rpo.\nMove them back into the output directory or delete the staging directory and try again.",
+                    staging_dir_string
+                ).as_str()
+            );
+        }
+
+        fs::remove_dir_all(&staging_dir).expect("Failed to remove previous staging directory");
+    }
+
+    fs::create_dir_all(&staging_dir).expect("Failed to create staging directory");
+
+    return staging_dir;
+}
+
+/**
+ * Lists every empty directory in a directory by its `/` separated path, ending in `/`.
+ */
+fn list_empty_dirs(
+    dir: &path::Path,
+) -> collections::BTreeSet<String> {
+    return traverse_dir(dir, &TraverseDirLookFor::Folders, None).iter().filter(
+        |sub_dir| return fs::read_dir(sub_dir).expect("Failed to read directory").next().is_none()
+    ).map(
+        |sub_dir| return format!("{}/", hash::to_pack_path(sub_dir.strip_prefix(dir).expect("Failed to strip prefix")))
+    ).collect::<collections::BTreeSet<String>>();
+}
+
+/**
+ * Returns the files in the output directory that were not created by ms-rpo, and its empty directories.
+ *
+ * ms-rpo only creates directories to hold its files, so an empty directory was made by someone else.
+ */
+pub fn list_foreign_files(
+    output_dir: &path::Path,
+) -> collections::BTreeSet<String> {
+    let owned_files = read_owned_files(output_dir);
+
+    let mut foreign_files = list_files(output_dir).into_iter().filter(
+        |pack_path| return !owned_files.contains(pack_path)
+    ).collect::<collections::BTreeSet<String>>();
+
+    foreign_files.extend(list_empty_dirs(output_dir));
+
+    return foreign_files;
+}
+
+/**
+ * Describes what replacing the output directory will do, for the plan shown before a run.
+ */
+pub fn describe_replacement(
+    output_dir: &path::Path,
+    keep_backup: bool,
+) -> Vec<String> {
+    let mut plan_steps: Vec<String> = Vec::new();
+
+    let output_dir_string = output_dir.to_str().expect("Failed to convert output_dir to string");
+
+    let replaced_files = read_owned_files(output_dir);
+    if !replaced_files.is_empty() {
+        plan_steps.push(format!("Replace the {} files created by a previous run in {}", replaced_files.len(), output_dir_string));
+    }
+
+    let foreign_files = list_foreign_files(output_dir);
+    if !foreign_files.is_empty() {
+        plan_steps.push(format!("Keep the {} other files and empty directories in {}", foreign_files.len(), output_dir_string));
+    }
+
+    if keep_backup {
+        plan_steps.push(
+            format!(
+                "Keep the previous output in {}",
+                backup_dir_path(output_dir).to_str().expect("Failed to convert backup_dir to string")
+            )
+        );
+    }
+
+    return plan_steps;
+}
+
+/**
+ * Exchanges two directories with a single rename, so neither path is ever missing or half replaced.
+ */
+#[cfg(target_os = "linux")]
+fn exchange_dirs(
+    dir: &path::Path,
+    other_dir: &path::Path,
+) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = ffi::CString::new(dir.as_os_str().as_bytes()).map_err(io::Error::from)?;
+    let other_dir = ffi::CString::new(other_dir.as_os_str().as_bytes()).map_err(io::Error::from)?;
+
+    // SAFETY: both paths are valid nul terminated strings that outlive the call
+    let result = unsafe {
+        libc::renameat2(libc::AT_FDCWD, dir.as_ptr(), libc::AT_FDCWD, other_dir.as_ptr(), libc::RENAME_EXCHANGE)
+    };
+
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    return Ok(());
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exchange_dirs(
+    _dir: &path::Path,
+    _other_dir: &path::Path,
+) -> io::Result<()> {
+    return Err(io::Error::from(io::ErrorKind::Unsupported));
+}
+
+/**
+ * Puts the staging directory in place of the output directory and returns where the previous output ended up.
+ *
+ * Where the directories cannot be exchanged in one step, the output directory is moved to the backup directory
+ * first, and moved back if the staging directory cannot take its place.
+ */
+fn swap_dirs(
+    output_dir: &path::Path,
+    staging_dir: &path::Path,
+    backup_dir: &path::Path,
+) -> Result<path::PathBuf, String> {
+    if exchange_dirs(output_dir, staging_dir).is_ok() {
+        return Ok(staging_dir.to_path_buf());
+    }
+
+    fs::rename(output_dir, backup_dir).map_err(|error| return format!("Failed to move previous output to backup directory: {}", error))?;
+
+    if let Err(error) = fs::rename(staging_dir, output_dir) {
+        fs::rename(backup_dir, output_dir).expect("Failed to restore previous output");
+
+        return Err(format!("Failed to move staging directory into place: {}", error));
+    }
+
+    return Ok(backup_dir.to_path_buf());
+}
+
+/**
+ * Removes the directories above a removed file that are left empty, up to the given directory.
+ */
+fn remove_empty_parent_dirs(
+    file_path: &path::Path,
+    dir: &path::Path,
+) {
+    let mut parent_dir = file_path.parent();
+
+    while let Some(parent) = parent_dir {
+        if parent == dir || fs::remove_dir(parent).is_err() {
+            break;
+        }
+
+        parent_dir = parent.parent();
+    }
+}
+
+/**
+ * Moves files from one directory to the same paths in another, moving them back if one of them cannot be moved.
+ */
+fn move_files(
+    from_dir: &path::Path,
+    to_dir: &path::Path,
+    pack_paths: &[String],
+) -> Result<(), String> {
+    let move_file = |from_path: &path::Path, to_path: &path::Path| {
+        fs::create_dir_all(to_path.parent().expect("Failed to get parent directory"))?;
+        fs::rename(from_path, to_path)?;
+
+        remove_empty_parent_dirs(from_path, from_dir);
+
+        return io::Result::Ok(());
+    };
+
+    for (index, pack_path) in pack_paths.iter().enumerate() {
+        if let Err(error) = move_file(&from_dir.join(pack_path), &to_dir.join(pack_path)) {
+            for moved_path in &pack_paths[..index] {
+                move_file(&to_dir.join(moved_path), &from_dir.join(moved_path)).expect("Failed to move file back");
+            }
+
+            return Err(format!("Failed to move {}: {}", pack_path, error));
+        }
+    }
+
+    return Ok(());
+}
+
+/**
+ * Swaps a finished staging directory into place of the output directory.
+ *
+ * If every file in the output directory was created by ms-rpo and it has no empty directories, the two directories
+ * are exchanged in one step. Otherwise the files and empty directories of others stay where they are, and only the files ms-rpo created before are swapped for
+ * the new ones, each put back if the swap fails part way. The previous files end up in the backup directory,
+ * which is removed unless `keep_backup` is set.
+ */
+pub fn commit_staging_dir(
+    output_dir: &path::Path,
+    staging_dir: &path::Path,
+    keep_backup: bool,
+) {
+    let operation_start = std::time::Instant::now();
+
+    // renaming `.` or a path ending in `..` does not work, so always use the full path
+    let output_dir = &fs::canonicalize(output_dir).expect("Failed to resolve output directory");
+
+    let new_files = list_files(staging_dir);
+    let new_file_count = new_files.len();
+    let foreign_files = list_foreign_files(output_dir);
+
+    // never overwrite files that ms-rpo did not create
+    let conflicting_files = foreign_files.intersection(&new_files).cloned().collect::<Vec<String>>();
+    if !conflicting_files.is_empty() {
+        fs::remove_dir_all(staging_dir).expect("Failed to remove staging directory");
+
+        crate::fail_program(
+            format!(
+                "The output directory contains files that were not created by ms-rpo and would be overwritten:\n{}\nMove or delete them and try again.",
+                conflicting_files.join("\n")
+            ).as_str()
+        );
+    }
+
+    let backup_dir = backup_dir_path(output_dir);
+
+    if backup_dir.exists() {
+        fs::remove_dir_all(&backup_dir).expect("Failed to remove previous backup directory");
+    }
+
+    if foreign_files.is_empty() {
+        write_ownership_marker(staging_dir, &new_files);
+
+        let previous_output_dir = swap_dirs(output_dir, staging_dir, &backup_dir).unwrap_or_else(
+            |error| crate::fail_program(format!("{}, the output was not changed", error).as_str())
+        );
+
+        if keep_backup && previous_output_dir != backup_dir {
+            fs::rename(&previous_output_dir, &backup_dir).expect("Failed to move previous output to backup directory");
+        } else if !keep_backup {
+            fs::remove_dir_all(&previous_output_dir).expect("Failed to remove previous output");
+        }
+    } else {
+        let replaced_files = read_owned_files(output_dir).into_iter().filter(
+            |pack_path| return output_dir.join(pack_path).is_file()
+        ).collect::<Vec<String>>();
+        let new_files = new_files.iter().cloned().collect::<Vec<String>>();
+
+        fs::create_dir_all(&backup_dir).expect("Failed to create backup directory");
+
+        if let Err(error) = move_files(output_dir, &backup_dir, &replaced_files) {
+            crate::fail_program(format!("{}, the output was not changed", error).as_str());
+        }
+
+        if let Err(error) = move_files(staging_dir, output_dir, &new_files) {
+            move_files(&backup_dir, output_dir, &replaced_files).expect("Failed to restore previous output");
+
+            crate::fail_program(format!("{}, the output was not changed", error).as_str());
+        }
+
+        write_ownership_marker(output_dir, &new_files.into_iter().collect::<collections::BTreeSet<String>>());
+
+        // only empty directories are left in it
+        fs::remove_dir_all(staging_dir).expect("Failed to remove staging directory");
+
+        if !keep_backup {
+            fs::remove_dir_all(&backup_dir).expect("Failed to remove backup directory");
+        }
+    }
+
+    if keep_backup {
+        info!("Kept previous output in {}", backup_dir.to_str().expect("Failed to convert backup_dir to string"));
+    }
+
+    let operation_end = operation_start.elapsed();
+
+    info!("Replaced output with {} files ({} files and empty directories kept from others) in {:.2?}", new_file_count, foreign_files.len(), operation_end);
+}
+
+/**
+ * Records files that were written straight into the output directory as created by ms-rpo.
+ */
+pub fn add_owned_files(
+    output_dir: &path::Path,
+    pack_paths: &[String],
+) {
+    let mut owned_files = read_owned_files(output_dir);
+    owned_files.extend(pack_paths.iter().cloned());
+
+    write_ownership_marker(output_dir, &owned_files);
+}
+
+/**
+ * Removes the owned files at or below the given paths from the output directory.
+ *
+ * Files that were not created by ms-rpo are left alone.
+ */
+pub fn remove_owned_files(
+    output_dir: &path::Path,
+    pack_paths: &[String],
+) {
+    let mut owned_files = read_owned_files(output_dir);
+
+    let removed_files = owned_files.iter().filter(
+        |owned_file| {
+            return pack_paths.iter().any(
+                |pack_path| return *owned_file == pack_path || owned_file.starts_with(&format!("{}/", pack_path))
+            );
+        }
+    ).cloned().collect::<Vec<String>>();
+
+    for removed_file in &removed_files {
+        let removed_file_path = output_dir.join(removed_file);
+
+        if removed_file_path.is_file() {
+            fs::remove_file(&removed_file_path).expect("Failed to remove file");
+        }
+
+        // clean up directories that were only there for the removed file
+        remove_empty_parent_dirs(&removed_file_path, output_dir);
+
+        owned_files.remove(removed_file);
+    }
+
+    write_ownership_marker(output_dir, &owned_files);
+}
@@ -0,0 +1,575 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::target;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+const PACK_MCMETA: &str = "pack.mcmeta";
+
+// the keys that give a text component its content
+const TEXT_COMPONENT_CONTENT_KEYS: [&str; 6] = ["text", "translate", "score", "selector", "keybind", "nbt"];
+
+//------------------------------------------------------------//
+
+/**
+ * A directory of files that replace the root files for some pack formats.
+ */
+pub struct Overlay {
+    pub directory: String,
+    pub formats: Option<(u64, u64)>,
+}
+
+//------------------------------------------------------------//
+
+pub fn read_pack_mcmeta(
+    pack_dir: &path::Path,
+) -> Option<serde_json::Value> {
+    return validate::read_json(&pack_dir.join(PACK_MCMETA));
+}
+
+/**
+ * Reads the `overlays.entries` of the `pack.mcmeta` of a pack, skipping entries without a directory.
+ */
+pub fn read_overlays(
+    pack_dir: &path::Path,
+) -> Vec<Overlay> {
+    let pack_mcmeta = read_pack_mcmeta(pack_dir);
+
+    let entries = pack_mcmeta.as_ref()
+        .and_then(|pack_mcmeta| return pack_mcmeta.pointer("/overlays/entries"))
+        .and_then(|entries| return entries.as_array());
+
+    return entries.into_iter().flatten().filter_map(
+        |entry| return Some(
+            Overlay {
+                directory: entry.get("directory")?.as_str()?.to_string(),
+                formats: entry.get("formats").and_then(target::read_supported_formats).or_else(
+                    || return target::read_min_max_formats(entry.as_object()?)
+                ),
+            }
+        )
+    ).collect();
+}
+
+/**
+ * Returns the path of a file in an overlay as if it was in the root of the pack (`overlay/assets/...` becomes `assets/...`).
+ */
+pub fn strip_overlay_dir(
+    pack_path: &str,
+) -> &str {
+    return match pack_path.split_once('/') {
+        Some((directory, rest)) if directory != "assets" && rest.starts_with("assets/") => rest,
+        _ => pack_path,
+    };
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Checks a text component, which is a string, an object with content (`text`, `translate`, ...) or a list of them.
+ */
+fn check_text_component(
+    text_component: &serde_json::Value,
+    json_path: &str,
+    validation: &mut validate::Validation,
+) {
+    match text_component {
+        serde_json::Value::String(_) => {},
+        serde_json::Value::Array(text_components) => {
+            if text_components.is_empty() {
+                validation.error(PACK_MCMETA, format!("`{}` is an empty list", json_path));
+            }
+
+            for (index, text_component) in text_components.iter().enumerate() {
+                check_text_component(text_component, format!("{}[{}]", json_path, index).as_str(), validation);
+            }
+        },
+        serde_json::Value::Object(text_component) => {
+            if !TEXT_COMPONENT_CONTENT_KEYS.iter().any(|key| return text_component.contains_key(*key)) {
+                validation.error(
+                    PACK_MCMETA,
+                    format!("`{}` has no content, give it one of: {}", json_path, TEXT_COMPONENT_CONTENT_KEYS.join(", "))
+                );
+            }
+
+            for key in ["text", "translate", "keybind", "color", "font"] {
+                if text_component.get(key).is_some_and(|value| return !value.is_string()) {
+                    validation.error(PACK_MCMETA, format!("`{}.{}` has to be a string", json_path, key));
+                }
+            }
+
+            for key in ["extra", "with"] {
+                match text_component.get(key) {
+                    Some(serde_json::Value::Array(text_components)) => {
+                        for (index, text_component) in text_components.iter().enumerate() {
+                            check_text_component(text_component, format!("{}.{}[{}]", json_path, key, index).as_str(), validation);
+                        }
+                    },
+                    Some(_) => validation.error(PACK_MCMETA, format!("`{}.{}` has to be a list", json_path, key)),
+                    None => {},
+                }
+            }
+        },
+        _ => validation.error(
+            PACK_MCMETA,
+            format!("`{}` has to be a string, a text component or a list of them", json_path)
+        ),
+    }
+}
+
+/**
+ * Checks a pack format range, returning it if it is valid.
+ */
+fn check_formats(
+    formats: &serde_json::Value,
+    json_path: &str,
+    validation: &mut validate::Validation,
+) -> Option<(u64, u64)> {
+    let Some((min_format, max_format)) = target::read_supported_formats(formats) else {
+        validation.error(
+            PACK_MCMETA,
+            format!("`{}` has to be a number, a [min, max] list or a {{ min_inclusive, max_inclusive }} object", json_path)
+        );
+
+        return None;
+    };
+
+    if min_format > max_format {
+        validation.error(PACK_MCMETA, format!("`{}` starts at {} after it ends at {}", json_path, min_format, max_format));
+
+        return None;
+    }
+
+    return Some((min_format, max_format));
+}
+
+/**
+ * Checks a `min_format` or `max_format`, returning its major and minor version if it is valid.
+ */
+fn check_format(
+    format: &serde_json::Value,
+    json_path: &str,
+    validation: &mut validate::Validation,
+) -> Option<(u64, u64)> {
+    let format = target::read_format(format);
+
+    if format.is_none() {
+        validation.error(PACK_MCMETA, format!("`{}` has to be a number or a [major, minor] list", json_path));
+    }
+
+    return format;
+}
+
+/**
+ * Checks the `min_format` and `max_format` of `pack` or an overlay entry, returning the range of release formats they cover
+ * if they are given and valid.
+ */
+fn check_min_max_formats(
+    object: &serde_json::Map<String, serde_json::Value>,
+    json_path: &str,
+    validation: &mut validate::Validation,
+) -> Option<(u64, u64)> {
+    let (min_format, max_format) = match (object.get("min_format"), object.get("max_format")) {
+        (Some(min_format), Some(max_format)) => (min_format, max_format),
+        (None, None) => return None,
+        _ => {
+            validation.error(PACK_MCMETA, format!("`{0}.min_format` and `{0}.max_format` have to be given together", json_path));
+
+            return None;
+        },
+    };
+
+    let min_format = check_format(min_format, format!("{}.min_format", json_path).as_str(), validation);
+    let max_format = check_format(max_format, format!("{}.max_format", json_path).as_str(), validation);
+
+    let (Some(min_format), Some(max_format)) = (min_format, max_format) else {
+        return None;
+    };
+
+    if min_format > max_format {
+        validation.error(
+            PACK_MCMETA,
+            format!(
+                "`{}.min_format` starts at {}.{} after `{}.max_format` ends at {}.{}",
+                json_path,
+                min_format.0,
+                min_format.1,
+                json_path,
+                max_format.0,
+                max_format.1
+            )
+        );
+
+        return None;
+    }
+
+    return target::read_min_max_formats(object);
+}
+
+/**
+ * Checks the regexes of `filter.block`, which hide files of packs below this one.
+ *
+ * Minecraft uses Java regexes, whose look-around and back references cannot be checked here, so they only get a warning.
+ */
+fn check_filter(
+    filter: &serde_json::Value,
+    validation: &mut validate::Validation,
+) {
+    let Some(block) = filter.get("block").and_then(|block| return block.as_array()) else {
+        validation.error(PACK_MCMETA, "`filter.block` has to be a list".to_string());
+
+        return;
+    };
+
+    for (index, entry) in block.iter().enumerate() {
+        if !entry.is_object() {
+            validation.error(PACK_MCMETA, format!("`filter.block[{}]` has to be an object with `namespace` or `path`", index));
+
+            continue;
+        }
+
+        for key in ["namespace", "path"] {
+            match entry.get(key) {
+                Some(serde_json::Value::String(pattern)) => {
+                    let Err(error) = regex::Regex::new(pattern) else {
+                        continue;
+                    };
+
+                    let error_message = error.to_string().lines().last().unwrap_or_default().trim_start_matches("error: ").to_string();
+
+                    if error_message.contains("not supported") {
+                        validation.warning(
+                            PACK_MCMETA,
+                            format!("`filter.block[{}].{}` could not be checked: {}", index, key, error_message)
+                        );
+                    } else {
+                        validation.error(
+                            PACK_MCMETA,
+                            format!("`filter.block[{}].{}` is not a valid regex: {}", index, key, error_message)
+                        );
+                    }
+                },
+                Some(_) => validation.error(PACK_MCMETA, format!("`filter.block[{}].{}` has to be a string", index, key)),
+                None => {},
+            }
+        }
+    }
+}
+
+/**
+ * Checks the `overlays.entries`, whose directories have to exist, and reports overlay directories they do not list.
+ */
+fn check_overlays(
+    pack_dir: &path::Path,
+    overlays: Option<&serde_json::Value>,
+    target: Option<&target::Target>,
+    validation: &mut validate::Validation,
+) {
+    let mut listed_directories: Vec<String> = Vec::new();
+
+    if let Some(overlays) = overlays {
+        match overlays.get("entries").and_then(|entries| return entries.as_array()) {
+            Some(entries) => {
+                for (index, entry) in entries.iter().enumerate() {
+                    let json_path = format!("overlays.entries[{}]", index);
+
+                    // versions since 1.21.9 read `min_format` and `max_format`, older ones `formats`
+                    let min_max_formats = entry.as_object().and_then(
+                        |entry| return check_min_max_formats(entry, json_path.as_str(), validation)
+                    );
+
+                    let formats = match entry.get("formats") {
+                        Some(formats) => check_formats(formats, format!("{}.formats", json_path).as_str(), validation),
+                        None => {
+                            if entry.get("min_format").is_none() && entry.get("max_format").is_none() {
+                                validation.error(PACK_MCMETA, format!("`{}` is missing `formats` or `min_format` and `max_format`", json_path));
+                            }
+
+                            min_max_formats
+                        },
+                    };
+
+                    if let (Some((min_format, max_format)), Some(target)) = (formats, target) {
+                        if max_format < target.min_format || min_format > target.max_format {
+                            validation.warning(
+                                PACK_MCMETA,
+                                format!(
+                                    "`{}` is for formats {} to {}, so it is never used by {} ({})",
+                                    json_path,
+                                    min_format,
+                                    max_format,
+                                    target.name(),
+                                    target.formats()
+                                )
+                            );
+                        }
+                    }
+
+                    let Some(directory) = entry.get("directory").and_then(|directory| return directory.as_str()) else {
+                        validation.error(PACK_MCMETA, format!("`{}` is missing `directory`", json_path));
+
+                        continue;
+                    };
+
+                    let is_valid_name = !directory.is_empty() && directory.chars().all(
+                        |character| return matches!(character, 'a'..='z' | '0'..='9' | '_' | '-' | '.')
+                    );
+
+                    if !is_valid_name {
+                        validation.error(
+                            PACK_MCMETA,
+                            format!("`{}.directory` `{}` can only have a-z, 0-9, `_`, `-` and `.` in it", json_path, directory)
+                        );
+                    } else if !pack_dir.join(directory).is_dir() {
+                        validation.error(PACK_MCMETA, format!("`{}.directory` `{}` does not exist", json_path, directory));
+                    }
+
+                    listed_directories.push(directory.to_string());
+                }
+            },
+            None => validation.error(PACK_MCMETA, "`overlays.entries` has to be a list".to_string()),
+        }
+    }
+
+    let Ok(dir_items) = fs::read_dir(pack_dir) else {
+        return;
+    };
+
+    for dir_item in dir_items {
+        let item = dir_item.expect("Failed to read item").path();
+        let name = item.file_name().expect("Failed to get file name").to_str().expect("Failed to convert file name to string");
+
+        if name == "assets" || !item.join("assets").is_dir() || listed_directories.iter().any(|directory| return directory == name) {
+            continue;
+        }
+
+        validation.warning(name, "Looks like an overlay but is not in `overlays.entries`, so Minecraft ignores it".to_string());
+    }
+}
+
+/**
+ * Checks the fields of `pack.mcmeta` that Minecraft reads.
+ */
+pub fn check_pack_mcmeta(
+    pack_dir: &path::Path,
+    target: Option<&target::Target>,
+    validation: &mut validate::Validation,
+) {
+    if !pack_dir.join(PACK_MCMETA).is_file() {
+        validation.error(PACK_MCMETA, "The pack has no pack.mcmeta file, Minecraft will not load it".to_string());
+
+        return;
+    }
+
+    let Some(pack_mcmeta) = read_pack_mcmeta(pack_dir) else {
+        return;
+    };
+
+    let Some(pack) = pack_mcmeta.get("pack").and_then(|pack| return pack.as_object()) else {
+        validation.error(PACK_MCMETA, "Missing the `pack` object".to_string());
+
+        return;
+    };
+
+    let min_max_formats = check_min_max_formats(pack, "pack", validation);
+
+    let pack_format = pack.get("pack_format").and_then(|pack_format| return pack_format.as_u64());
+
+    match pack_format {
+        Some(pack_format) if pack_format > 0 => {},
+        // only the versions older than `min_format` and `max_format` read `pack_format`
+        None if !pack.contains_key("pack_format") && min_max_formats.is_some_and(
+            |(min_format, _)| return min_format >= target::MIN_MAX_FORMATS_FORMAT
+        ) => {},
+        None if !pack.contains_key("pack_format") && min_max_formats.is_some() => validation.error(
+            PACK_MCMETA,
+            format!("`pack.pack_format` is missing, but `pack.min_format` includes formats below {}, whose versions read it instead", target::MIN_MAX_FORMATS_FORMAT)
+        ),
+        _ => validation.error(PACK_MCMETA, "`pack.pack_format` has to be a positive whole number".to_string()),
+    }
+
+    if let Some(supported_formats) = pack.get("supported_formats") {
+        let supported_formats = check_formats(supported_formats, "pack.supported_formats", validation);
+
+        if let (Some((min_format, max_format)), Some(pack_format)) = (supported_formats, pack_format) {
+            if pack_format < min_format || pack_format > max_format {
+                validation.error(
+                    PACK_MCMETA,
+                    format!(
+                        "`pack.pack_format` {} is outside of `pack.supported_formats` [{}, {}], Minecraft will not load the pack",
+                        pack_format,
+                        min_format,
+                        max_format
+                    )
+                );
+            }
+        }
+    }
+
+    match pack.get("description") {
+        Some(description) => check_text_component(description, "pack.description", validation),
+        None => validation.warning(PACK_MCMETA, "`pack.description` is missing".to_string()),
+    }
+
+    if let Some(filter) = pack_mcmeta.get("filter") {
+        check_filter(filter, validation);
+    }
+
+    check_overlays(pack_dir, pack_mcmeta.get("overlays"), target, validation);
+
+    if let Some(target) = target {
+        target::check_pack_mcmeta(target, pack, validation);
+    }
+}
+
+//------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempdir::TempDir;
+
+    /**
+     * Creates a pack with the given `pack.mcmeta` and an overlay directory for each of `overlay_dirs`.
+     */
+    fn create_pack(
+        pack_mcmeta: &serde_json::Value,
+        overlay_dirs: &[&str],
+    ) -> TempDir {
+        let pack_dir = TempDir::new("ms-rpo-test").expect("Failed to create temporary directory");
+
+        fs::write(pack_dir.path().join(PACK_MCMETA), pack_mcmeta.to_string()).expect("Failed to write pack.mcmeta");
+
+        for overlay_dir in overlay_dirs {
+            fs::create_dir_all(pack_dir.path().join(overlay_dir).join("assets")).expect("Failed to create overlay directory");
+        }
+
+        return pack_dir;
+    }
+
+    fn overlay_entries_pack_mcmeta(
+        entries: serde_json::Value,
+    ) -> serde_json::Value {
+        return serde_json::json!({
+            "pack": { "pack_format": 15, "supported_formats": [15, 75], "min_format": 15, "max_format": 75, "description": "test" },
+            "overlays": { "entries": entries },
+        });
+    }
+
+    #[test]
+    fn read_overlays_prefers_formats_over_min_max_formats() {
+        let pack_dir = create_pack(
+            &overlay_entries_pack_mcmeta(serde_json::json!([
+                { "directory": "both", "formats": [18, 22], "min_format": 69, "max_format": 75 },
+                { "directory": "min_max", "min_format": [69, 1], "max_format": 75 },
+                { "directory": "formats", "formats": { "min_inclusive": 15, "max_inclusive": 18 } },
+                { "directory": "broken_formats", "formats": "15", "min_format": 15, "max_format": 18 },
+                { "directory": "none" },
+                { "formats": 15 },
+            ])),
+            &[],
+        );
+
+        let overlays = read_overlays(pack_dir.path()).iter().map(
+            |overlay| return (overlay.directory.clone(), overlay.formats)
+        ).collect::<Vec<(String, Option<(u64, u64)>)>>();
+
+        assert_eq!(
+            overlays,
+            [
+                ("both".to_string(), Some((18, 22))),
+                ("min_max".to_string(), Some((70, 75))),
+                ("formats".to_string(), Some((15, 18))),
+                // a `formats` that cannot be read falls back to `min_format` and `max_format`
+                ("broken_formats".to_string(), Some((15, 18))),
+                ("none".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_pack_mcmeta_checks_formats_and_min_max_formats_of_overlays() {
+        let cases = [
+            (serde_json::json!({ "directory": "overlay", "formats": [18, 22], "min_format": 18, "max_format": 22 }), Vec::<&str>::new()),
+            (serde_json::json!({ "directory": "overlay", "min_format": [69, 1], "max_format": 75 }), Vec::new()),
+            (serde_json::json!({ "directory": "overlay", "formats": [18, 22] }), Vec::new()),
+            (
+                serde_json::json!({ "directory": "overlay" }),
+                vec!["`overlays.entries[0]` is missing `formats` or `min_format` and `max_format`"],
+            ),
+            (
+                serde_json::json!({ "directory": "overlay", "formats": [18, 22], "min_format": 18 }),
+                vec!["`overlays.entries[0].min_format` and `overlays.entries[0].max_format` have to be given together"],
+            ),
+            (
+                serde_json::json!({ "directory": "overlay", "formats": [22, 18], "min_format": [22, 1], "max_format": [22] }),
+                vec![
+                    "`overlays.entries[0].min_format` starts at 22.1 after `overlays.entries[0].max_format` ends at 22.0",
+                    "`overlays.entries[0].formats` starts at 22 after it ends at 18",
+                ],
+            ),
+            (
+                serde_json::json!({ "directory": "overlay", "min_format": "18", "max_format": 22 }),
+                vec!["`overlays.entries[0].min_format` has to be a number or a [major, minor] list"],
+            ),
+        ];
+
+        for (entry, errors) in cases {
+            let pack_dir = create_pack(&overlay_entries_pack_mcmeta(serde_json::json!([entry])), &["overlay"]);
+
+            let mut validation = validate::Validation::default();
+            check_pack_mcmeta(pack_dir.path(), None, &mut validation);
+
+            assert_eq!(
+                validation.issues.iter().map(|issue| return issue.message.as_str()).collect::<Vec<&str>>(),
+                errors,
+                "Checking {}",
+                entry
+            );
+            assert_eq!(validation.count(validate::Severity::Error), errors.len());
+        }
+    }
+
+    #[test]
+    fn check_pack_mcmeta_warns_about_overlays_outside_of_the_target() {
+        let pack_dir = create_pack(
+            &overlay_entries_pack_mcmeta(serde_json::json!([
+                // `formats` is what the check goes by when both are given
+                { "directory": "old", "formats": [18, 22], "min_format": 69, "max_format": 75 },
+                { "directory": "new", "min_format": [69, 1], "max_format": 75 },
+                { "directory": "between", "min_format": 69, "max_format": [69, 5] },
+            ])),
+            &["old", "new", "between"],
+        );
+
+        let target = target::parse_target("1.21.11").expect("Failed to parse target");
+
+        let mut validation = validate::Validation::default();
+        check_pack_mcmeta(pack_dir.path(), Some(&target), &mut validation);
+
+        let warnings = validation.issues.iter().filter(
+            |issue| return issue.severity == validate::Severity::Warning
+        ).map(|issue| return issue.message.as_str()).collect::<Vec<&str>>();
+
+        assert_eq!(
+            warnings,
+            [
+                "`overlays.entries[0]` is for formats 18 to 22, so it is never used by 1.21.11 (75)",
+                "`overlays.entries[2]` is for formats 69 to 69, so it is never used by 1.21.11 (75)",
+            ]
+        );
+    }
+}
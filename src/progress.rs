@@ -0,0 +1,214 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::env;
+use std::fmt;
+use std::io::IsTerminal;
+use std::io;
+use std::sync;
+use std::sync::atomic;
+use std::time;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle}; // progress bars
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::ProcessedFile;
+use crate::StageResult;
+use crate::logging;
+use crate::logging::info;
+
+//------------------------------------------------------------//
+
+static PROGRESS_ENABLED: atomic::AtomicBool = atomic::AtomicBool::new(false);
+
+// the bar is redrawn this often, so the eta and the running time of the slowest file stay current
+const PROGRESS_TICK_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+// longer paths are shortened from the start, so the bar fits on one line
+const SLOWEST_FILE_PATH_WIDTH: usize = 32;
+
+//------------------------------------------------------------//
+
+/**
+ * Decides whether progress bars are shown, call after `logging::init`.
+ *
+ * They are only shown on an interactive terminal outside of CI, and not in quiet, trace or json mode,
+ * where they would get in the way of the other output.
+ */
+pub fn init() {
+    let is_enabled = io::stderr().is_terminal()
+        && env::var_os("CI").is_none()
+        && !logging::is_json()
+        && logging::is_enabled(logging::Level::Info)
+        && !logging::is_enabled(logging::Level::Trace);
+
+    PROGRESS_ENABLED.store(is_enabled, atomic::Ordering::Relaxed);
+}
+
+//------------------------------------------------------------//
+
+/**
+ * The progress bar of a stage, counting the bytes of the files it finished.
+ *
+ * Files can be started and finished from multiple threads.
+ */
+pub struct StageProgress {
+    progress_bar: Option<ProgressBar>,
+    finished_files: sync::Arc<atomic::AtomicUsize>,
+    running_files: sync::Arc<sync::Mutex<Vec<(String, time::Instant)>>>,
+}
+
+impl StageProgress {
+    pub fn new(
+        stage: &str,
+        file_count: usize,
+        total_bytes: u64,
+    ) -> StageProgress {
+        let finished_files = sync::Arc::new(atomic::AtomicUsize::new(0));
+        let running_files = sync::Arc::new(sync::Mutex::new(Vec::<(String, time::Instant)>::new()));
+
+        if !PROGRESS_ENABLED.load(atomic::Ordering::Relaxed) || file_count == 0 {
+            return StageProgress {
+                progress_bar: None,
+                finished_files,
+                running_files,
+            };
+        }
+
+        let files_key = {
+            let finished_files = sync::Arc::clone(&finished_files);
+
+            move |_: &ProgressState, writer: &mut dyn fmt::Write| {
+                let _ = write!(writer, "{}/{} files", finished_files.load(atomic::Ordering::Relaxed), file_count);
+            }
+        };
+
+        // the file that has been running the longest, which is the one holding up the stage
+        let slowest_key = {
+            let running_files = sync::Arc::clone(&running_files);
+
+            move |_: &ProgressState, writer: &mut dyn fmt::Write| {
+                let running_files = running_files.lock().expect("Failed to lock running files");
+
+                if let Some((path, start)) = running_files.iter().min_by_key(|(_, start)| return *start) {
+                    let path_chars = path.chars().collect::<Vec<char>>();
+
+                    let shortened_path = match path_chars.len() > SLOWEST_FILE_PATH_WIDTH {
+                        true => format!("...{}", path_chars[path_chars.len() - (SLOWEST_FILE_PATH_WIDTH - 3)..].iter().collect::<String>()),
+                        false => path.clone(),
+                    };
+
+                    let _ = write!(writer, "{:.1?} {}", start.elapsed(), shortened_path);
+                }
+            }
+        };
+
+        let progress_style = ProgressStyle::with_template(
+            "{prefix:>8} [{bar:20}] {files}, {binary_bytes}/{binary_total_bytes}, ETA {eta} {slowest}"
+        ).expect("Failed to create progress style")
+            .with_key("files", files_key)
+            .with_key("slowest", slowest_key)
+            .progress_chars("=> ");
+
+        let progress_bar = ProgressBar::with_draw_target(
+            Some(total_bytes),
+            ProgressDrawTarget::stderr()
+        ).with_style(progress_style).with_prefix(stage.to_string());
+
+        progress_bar.enable_steady_tick(PROGRESS_TICK_INTERVAL);
+
+        return StageProgress {
+            progress_bar: Some(progress_bar),
+            finished_files,
+            running_files,
+        };
+    }
+
+    pub fn start_file(
+        &self,
+        pack_path: &str,
+    ) {
+        if self.progress_bar.is_none() {
+            return;
+        }
+
+        self.running_files.lock().expect("Failed to lock running files").push((pack_path.to_string(), time::Instant::now()));
+    }
+
+    pub fn finish_file(
+        &self,
+        processed_file: &ProcessedFile,
+    ) {
+        let Some(progress_bar) = &self.progress_bar else {
+            return;
+        };
+
+        let pack_path = processed_file.path.to_str().expect("Failed to convert path to string");
+
+        self.running_files.lock().expect("Failed to lock running files").retain(
+            |(running_file, _)| return running_file != pack_path
+        );
+
+        self.finished_files.fetch_add(1, atomic::Ordering::Relaxed);
+        progress_bar.inc(processed_file.size_before);
+    }
+
+    /**
+     * Removes the progress bar, so the summary line of the stage takes its place.
+     */
+    pub fn finish(
+        &self,
+    ) {
+        if let Some(progress_bar) = &self.progress_bar {
+            progress_bar.finish_and_clear();
+        }
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Lists the files that took the longest to process across all stages.
+ */
+pub fn log_slowest_files(
+    stage_results: &[StageResult],
+    count: usize,
+) {
+    if count == 0 {
+        return;
+    }
+
+    let mut processed_files = stage_results.iter().flat_map(
+        |stage_result| return stage_result.files.iter().map(|file| return (stage_result.name, file))
+    ).collect::<Vec<(&str, &ProcessedFile)>>();
+
+    if processed_files.is_empty() {
+        return;
+    }
+
+    processed_files.sort_by(|(_, a), (_, b)| return b.duration.cmp(&a.duration));
+
+    info!(""); // empty line
+    info!("Slowest files:");
+
+    for (stage, processed_file) in processed_files.iter().take(count) {
+        info!(
+            "  {:>10} {:<8} {}",
+            format!("{:.2?}", processed_file.duration),
+            stage,
+            processed_file.path.to_str().expect("Failed to convert path to string")
+        );
+    }
+
+    info!(""); // empty line
+}
@@ -0,0 +1,418 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use rayon::prelude::*; // parallel iterators
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::client_jar;
+use crate::pack_mcmeta;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+/**
+ * A reference from one pack file to an asset, e.g. from a model to its parent model.
+ *
+ * The `texture directory` references of atlases point to a directory of textures in every namespace,
+ * so their asset path has `*` as its namespace and ends with `/`.
+ */
+pub struct Reference {
+    // the pack path of the file the reference is in
+    pub from: String,
+    // where in that file, e.g. `textures.layer0`
+    pub json_path: String,
+    // `model`, `texture`, ...
+    pub kind: &'static str,
+    // the resource location as written, e.g. `demo:item/ruby`
+    pub resource_location: String,
+    // the pack path the resource location points to, in the root of the pack
+    pub asset_path: String,
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Turns a resource location into the pack path of the asset it points to, e.g. `demo:item/ruby` to
+ * `assets/demo/textures/item/ruby.png` for textures.
+ */
+pub fn to_asset_path(
+    resource_location: &str,
+    asset_dir: &str,
+    extension: &str,
+) -> String {
+    let (namespace, resource_path) = resource_location.split_once(':').unwrap_or(("minecraft", resource_location));
+
+    if asset_dir.is_empty() {
+        return format!("assets/{}/{}{}", namespace, resource_path, extension);
+    }
+
+    return format!("assets/{}/{}/{}{}", namespace, asset_dir, resource_path, extension);
+}
+
+/**
+ * Whether a resource location, with or without a namespace, only has the characters Minecraft allows in it.
+ */
+pub fn is_valid_resource_location(
+    resource_location: &str,
+) -> bool {
+    let (namespace, resource_path) = resource_location.split_once(':').unwrap_or(("minecraft", resource_location));
+
+    return !namespace.is_empty() && namespace.chars().all(|character| return matches!(character, 'a'..='z' | '0'..='9' | '_' | '-' | '.'))
+        && !resource_path.is_empty() && resource_path.chars().all(|character| return matches!(character, 'a'..='z' | '0'..='9' | '_' | '-' | '.' | '/'));
+}
+
+/**
+ * Collects the references of files, keeping the kind of reference and the json path it was found at.
+ */
+struct ReferenceCollector<'a> {
+    from: &'a str,
+    references: Vec<Reference>,
+}
+
+impl ReferenceCollector<'_> {
+    /**
+     * Adds a reference if `value` is a resource location, pointing to `<asset_dir>/<path><extension>` in its namespace.
+     */
+    fn add(
+        &mut self,
+        json_path: String,
+        kind: &'static str,
+        value: &serde_json::Value,
+        asset_dir: &str,
+        extension: &str,
+    ) {
+        let Some(resource_location) = value.as_str() else {
+            return;
+        };
+
+        self.references.push(
+            Reference {
+                from: self.from.to_string(),
+                json_path,
+                kind,
+                resource_location: resource_location.to_string(),
+                asset_path: to_asset_path(resource_location, asset_dir, extension),
+            }
+        );
+    }
+
+    fn add_model(
+        &mut self,
+        json_path: String,
+        value: &serde_json::Value,
+    ) {
+        self.add(json_path, "model", value, "models", ".json");
+    }
+
+    fn add_texture(
+        &mut self,
+        json_path: String,
+        value: &serde_json::Value,
+    ) {
+        self.add(json_path, "texture", value, "textures", ".png");
+    }
+
+    fn collect_blockstate(
+        &mut self,
+        blockstate: &serde_json::Value,
+    ) {
+        let add_variant = |collector: &mut Self, json_path: String, variant: &serde_json::Value| {
+            match variant {
+                serde_json::Value::Array(models) => {
+                    for (index, model) in models.iter().enumerate() {
+                        collector.add_model(format!("{}[{}].model", json_path, index), model.get("model").unwrap_or(&serde_json::Value::Null));
+                    }
+                },
+                _ => collector.add_model(format!("{}.model", json_path), variant.get("model").unwrap_or(&serde_json::Value::Null)),
+            }
+        };
+
+        for (name, variant) in blockstate.get("variants").and_then(|variants| return variants.as_object()).into_iter().flatten() {
+            add_variant(self, format!("variants.{}", name), variant);
+        }
+
+        for (index, part) in blockstate.get("multipart").and_then(|multipart| return multipart.as_array()).into_iter().flatten().enumerate() {
+            if let Some(apply) = part.get("apply") {
+                add_variant(self, format!("multipart[{}].apply", index), apply);
+            }
+        }
+    }
+
+    fn collect_model(
+        &mut self,
+        model: &serde_json::Value,
+    ) {
+        if let Some(parent) = model.get("parent") {
+            // `builtin/...` parents are made by the game, not read from a file
+            if !parent.as_str().is_some_and(|parent| return parent.trim_start_matches("minecraft:").starts_with("builtin/")) {
+                self.add_model("parent".to_string(), parent);
+            }
+        }
+
+        for (name, texture) in model.get("textures").and_then(|textures| return textures.as_object()).into_iter().flatten() {
+            // `#name` refers to another texture variable of the model
+            let texture = texture.get("sprite").unwrap_or(texture);
+
+            if !texture.as_str().is_some_and(|texture| return texture.starts_with('#')) {
+                self.add_texture(format!("textures.{}", name), texture);
+            }
+        }
+
+        for (index, item_override) in model.get("overrides").and_then(|overrides| return overrides.as_array()).into_iter().flatten().enumerate() {
+            if let Some(model) = item_override.get("model") {
+                self.add_model(format!("overrides[{}].model", index), model);
+            }
+        }
+    }
+
+    /**
+     * Item model definitions nest models in conditions, selects and range dispatches, so every `model` and `base` is a reference.
+     */
+    fn collect_item_definition(
+        &mut self,
+        json_path: String,
+        value: &serde_json::Value,
+    ) {
+        match value {
+            serde_json::Value::Object(object) => {
+                for (key, value) in object {
+                    let value_path = match json_path.is_empty() {
+                        true => key.clone(),
+                        false => format!("{}.{}", json_path, key),
+                    };
+
+                    if (key == "model" || key == "base") && value.is_string() {
+                        self.add_model(value_path, value);
+                    } else {
+                        self.collect_item_definition(value_path, value);
+                    }
+                }
+            },
+            serde_json::Value::Array(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    self.collect_item_definition(format!("{}[{}]", json_path, index), value);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn collect_particle(
+        &mut self,
+        particle: &serde_json::Value,
+    ) {
+        for (index, texture) in particle.get("textures").and_then(|textures| return textures.as_array()).into_iter().flatten().enumerate() {
+            self.add(format!("textures[{}]", index), "particle texture", texture, "textures/particle", ".png");
+        }
+    }
+
+    fn collect_atlas(
+        &mut self,
+        atlas: &serde_json::Value,
+    ) {
+        for (index, source) in atlas.get("sources").and_then(|sources| return sources.as_array()).into_iter().flatten().enumerate() {
+            let source_type = source.get("type").and_then(|source_type| return source_type.as_str()).unwrap_or_default();
+
+            match source_type.trim_start_matches("minecraft:") {
+                "directory" => {
+                    if let Some(directory) = source.get("source").and_then(|directory| return directory.as_str()) {
+                        self.references.push(
+                            Reference {
+                                from: self.from.to_string(),
+                                json_path: format!("sources[{}].source", index),
+                                kind: "texture directory",
+                                resource_location: directory.to_string(),
+                                asset_path: format!("assets/*/textures/{}/", directory.trim_end_matches('/')),
+                            }
+                        );
+                    }
+                },
+                "single" | "unstitch" => {
+                    self.add_texture(format!("sources[{}].resource", index), source.get("resource").unwrap_or(&serde_json::Value::Null));
+                },
+                "paletted_permutations" => {
+                    for (texture_index, texture) in source.get("textures").and_then(|textures| return textures.as_array()).into_iter().flatten().enumerate() {
+                        self.add_texture(format!("sources[{}].textures[{}]", index, texture_index), texture);
+                    }
+
+                    self.add_texture(format!("sources[{}].palette_key", index), source.get("palette_key").unwrap_or(&serde_json::Value::Null));
+
+                    for (name, palette) in source.get("permutations").and_then(|permutations| return permutations.as_object()).into_iter().flatten() {
+                        self.add_texture(format!("sources[{}].permutations.{}", index, name), palette);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn collect_font(
+        &mut self,
+        font: &serde_json::Value,
+    ) {
+        for (index, provider) in font.get("providers").and_then(|providers| return providers.as_array()).into_iter().flatten().enumerate() {
+            let provider_type = provider.get("type").and_then(|provider_type| return provider_type.as_str()).unwrap_or_default();
+            let json_path = format!("providers[{}]", index);
+
+            // font files are written with their extension, e.g. `minecraft:font/ascii.png`
+            match provider_type.trim_start_matches("minecraft:") {
+                "bitmap" => self.add(format!("{}.file", json_path), "font texture", provider.get("file").unwrap_or(&serde_json::Value::Null), "textures", ""),
+                "ttf" => self.add(format!("{}.file", json_path), "font file", provider.get("file").unwrap_or(&serde_json::Value::Null), "font", ""),
+                "unihex" => self.add(format!("{}.hex_file", json_path), "font file", provider.get("hex_file").unwrap_or(&serde_json::Value::Null), "", ""),
+                "reference" => self.add(format!("{}.id", json_path), "font", provider.get("id").unwrap_or(&serde_json::Value::Null), "font", ".json"),
+                _ => {},
+            }
+        }
+    }
+
+    fn collect_sounds(
+        &mut self,
+        sounds: &serde_json::Value,
+    ) {
+        for (event, sound_event) in sounds.as_object().into_iter().flatten() {
+            for (index, sound) in sound_event.get("sounds").and_then(|sounds| return sounds.as_array()).into_iter().flatten().enumerate() {
+                match sound {
+                    // `event` sounds play another sound event instead of a file
+                    serde_json::Value::Object(sound) if sound.get("type").and_then(|sound_type| return sound_type.as_str()) == Some("event") => {},
+                    serde_json::Value::Object(sound) => {
+                        self.add(format!("{}.sounds[{}].name", event, index), "sound", sound.get("name").unwrap_or(&serde_json::Value::Null), "sounds", ".ogg");
+                    },
+                    _ => self.add(format!("{}.sounds[{}]", event, index), "sound", sound, "sounds", ".ogg"),
+                }
+            }
+        }
+    }
+
+    fn collect_equipment(
+        &mut self,
+        equipment: &serde_json::Value,
+    ) {
+        for (layer_type, layers) in equipment.get("layers").and_then(|layers| return layers.as_object()).into_iter().flatten() {
+            for (index, layer) in layers.as_array().into_iter().flatten().enumerate() {
+                self.add(
+                    format!("layers.{}[{}].texture", layer_type, index),
+                    "texture",
+                    layer.get("texture").unwrap_or(&serde_json::Value::Null),
+                    format!("textures/entity/equipment/{}", layer_type).as_str(),
+                    ".png",
+                );
+            }
+        }
+    }
+}
+
+/**
+ * Returns the overlay directory a pack path is in (e.g. `overlay/`), or an empty string for the pack root.
+ */
+pub fn layer_of(
+    pack_path: &str,
+) -> &str {
+    return &pack_path[..pack_path.len() - pack_mcmeta::strip_overlay_dir(pack_path).len()];
+}
+
+/**
+ * Reads the references of every blockstate, model, item model definition, particle, atlas, font, equipment
+ * and `sounds.json` in a pack (and its overlays).
+ */
+pub fn collect_references(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+) -> Vec<Reference> {
+    return pack_paths.par_iter().flat_map(
+        |pack_path| {
+            let components = pack_mcmeta::strip_overlay_dir(pack_path).split('/').collect::<Vec<&str>>();
+
+            if components.len() < 3 || components[0] != "assets" || !pack_path.ends_with(".json") {
+                return Vec::new();
+            }
+
+            let Some(json) = validate::read_json(&pack_dir.join(pack_path)) else {
+                return Vec::new();
+            };
+
+            let mut collector = ReferenceCollector { from: pack_path, references: Vec::new() };
+
+            match components[2] {
+                "blockstates" => collector.collect_blockstate(&json),
+                "models" => collector.collect_model(&json),
+                "items" => collector.collect_item_definition(String::new(), &json),
+                "particles" => collector.collect_particle(&json),
+                "atlases" => collector.collect_atlas(&json),
+                "font" => collector.collect_font(&json),
+                "equipment" => collector.collect_equipment(&json),
+                "sounds.json" => collector.collect_sounds(&json),
+                _ => {},
+            }
+
+            return collector.references;
+        }
+    ).collect();
+}
+
+/**
+ * Reports references to assets that are neither in the pack nor, for `minecraft:` assets, in the client jar.
+ *
+ * A reference from an overlay can point to a file in the same overlay or in the pack root.
+ * Without a client jar, references to `minecraft:` assets the pack does not have are not checked, and neither are
+ * references to `minecraft:` sounds at all.
+ */
+pub fn check_references(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+    client_jar: Option<&client_jar::ClientJar>,
+    validation: &mut validate::Validation,
+) {
+    let pack_path_set = pack_paths.iter().map(|pack_path| return pack_path.as_str()).collect::<collections::HashSet<&str>>();
+
+    for reference in collect_references(pack_dir, pack_paths) {
+        // a directory of an atlas does not have to have textures in it
+        if reference.kind == "texture directory" {
+            continue;
+        }
+
+        let overlay_asset_path = format!("{}{}", layer_of(&reference.from), reference.asset_path);
+
+        if pack_path_set.contains(reference.asset_path.as_str()) || pack_path_set.contains(overlay_asset_path.as_str()) {
+            continue;
+        }
+
+        let is_vanilla = reference.asset_path.starts_with("assets/minecraft/");
+
+        // vanilla sounds are downloaded with the other game assets, they are not in the client jar
+        if is_vanilla && reference.kind == "sound" {
+            continue;
+        }
+
+        let location = match (is_vanilla, client_jar) {
+            (true, Some(client_jar)) if client_jar.contains(&reference.asset_path) => continue,
+            (true, Some(_)) => "the pack or the client jar",
+            (true, None) => continue,
+            (false, _) => "the pack",
+        };
+
+        validation.error(
+            &reference.from,
+            format!(
+                "`{}` references the {} `{}`, but {} is not in {}",
+                reference.json_path,
+                reference.kind,
+                reference.resource_location,
+                reference.asset_path,
+                location
+            )
+        );
+    }
+}
@@ -0,0 +1,735 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::TraverseDirLookFor;
+use crate::traverse_dir;
+use crate::StageResult;
+use crate::bitmap_font;
+use crate::logging;
+use crate::logging::info;
+use crate::pack_mcmeta;
+
+//------------------------------------------------------------//
+
+/**
+ * Formats a byte count for humans (e.g. `1.50 MiB`).
+ */
+pub fn format_bytes(
+    bytes: u64,
+) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit_index = 0;
+
+    while value >= 1024.0 && unit_index < units.len() - 1 {
+        value /= 1024.0;
+        unit_index += 1;
+    }
+
+    if unit_index == 0 {
+        return format!("{} B", bytes);
+    }
+
+    return format!("{:.2} {}", value, units[unit_index]);
+}
+
+/**
+ * Formats how much smaller `size_after` is than `size_before` (e.g. `1.50 KiB (12.5%)`).
+ */
+pub fn format_savings(
+    size_before: u64,
+    size_after: u64,
+) -> String {
+    let saved_bytes = size_before as i64 - size_after as i64;
+
+    let saved_percentage = match size_before {
+        0 => 0.0,
+        _ => saved_bytes as f64 / size_before as f64 * 100.0,
+    };
+
+    let formatted_saved_bytes = match saved_bytes < 0 {
+        true => format!("-{}", format_bytes(saved_bytes.unsigned_abs())),
+        false => format_bytes(saved_bytes as u64),
+    };
+
+    return format!("{} ({:.1}%)", formatted_saved_bytes, saved_percentage);
+}
+
+fn dir_size(
+    dir: &path::Path,
+    predicate: Option<&dyn Fn(&path::Path) -> bool>,
+) -> u64 {
+    return traverse_dir(dir, &TraverseDirLookFor::Files, predicate).iter().map(
+        |file| return fs::metadata(file).expect("Failed to read metadata").len()
+    ).sum();
+}
+
+/**
+ * Lists the pack path and size of every file in a directory, for `build_report`.
+ */
+pub fn list_file_sizes(
+    dir: &path::Path,
+) -> Vec<(String, u64)> {
+    return traverse_dir(dir, &TraverseDirLookFor::Files, None).iter().map(
+        |file| {
+            return (
+                crate::hash::to_pack_path(file.strip_prefix(dir).expect("Failed to strip prefix")),
+                fs::metadata(file).expect("Failed to read metadata").len(),
+            );
+        }
+    ).collect();
+}
+
+/**
+ * Lists the items of the input directory that are left out of the pack.
+ */
+pub fn list_excluded_items(
+    input_dir: &path::Path,
+) -> Vec<path::PathBuf> {
+    return traverse_dir(input_dir, &TraverseDirLookFor::All, None).into_iter().filter(
+        |item| return !crate::is_pack_file(item)
+    ).map(
+        |item| return item.strip_prefix(input_dir).expect("Failed to strip prefix").to_path_buf()
+    ).collect::<Vec<path::PathBuf>>();
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Runs every stage on the pack files in memory and reports how much each of them would save.
+ *
+ * Nothing is written besides the reports, the output directory is never touched. If `is_zipped` is set,
+ * the zip file is built in memory too. In json mode, the summary is a single `dry_run` event.
+ * Returns the report of the run and the SHA-1 hash of the zip file, if one was built.
+ */
+pub fn run_dry_run(
+    input_dir: &path::Path,
+    is_zipped: bool,
+    report_paths: &[&path::PathBuf],
+    top_files: usize,
+    pruned_paths: &collections::HashSet<String>,
+    pruned_lang_keys: &collections::HashMap<String, Vec<String>>,
+    previous_code_points: Option<&bitmap_font::AssignedCodePoints>,
+) -> (Report, Option<String>) {
+    let mut pack_files = crate::read_input_pack_files(input_dir, pruned_paths);
+
+    let stage_results = crate::process_pack_files(&mut pack_files, pruned_lang_keys, previous_code_points);
+
+    let pack_size_before = dir_size(input_dir, Some(&|item| return crate::is_pack_file(item)));
+
+    let zip_hashes = match is_zipped {
+        true => Some(crate::zip_pack_files(&pack_files).1),
+        false => None,
+    };
+
+    let output_files = pack_files.iter().map(
+        |(pack_path, file_contents)| return (pack_path.clone(), file_contents.len() as u64)
+    ).collect::<Vec<(String, u64)>>();
+
+    let report = build_report(input_dir, &output_files, &stage_results, zip_hashes.as_ref().map(|zip_hashes| return zip_hashes.size), top_files);
+
+    let excluded_items = list_excluded_items(input_dir).iter().map(
+        |excluded_item| return excluded_item.to_str().expect("Failed to convert excluded item to string").to_string()
+    ).collect::<Vec<String>>();
+
+    let mut pruned_paths = pruned_paths.iter().collect::<Vec<&String>>();
+    pruned_paths.sort();
+
+    if logging::is_json() {
+        logging::event(
+            logging::Level::Info,
+            "dry_run",
+            serde_json::json!({
+                "stages": report.stages.iter().map(group_to_json).collect::<Vec<serde_json::Value>>(),
+                "size_before": pack_size_before,
+                "size_after": report.size_after,
+                "zip_size": report.zip_size,
+                "excluded_items": excluded_items,
+                "pruned_paths": pruned_paths,
+            }),
+        );
+    } else {
+        print_dry_run_summary(&report, pack_size_before, &excluded_items, &pruned_paths);
+    }
+
+    if !report_paths.is_empty() {
+        info!("");
+
+        write_reports(&report, report_paths);
+    }
+
+    return (report, zip_hashes.map(|zip_hashes| return zip_hashes.sha1));
+}
+
+fn print_dry_run_summary(
+    report: &Report,
+    pack_size_before: u64,
+    excluded_items: &[String],
+    pruned_paths: &[&String],
+) {
+    info!("");
+    info!("Dry run, nothing was written to the output directory.");
+    info!("");
+
+    info!("{:<10} {:>8} {:>14} {:>14} {:>22} {:>12}", "Stage", "Files", "Before", "After", "Saved", "Time");
+
+    for stage in &report.stages {
+        info!(
+            "{:<10} {:>8} {:>14} {:>14} {:>22} {:>12}",
+            stage.name,
+            stage.files,
+            format_bytes(stage.size_before),
+            format_bytes(stage.size_after),
+            format_savings(stage.size_before, stage.size_after),
+            format!("{:.2?}", stage.duration),
+        );
+    }
+
+    info!("");
+    info!("Pack size: {} -> {} (saved {})", format_bytes(pack_size_before), format_bytes(report.size_after), format_savings(pack_size_before, report.size_after));
+
+    if let Some(zip_size) = report.zip_size {
+        info!(
+            "Zip file size: {} (saved {} compared to the optimized files, {} compared to the input)",
+            format_bytes(zip_size),
+            format_savings(report.size_after, zip_size),
+            format_savings(pack_size_before, zip_size),
+        );
+    }
+
+    info!("");
+
+    if excluded_items.is_empty() {
+        info!("No files would be excluded.");
+    } else {
+        info!("{} items would be excluded:", excluded_items.len());
+
+        for excluded_item in excluded_items {
+            info!("  {}", excluded_item);
+        }
+    }
+
+    if !pruned_paths.is_empty() {
+        info!("");
+        info!("{} unused or vanilla files would be left out:", pruned_paths.len());
+
+        for pruned_path in pruned_paths {
+            info!("  {}", pruned_path);
+        }
+    }
+}
+
+//------------------------------------------------------------//
+
+pub struct FileReport {
+    pub path: String,
+    pub stage: Option<&'static str>,
+    pub size_before: u64,
+    pub size_after: u64,
+    pub duration: Option<std::time::Duration>,
+}
+
+pub struct GroupReport {
+    pub name: String,
+    pub files: usize,
+    pub size_before: u64,
+    pub size_after: u64,
+    pub duration: std::time::Duration,
+}
+
+pub struct Report {
+    pub files: Vec<FileReport>,
+    pub stages: Vec<GroupReport>,
+    pub namespaces: Vec<GroupReport>,
+    pub categories: Vec<GroupReport>,
+    pub size_before: u64,
+    pub size_after: u64,
+    pub zip_size: Option<u64>,
+    pub top_files: usize,
+}
+
+/**
+ * Returns the namespace of a pack path (`assets/<namespace>/...`), or `(root)` for files outside of `assets/`.
+ *
+ * Files in overlays count towards the namespace they are in.
+ */
+pub fn namespace_of(
+    pack_path: &str,
+) -> String {
+    let mut components = pack_mcmeta::strip_overlay_dir(pack_path).split('/');
+
+    if components.next() == Some("assets") {
+        if let (Some(namespace), Some(_)) = (components.next(), components.next()) {
+            return namespace.to_string();
+        }
+    }
+
+    return "(root)".to_string();
+}
+
+/**
+ * Returns the asset category of a pack path (textures, models, sounds, shaders, lang or other).
+ */
+pub fn category_of(
+    pack_path: &str,
+) -> &'static str {
+    let components = pack_mcmeta::strip_overlay_dir(pack_path).split('/').collect::<Vec<&str>>();
+
+    if components.len() < 3 || components[0] != "assets" {
+        return "other";
+    }
+
+    // `sounds.json` sits right inside of the namespace
+    if components.len() == 3 && components[2] == "sounds.json" {
+        return "sounds";
+    }
+
+    return match components[2] {
+        "textures" => "textures",
+        "models" | "blockstates" | "items" => "models",
+        "sounds" => "sounds",
+        "shaders" => "shaders",
+        "lang" => "lang",
+        _ => "other",
+    };
+}
+
+fn group_files(
+    files: &[FileReport],
+    group_name: &dyn Fn(&FileReport) -> String,
+) -> Vec<GroupReport> {
+    let mut groups: Vec<GroupReport> = Vec::new();
+
+    for file in files {
+        let name = group_name(file);
+
+        let group_index = match groups.iter().position(|group| return group.name == name) {
+            Some(group_index) => group_index,
+            None => {
+                groups.push(
+                    GroupReport {
+                        name,
+                        files: 0,
+                        size_before: 0,
+                        size_after: 0,
+                        duration: std::time::Duration::ZERO,
+                    }
+                );
+
+                groups.len() - 1
+            },
+        };
+
+        let group = &mut groups[group_index];
+        group.files += 1;
+        group.size_before += file.size_before;
+        group.size_after += file.size_after;
+        group.duration += file.duration.unwrap_or_default();
+    }
+
+    groups.sort_by(|a, b| return b.size_after.cmp(&a.size_after));
+
+    return groups;
+}
+
+/**
+ * Collects the size of every file before and after processing, along with the stage that changed it.
+ *
+ * `output_files` are the pack paths and sizes of the files after all stages ran on them (see `list_file_sizes`).
+ */
+pub fn build_report(
+    input_dir: &path::Path,
+    output_files: &[(String, u64)],
+    stage_results: &[StageResult],
+    zip_size: Option<u64>,
+    top_files: usize,
+) -> Report {
+    // the first stage that processed a file is the one it is reported under
+    let mut processed_files = collections::HashMap::<String, (&str, &crate::ProcessedFile)>::new();
+
+    for stage_result in stage_results {
+        for processed_file in &stage_result.files {
+            processed_files.entry(crate::hash::to_pack_path(&processed_file.path)).or_insert((stage_result.name, processed_file));
+        }
+    }
+
+    let mut files = output_files.iter().map(
+        |(pack_path, size_after)| {
+            let processed_file = processed_files.get(pack_path).copied();
+
+            return FileReport {
+                path: pack_path.clone(),
+                stage: processed_file.map(|(stage, _)| return stage),
+                // files generated by a stage (e.g. font sheets) were not in the input
+                size_before: fs::metadata(input_dir.join(pack_path)).map_or(0, |metadata| return metadata.len()),
+                size_after: *size_after,
+                duration: processed_file.map(|(_, processed_file)| return processed_file.duration),
+            };
+        }
+    ).collect::<Vec<FileReport>>();
+
+    files.sort_by(|a, b| return a.path.cmp(&b.path));
+
+    let stages = stage_results.iter().map(
+        |stage_result| {
+            return GroupReport {
+                name: stage_result.name.to_string(),
+                files: stage_result.files.len(),
+                size_before: stage_result.files.iter().map(|file| return file.size_before).sum(),
+                size_after: stage_result.files.iter().map(|file| return file.size_after).sum(),
+                duration: stage_result.duration,
+            };
+        }
+    ).collect::<Vec<GroupReport>>();
+
+    return Report {
+        namespaces: group_files(&files, &|file| return namespace_of(&file.path)),
+        categories: group_files(&files, &|file| return category_of(&file.path).to_string()),
+        size_before: files.iter().map(|file| return file.size_before).sum(),
+        size_after: files.iter().map(|file| return file.size_after).sum(),
+        files,
+        stages,
+        zip_size,
+        top_files,
+    };
+}
+
+//------------------------------------------------------------//
+
+pub fn group_to_json(
+    group: &GroupReport,
+) -> serde_json::Value {
+    return serde_json::json!({
+        "name": group.name,
+        "files": group.files,
+        "size_before": group.size_before,
+        "size_after": group.size_after,
+        "duration_ms": group.duration.as_secs_f64() * 1000.0,
+    });
+}
+
+pub fn to_json(
+    report: &Report,
+) -> serde_json::Value {
+    let files = report.files.iter().map(
+        |file| {
+            return serde_json::json!({
+                "path": file.path,
+                "stage": file.stage,
+                "size_before": file.size_before,
+                "size_after": file.size_after,
+                "duration_ms": file.duration.map(|duration| return duration.as_secs_f64() * 1000.0),
+            });
+        }
+    ).collect::<Vec<serde_json::Value>>();
+
+    return serde_json::json!({
+        "totals": {
+            "files": report.files.len(),
+            "size_before": report.size_before,
+            "size_after": report.size_after,
+            "zip_size": report.zip_size,
+            "duration_ms": report.stages.iter().map(|stage| return stage.duration.as_secs_f64() * 1000.0).sum::<f64>(),
+        },
+        "stages": report.stages.iter().map(group_to_json).collect::<Vec<serde_json::Value>>(),
+        "namespaces": report.namespaces.iter().map(group_to_json).collect::<Vec<serde_json::Value>>(),
+        "categories": report.categories.iter().map(group_to_json).collect::<Vec<serde_json::Value>>(),
+        "largest_files": largest_files(report).iter().map(|file| return file.path.clone()).collect::<Vec<String>>(),
+        "files": files,
+    });
+}
+
+pub fn largest_files(
+    report: &Report,
+) -> Vec<&FileReport> {
+    let mut largest_files = report.files.iter().collect::<Vec<&FileReport>>();
+
+    largest_files.sort_by(|a, b| return b.size_after.cmp(&a.size_after));
+    largest_files.truncate(report.top_files);
+
+    return largest_files;
+}
+
+//------------------------------------------------------------//
+
+// a table cell holding the displayed text and, for numbers, the raw value to sort by
+struct Cell {
+    text: String,
+    sort_value: Option<f64>,
+}
+
+struct Table {
+    title: String,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+fn text_cell(
+    text: &str,
+) -> Cell {
+    return Cell {
+        text: text.to_string(),
+        sort_value: None,
+    };
+}
+
+fn bytes_cell(
+    bytes: u64,
+) -> Cell {
+    return Cell {
+        text: format_bytes(bytes),
+        sort_value: Some(bytes as f64),
+    };
+}
+
+fn savings_cell(
+    size_before: u64,
+    size_after: u64,
+) -> Cell {
+    return Cell {
+        text: format_savings(size_before, size_after),
+        sort_value: Some(size_before as f64 - size_after as f64),
+    };
+}
+
+fn duration_cell(
+    duration: Option<std::time::Duration>,
+) -> Cell {
+    return match duration {
+        Some(duration) => Cell {
+            text: format!("{:.2?}", duration),
+            sort_value: Some(duration.as_secs_f64()),
+        },
+        None => text_cell(""),
+    };
+}
+
+fn group_table(
+    title: &str,
+    name_header: &'static str,
+    groups: &[GroupReport],
+) -> Table {
+    return Table {
+        title: title.to_string(),
+        headers: vec![name_header, "Files", "Before", "After", "Saved", "Time"],
+        rows: groups.iter().map(
+            |group| {
+                return vec![
+                    text_cell(&group.name),
+                    Cell { text: group.files.to_string(), sort_value: Some(group.files as f64) },
+                    bytes_cell(group.size_before),
+                    bytes_cell(group.size_after),
+                    savings_cell(group.size_before, group.size_after),
+                    duration_cell(Some(group.duration)),
+                ];
+            }
+        ).collect::<Vec<Vec<Cell>>>(),
+    };
+}
+
+fn file_table(
+    title: &str,
+    files: &[&FileReport],
+) -> Table {
+    return Table {
+        title: title.to_string(),
+        headers: vec!["File", "Stage", "Before", "After", "Saved", "Time"],
+        rows: files.iter().map(
+            |file| {
+                return vec![
+                    text_cell(&file.path),
+                    text_cell(file.stage.unwrap_or("")),
+                    bytes_cell(file.size_before),
+                    bytes_cell(file.size_after),
+                    savings_cell(file.size_before, file.size_after),
+                    duration_cell(file.duration),
+                ];
+            }
+        ).collect::<Vec<Vec<Cell>>>(),
+    };
+}
+
+fn report_tables(
+    report: &Report,
+) -> Vec<Table> {
+    let mut totals_rows = vec![
+        vec![text_cell("Files"), bytes_cell(report.size_before), bytes_cell(report.size_after), savings_cell(report.size_before, report.size_after)],
+    ];
+
+    if let Some(zip_size) = report.zip_size {
+        totals_rows.push(vec![text_cell("Zip file"), bytes_cell(report.size_before), bytes_cell(zip_size), savings_cell(report.size_before, zip_size)]);
+    }
+
+    return vec![
+        Table {
+            title: "Totals".to_string(),
+            headers: vec!["", "Before", "After", "Saved"],
+            rows: totals_rows,
+        },
+        group_table("Stages", "Stage", &report.stages),
+        file_table(format!("Largest Files (top {})", report.top_files).as_str(), &largest_files(report)),
+        group_table("Namespaces", "Namespace", &report.namespaces),
+        group_table("Categories", "Category", &report.categories),
+        file_table("All Files", &report.files.iter().collect::<Vec<&FileReport>>()),
+    ];
+}
+
+pub fn to_markdown(
+    report: &Report,
+) -> String {
+    let mut markdown = String::from("# Optimization Report\n");
+
+    for table in report_tables(report) {
+        markdown.push_str(&format!("\n## {}\n\n", table.title));
+        markdown.push_str(&format!("| {} |\n", table.headers.join(" | ")));
+        markdown.push_str(&format!("|{}\n", table.headers.iter().map(|_| return "---|").collect::<String>()));
+
+        for row in &table.rows {
+            let cells = row.iter().map(
+                |cell| return cell.text.replace('|', "\\|")
+            ).collect::<Vec<String>>();
+
+            markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+
+    return markdown;
+}
+
+fn escape_html(
+    text: &str,
+) -> String {
+    return text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+}
+
+// sorts a table by the clicked column, using `data-value` for numbers
+const HTML_SORT_SCRIPT: &str = r#"
+document.querySelectorAll("th").forEach(function (header) {
+    header.addEventListener("click", function () {
+        var table = header.closest("table");
+        var body = table.tBodies[0];
+        var column = Array.prototype.indexOf.call(header.parentNode.children, header);
+        var ascending = header.dataset.order !== "ascending";
+        header.dataset.order = ascending ? "ascending" : "descending";
+        var value = function (row) {
+            var cell = row.children[column];
+            return cell.dataset.value !== undefined ? parseFloat(cell.dataset.value) : cell.textContent;
+        };
+        Array.from(body.rows).sort(function (a, b) {
+            var left = value(a), right = value(b);
+            var order = left < right ? -1 : left > right ? 1 : 0;
+            return ascending ? order : -order;
+        }).forEach(function (row) { body.appendChild(row); });
+    });
+});
+"#;
+
+const HTML_STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.75em; text-align: left; }
+th { background: #eee; cursor: pointer; user-select: none; }
+td[data-value] { text-align: right; }
+"#;
+
+pub fn to_html(
+    report: &Report,
+) -> String {
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Optimization Report</title>\n");
+    html.push_str(&format!("<style>{}</style>\n", HTML_STYLE));
+    html.push_str("</head>\n<body>\n<h1>Optimization Report</h1>\n");
+
+    for table in report_tables(report) {
+        html.push_str(&format!("<h2>{}</h2>\n<table>\n<thead><tr>", escape_html(&table.title)));
+
+        for header in &table.headers {
+            html.push_str(&format!("<th>{}</th>", escape_html(header)));
+        }
+
+        html.push_str("</tr></thead>\n<tbody>\n");
+
+        for row in &table.rows {
+            html.push_str("<tr>");
+
+            for cell in row {
+                match cell.sort_value {
+                    Some(sort_value) => html.push_str(&format!("<td data-value=\"{}\">{}</td>", sort_value, escape_html(&cell.text))),
+                    None => html.push_str(&format!("<td>{}</td>", escape_html(&cell.text))),
+                }
+            }
+
+            html.push_str("</tr>\n");
+        }
+
+        html.push_str("</tbody>\n</table>\n");
+    }
+
+    html.push_str(&format!("<script>{}</script>\n</body>\n</html>\n", HTML_SORT_SCRIPT));
+
+    return html;
+}
+
+/**
+ * Picks the report format from the file extension (`.json`, `.md` or `.html`).
+ */
+fn report_format(
+    report_path: &path::Path,
+) -> Option<&'static str> {
+    let extension = report_path.extension().and_then(|extension| return extension.to_str()).unwrap_or("");
+
+    return match extension.to_lowercase().as_str() {
+        "json" => Some("json"),
+        "md" | "markdown" => Some("markdown"),
+        "html" | "htm" => Some("html"),
+        _ => None,
+    };
+}
+
+/**
+ * Exits the program if the format of a report cannot be told from its file extension.
+ */
+pub fn validate_report_paths(
+    report_paths: &[&path::PathBuf],
+) {
+    for report_path in report_paths {
+        if report_format(report_path).is_none() {
+            crate::fail_program(
+                format!(
+                    "Unknown report format for {} (use .json, .md or .html)",
+                    report_path.to_str().expect("Failed to convert report path to string")
+                ).as_str()
+            );
+        }
+    }
+}
+
+pub fn write_reports(
+    report: &Report,
+    report_paths: &[&path::PathBuf],
+) {
+    for report_path in report_paths {
+        let report_contents = match report_format(report_path) {
+            Some("json") => serde_json::to_string_pretty(&to_json(report)).expect("Failed to convert report to string"),
+            Some("markdown") => to_markdown(report),
+            _ => to_html(report),
+        };
+
+        fs::write(report_path, report_contents).expect("Failed to write report");
+
+        info!("Wrote report to {}", report_path.to_str().expect("Failed to convert report path to string"));
+    }
+}
@@ -0,0 +1,321 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::any;
+use std::collections;
+use std::fs;
+use std::panic;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use tempdir::TempDir; // temporary directories
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::hash;
+use crate::server_properties;
+use crate::source;
+use crate::logging::{info, warning};
+
+//------------------------------------------------------------//
+
+struct BuiltPack {
+    zip_file_path: path::PathBuf,
+    zip_hashes: hash::Hashes,
+    input_fingerprint: Vec<(path::PathBuf, u64, Option<std::time::SystemTime>)>,
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Lists every item of the input directory with its size and modification time, used to detect changes.
+ *
+ * Items removed while the directory is listed (e.g. the temporary files of an editor saving) are left out.
+ */
+fn fingerprint_dir(
+    dir: &path::Path,
+) -> Vec<(path::PathBuf, u64, Option<std::time::SystemTime>)> {
+    let mut fingerprint: Vec<(path::PathBuf, u64, Option<std::time::SystemTime>)> = Vec::new();
+
+    let Ok(dir_items) = fs::read_dir(dir) else {
+        return fingerprint;
+    };
+
+    for dir_item in dir_items.flatten() {
+        let item = dir_item.path();
+
+        let Ok(metadata) = fs::metadata(&item) else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            fingerprint.extend(fingerprint_dir(&item));
+        }
+
+        fingerprint.push((item, metadata.len(), metadata.modified().ok()));
+    }
+
+    fingerprint.sort_by(|a, b| return a.0.cmp(&b.0));
+
+    return fingerprint;
+}
+
+/**
+ * Runs the full pipeline on the input directory and zips the result into `build_dir`.
+ */
+fn build_pack(
+    input_dir: &path::Path,
+    build_dir: &path::Path,
+    zip_name: &str,
+) -> BuiltPack {
+    // fingerprint before processing so changes made during the build trigger another rebuild
+    let input_fingerprint = fingerprint_dir(input_dir);
+
+    let temp_dir = TempDir::new("ms-rpo").expect("Failed to create temporary directory");
+
+    crate::process_pack(input_dir, temp_dir.path(), &collections::HashSet::new(), &collections::HashMap::new(), None);
+
+    let zip_file_path = build_dir.join(zip_name);
+
+    // the previous zip file is served until the new one is complete
+    let built_zip_file_path = hash::append_to_file_name(&zip_file_path, ".tmp");
+    let zip_hashes = crate::zip_dir(temp_dir.path(), &built_zip_file_path);
+
+    fs::rename(&built_zip_file_path, &zip_file_path).expect("Failed to replace zip file");
+
+    temp_dir.close().expect("Failed to remove temporary directory");
+
+    return BuiltPack {
+        zip_file_path,
+        zip_hashes,
+        input_fingerprint,
+    };
+}
+
+/**
+ * Returns the message a caught panic was raised with.
+ */
+fn panic_message(
+    panic_payload: &(dyn any::Any + Send),
+) -> String {
+    if let Some(message) = panic_payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+
+    return panic_payload.downcast_ref::<String>().cloned().unwrap_or("Unknown error".to_string());
+}
+
+fn header(
+    name: &str,
+    value: &str,
+) -> tiny_http::Header {
+    return tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("Failed to create header");
+}
+
+fn respond(
+    request: tiny_http::Request,
+    response: tiny_http::Response<impl std::io::Read>,
+) {
+    // a client disconnecting early is not a reason to stop serving
+    if let Err(error) = request.respond(response) {
+        warning!("Failed to send response: {}", error);
+    }
+}
+
+//------------------------------------------------------------//
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("serve")
+        .about("Builds the pack and hosts the zip file over http for testing in a client")
+        .arg(
+            clap::Arg::new("input_path")
+                .short('i')
+                .long("input-path")
+                .help("The pack directory or zip file to read from")
+                .value_name("INPUT_PATH")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("zip")
+                .short('z')
+                .long("zip")
+                .help("The file name the zip file is served under (defaults to the input directory name)")
+                .value_name("ZIP_NAME")
+                .required(false)
+        )
+        .arg(
+            clap::Arg::new("host")
+                .long("host")
+                .help("The address to listen on")
+                .value_name("HOST")
+                .default_value("127.0.0.1")
+        )
+        .arg(
+            clap::Arg::new("port")
+                .short('p')
+                .long("port")
+                .help("The port to listen on")
+                .value_name("PORT")
+                .value_parser(clap::value_parser!(u16))
+                .default_value("8080")
+        )
+        .arg(
+            clap::Arg::new("rebuild")
+                .long("rebuild")
+                .help("Rebuild the pack on each request if the input directory changed")
+                .action(clap::ArgAction::SetTrue)
+        );
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let input_path = matched_args.get_one::<path::PathBuf>("input_path").expect("Failed to get input_path");
+    let host = matched_args.get_one::<String>("host").expect("Failed to get host");
+    let port = *matched_args.get_one::<u16>("port").expect("Failed to get port");
+    let should_rebuild = matched_args.get_flag("rebuild");
+
+    if !input_path.is_dir() && !source::is_zip_file(input_path) {
+        crate::fail_program("Input directory does not exist or is not a directory or zip file");
+    }
+
+    if should_rebuild && !input_path.is_dir() {
+        crate::fail_program("--rebuild only works with an input directory");
+    }
+
+    let zip_name = match matched_args.get_one::<String>("zip") {
+        Some(zip_name) => zip_name.clone(),
+        None => {
+            let input_path = fs::canonicalize(input_path).expect("Failed to resolve input path");
+
+            // `pack.zip` is served as `pack.zip`, not `pack.zip.zip`
+            let input_name = match input_path.is_dir() {
+                true => input_path.file_name(),
+                false => input_path.file_stem(),
+            };
+
+            format!("{}.zip", input_name.expect("Failed to get input name").to_str().expect("Failed to convert input name to string"))
+        },
+    };
+
+    let pack_source = source::open_pack(input_path);
+    let input_dir = pack_source.dir();
+
+    let build_dir = TempDir::new("ms-rpo-serve").expect("Failed to create temporary directory");
+
+    let mut built_pack = build_pack(input_dir, build_dir.path(), &zip_name);
+
+    // the input directory as it was when a rebuild failed, which is not built again until it changes
+    let mut failed_fingerprint = None;
+
+    let server = match tiny_http::Server::http((host.as_str(), port)) {
+        Ok(server) => server,
+        Err(error) => crate::fail_program(format!("Failed to listen on {}:{}: {}", host, port, error).as_str()),
+    };
+
+    let zip_url_path = server_properties::resource_pack_url("", &zip_name);
+
+    info!("");
+    info!("Serving {}", server_properties::resource_pack_url(format!("http://{}:{}", host, port).as_str(), &zip_name));
+    info!("SHA-1: {}", built_pack.zip_hashes.sha1);
+
+    if should_rebuild {
+        info!("The pack is rebuilt when a request comes in after the input directory changed.");
+        info!("Leave `resource-pack-sha1` empty while testing, the hash changes with every rebuild.");
+    }
+
+    info!("Press Ctrl+C to stop.");
+    info!("");
+
+    for request in server.incoming_requests() {
+        let request_path = request.url().split('?').next().unwrap_or("").to_string();
+
+        info!("{} {}", request.method(), request.url());
+
+        if request_path != zip_url_path {
+            respond(request, tiny_http::Response::from_string("Not Found").with_status_code(404));
+            continue;
+        }
+
+        if *request.method() != tiny_http::Method::Get && *request.method() != tiny_http::Method::Head {
+            respond(
+                request,
+                tiny_http::Response::from_string("Method Not Allowed").with_status_code(405)
+                    .with_header(header("Allow", "GET, HEAD"))
+            );
+            continue;
+        }
+
+        let input_fingerprint = match should_rebuild {
+            true => Some(fingerprint_dir(input_dir)),
+            false => None,
+        };
+
+        if input_fingerprint.as_ref().is_some_and(
+            |input_fingerprint| return *input_fingerprint != built_pack.input_fingerprint && Some(input_fingerprint) != failed_fingerprint.as_ref()
+        ) {
+            info!("Input directory changed, rebuilding...");
+
+            // a broken file should not stop the server, the panic message is printed by the default hook
+            match panic::catch_unwind(panic::AssertUnwindSafe(|| return build_pack(input_dir, build_dir.path(), &zip_name))) {
+                Ok(rebuilt_pack) => {
+                    built_pack = rebuilt_pack;
+                    failed_fingerprint = None;
+
+                    info!("SHA-1: {}", built_pack.zip_hashes.sha1);
+                },
+                Err(panic_payload) => {
+                    warning!(
+                        "Failed to rebuild the pack: {}, serving the previous build until the input directory changes again",
+                        panic_message(panic_payload.as_ref())
+                    );
+
+                    failed_fingerprint = input_fingerprint;
+                },
+            }
+        }
+
+        let etag = format!("\"{}\"", built_pack.zip_hashes.sha1);
+
+        // let clients cache the pack, but make them check back every time since it can be rebuilt
+        let cache_headers = [
+            header("Cache-Control", "no-cache"),
+            header("ETag", &etag),
+        ];
+
+        let is_not_modified = request.headers().iter().any(
+            |request_header| return request_header.field.equiv("If-None-Match") && request_header.value.as_str() == etag
+        );
+
+        if is_not_modified {
+            let mut response = tiny_http::Response::empty(304);
+            for cache_header in cache_headers {
+                response.add_header(cache_header);
+            }
+
+            respond(request, response);
+            continue;
+        }
+
+        let zip_file = fs::File::open(&built_pack.zip_file_path).expect("Failed to open zip file");
+
+        let mut response = tiny_http::Response::from_file(zip_file)
+            .with_header(header("Content-Type", "application/zip"))
+            .with_header(header("Content-Disposition", format!("attachment; filename=\"{}\"", zip_name).as_str()));
+        for cache_header in cache_headers {
+            response.add_header(cache_header);
+        }
+
+        respond(request, response);
+    }
+}
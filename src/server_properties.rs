@@ -0,0 +1,406 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use uuid::Uuid; // resource pack ids
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::logging::info;
+
+//------------------------------------------------------------//
+
+/**
+ * Derives a stable resource pack id from the pack name.
+ *
+ * The same pack name always yields the same UUID, so clients keep their cached copy
+ * of the pack between server restarts until the pack name changes.
+ */
+pub fn resource_pack_id(
+    pack_name: &str,
+) -> Uuid {
+    return Uuid::new_v5(&Uuid::NAMESPACE_OID, format!("ms-rpo:{}", pack_name).as_bytes());
+}
+
+/**
+ * Joins the public base url and the zip file name, percent-encoding the file name.
+ */
+pub fn resource_pack_url(
+    base_url: &str,
+    zip_file_name: &str,
+) -> String {
+    let encoded_zip_file_name = zip_file_name.bytes().map(
+        |byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                return (byte as char).to_string();
+            }
+
+            return format!("%{:02X}", byte);
+        }
+    ).collect::<String>();
+
+    return format!("{}/{}", base_url.trim_end_matches('/'), encoded_zip_file_name);
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Escapes a value the same way the Minecraft server writes `server.properties`.
+ */
+fn escape_property_value(
+    value: &str,
+) -> String {
+    let mut escaped_value = String::new();
+
+    for character in value.chars() {
+        match character {
+            '\\' | ':' | '=' | '#' | '!' => {
+                escaped_value.push('\\');
+                escaped_value.push(character);
+            },
+            _ => {
+                escaped_value.push(character);
+            },
+        }
+    }
+
+    return escaped_value;
+}
+
+/**
+ * Returns the key of a `key=value`, `key: value` or `key value` line, or `None` for comments and blank lines.
+ */
+fn parse_property_key(
+    line: &str,
+) -> Option<String> {
+    let line = line.trim_start();
+
+    if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+        return None;
+    }
+
+    let mut key = String::new();
+    let mut characters = line.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => {
+                if let Some(escaped_character) = characters.next() {
+                    key.push(escaped_character);
+                }
+            },
+            '=' | ':' | ' ' | '\t' => {
+                break;
+            },
+            _ => {
+                key.push(character);
+            },
+        }
+    }
+
+    return Some(key);
+}
+
+/**
+ * Returns true if a line ends with an odd number of backslashes (the value continues on the next line).
+ */
+fn is_continued_line(
+    line: &str,
+) -> bool {
+    let trailing_backslashes = line.chars().rev().take_while(|character| return *character == '\\').count();
+
+    return trailing_backslashes % 2 == 1;
+}
+
+/**
+ * Returns the properties of a `server.properties` file by key, each with its line (and the lines its value continues on).
+ */
+fn read_property_lines(
+    contents: &str,
+) -> Vec<(String, String)> {
+    let mut property_lines: Vec<(String, String)> = Vec::new();
+    let mut previous_line_continues = false;
+
+    for line in contents.lines() {
+        if previous_line_continues {
+            previous_line_continues = is_continued_line(line);
+
+            if let Some((_, property_line)) = property_lines.last_mut() {
+                property_line.push('\n');
+                property_line.push_str(line);
+            }
+
+            continue;
+        }
+
+        let Some(key) = parse_property_key(line) else {
+            continue;
+        };
+
+        previous_line_continues = is_continued_line(line);
+        property_lines.push((key, line.to_string()));
+    }
+
+    return property_lines;
+}
+
+/**
+ * Sets the given properties in the contents of a `server.properties` file.
+ *
+ * Lines for other keys, comments and blank lines are kept exactly as they were, and so are the line endings
+ * (`\r\n` or `\n`) and whether the file ends with one. Properties that are not present yet are appended to the end.
+ */
+pub fn update_properties(
+    contents: &str,
+    properties: &[(&str, String)],
+) -> String {
+    let mut updated_lines: Vec<String> = Vec::new();
+    let mut written_keys: Vec<&str> = Vec::new();
+    let mut skip_continued_lines = false;
+    let mut previous_line_continues = false;
+
+    for line in contents.lines() {
+        // lines continuing a previous value are not keys of their own
+        if previous_line_continues {
+            previous_line_continues = is_continued_line(line);
+
+            if !skip_continued_lines {
+                updated_lines.push(line.to_string());
+            }
+
+            continue;
+        }
+
+        skip_continued_lines = false;
+
+        let key = parse_property_key(line);
+        previous_line_continues = key.is_some() && is_continued_line(line);
+
+        let property = key.and_then(
+            |key| return properties.iter().find(|(property_key, _)| return *property_key == key)
+        );
+
+        match property {
+            Some((property_key, property_value)) => {
+                updated_lines.push(format!("{}={}", property_key, escape_property_value(property_value)));
+                written_keys.push(property_key);
+                skip_continued_lines = true;
+            },
+            None => {
+                updated_lines.push(line.to_string());
+            },
+        }
+    }
+
+    for (property_key, property_value) in properties {
+        if !written_keys.contains(property_key) {
+            updated_lines.push(format!("{}={}", property_key, escape_property_value(property_value)));
+        }
+    }
+
+    let line_ending = match contents.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    };
+
+    let mut updated_contents = updated_lines.join(line_ending);
+
+    // a new file ends with a line ending like the files the server writes
+    if contents.is_empty() || contents.ends_with('\n') {
+        updated_contents.push_str(line_ending);
+    }
+
+    return updated_contents;
+}
+
+/**
+ * Returns the lines of a diff of the properties that differ between two versions of a `server.properties` file, by key.
+ *
+ * Comments and blank lines are left out, `update_properties` never changes them.
+ */
+fn diff_properties(
+    old_contents: &str,
+    new_contents: &str,
+) -> Vec<String> {
+    let old_properties = read_property_lines(old_contents);
+    let new_properties = read_property_lines(new_contents);
+
+    let find_line = |properties: &[(String, String)], key: &str| {
+        return properties.iter().find(|(property_key, _)| return property_key == key).map(|(_, line)| return line.clone());
+    };
+
+    // the keys of the old file in order, then the ones that were added
+    let mut keys: Vec<&String> = Vec::new();
+
+    for (key, _) in old_properties.iter().chain(new_properties.iter()) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let mut diff_lines: Vec<String> = Vec::new();
+
+    for key in keys {
+        let old_line = find_line(&old_properties, key);
+        let new_line = find_line(&new_properties, key);
+
+        if old_line == new_line {
+            continue;
+        }
+
+        diff_lines.push(format!("@@ {} @@", key));
+
+        if let Some(old_line) = old_line {
+            diff_lines.push(format!("- {}", old_line));
+        }
+
+        if let Some(new_line) = new_line {
+            diff_lines.push(format!("+ {}", new_line));
+        }
+    }
+
+    return diff_lines;
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Points a `server.properties` file at a freshly built zip file.
+ *
+ * Updates `resource-pack`, `resource-pack-sha1` and `resource-pack-id`.
+ * With `dry_run`, only the diff is printed and the file is left untouched.
+ */
+pub fn write_resource_pack_properties(
+    server_properties_path: &path::Path,
+    base_url: &str,
+    zip_file_path: &path::Path,
+    zip_sha1: &str,
+    dry_run: bool,
+) {
+    let zip_file_name = zip_file_path.file_name().expect("Failed to get zip file name").to_str().expect("Failed to convert zip file name to string");
+    let pack_name = zip_file_path.file_stem().expect("Failed to get zip file stem").to_str().expect("Failed to convert zip file stem to string");
+
+    // a missing file is created with just the resource pack properties
+    let old_contents = match server_properties_path.exists() {
+        true => fs::read_to_string(server_properties_path).expect("Failed to read server.properties"),
+        false => String::new(),
+    };
+
+    let new_contents = update_properties(
+        &old_contents,
+        &[
+            ("resource-pack", resource_pack_url(base_url, zip_file_name)),
+            ("resource-pack-sha1", zip_sha1.to_string()),
+            ("resource-pack-id", resource_pack_id(pack_name).to_string()),
+        ]
+    );
+
+    if dry_run {
+        println!("Changes to {} (dry run):", server_properties_path.to_str().expect("Failed to convert server_properties_path to string"));
+
+        let diff_lines = diff_properties(&old_contents, &new_contents);
+
+        if diff_lines.is_empty() {
+            println!("No changes to server.properties");
+        }
+
+        for diff_line in diff_lines {
+            println!("{}", diff_line);
+        }
+
+        return;
+    }
+
+    fs::write(server_properties_path, new_contents).expect("Failed to write server.properties");
+
+    info!("Updated resource pack settings in {}", server_properties_path.to_str().expect("Failed to convert server_properties_path to string"));
+}
+
+//------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource_pack_properties() -> Vec<(&'static str, String)> {
+        return vec![
+            ("resource-pack", "https://example.com/pack.zip".to_string()),
+            ("resource-pack-sha1", "0123456789abcdef".to_string()),
+        ];
+    }
+
+    #[test]
+    fn update_properties_keeps_line_endings() {
+        let cases = [
+            ("motd=hi\r\nresource-pack=old\r\n", "motd=hi\r\nresource-pack=https\\://example.com/pack.zip\r\nresource-pack-sha1=0123456789abcdef\r\n"),
+            ("motd=hi\nresource-pack=old\n", "motd=hi\nresource-pack=https\\://example.com/pack.zip\nresource-pack-sha1=0123456789abcdef\n"),
+            ("motd=hi\nresource-pack=old", "motd=hi\nresource-pack=https\\://example.com/pack.zip\nresource-pack-sha1=0123456789abcdef"),
+            ("", "resource-pack=https\\://example.com/pack.zip\nresource-pack-sha1=0123456789abcdef\n"),
+        ];
+
+        for (contents, updated_contents) in cases {
+            assert_eq!(update_properties(contents, &resource_pack_properties()), updated_contents, "Updating {:?}", contents);
+        }
+    }
+
+    #[test]
+    fn update_properties_keeps_other_lines_and_replaces_continued_values() {
+        let contents = "# comment\n\nresource-pack=first \\\n  second\nmotd : a\\=b\n";
+
+        assert_eq!(
+            update_properties(contents, &resource_pack_properties()),
+            "# comment\n\nresource-pack=https\\://example.com/pack.zip\nmotd : a\\=b\nresource-pack-sha1=0123456789abcdef\n"
+        );
+    }
+
+    #[test]
+    fn diff_properties_lists_changed_keys() {
+        let old_contents = "# comment\nmotd=hi\nresource-pack=old\nresource-pack-sha1=old\n";
+        let new_contents = update_properties(old_contents, &[("resource-pack-sha1", "new".to_string()), ("resource-pack-id", "id".to_string())]);
+
+        assert_eq!(
+            diff_properties(old_contents, &new_contents),
+            [
+                "@@ resource-pack-sha1 @@",
+                "- resource-pack-sha1=old",
+                "+ resource-pack-sha1=new",
+                "@@ resource-pack-id @@",
+                "+ resource-pack-id=id",
+            ]
+        );
+
+        assert!(diff_properties(&new_contents, &new_contents).is_empty());
+        assert!(diff_properties(old_contents, &old_contents.replace('\n', "\r\n")).is_empty());
+    }
+
+    #[test]
+    fn diff_properties_keeps_continued_lines_with_their_key() {
+        let old_contents = "resource-pack=first \\\n  second\n";
+
+        assert_eq!(
+            diff_properties(old_contents, "resource-pack=new\n"),
+            ["@@ resource-pack @@", "- resource-pack=first \\\n  second", "+ resource-pack=new"]
+        );
+    }
+
+    #[test]
+    fn resource_pack_id_is_a_stable_v5_uuid() {
+        let pack_id = resource_pack_id("optimized-resource-pack");
+
+        assert_eq!(pack_id.to_string(), "9020b90a-3faf-53b0-818b-9491c42b2169");
+        assert_eq!(pack_id.get_version_num(), 5);
+        assert_eq!(resource_pack_id("optimized-resource-pack"), pack_id);
+        assert_ne!(resource_pack_id("other-resource-pack"), pack_id);
+    }
+}
@@ -0,0 +1,545 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::hash;
+use crate::ogg;
+use crate::pack_mcmeta;
+use crate::references;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+// the config file that turns the directories of sounds under it into sound events
+const SOUND_EVENTS_CONFIG: &str = "sound_events.json";
+
+// the sound events under these are played without a position, as the vanilla `music.game` and `ui.button.click` are
+const NON_POSITIONAL_EVENT_PREFIXES: [&str; 2] = ["music.", "ui."];
+
+// the fields of a sound that have to be numbers
+const SOUND_NUMBER_FIELDS: [&str; 4] = ["volume", "pitch", "weight", "attenuation_distance"];
+
+// the fields of a sound that have to be booleans
+const SOUND_BOOLEAN_FIELDS: [&str; 2] = ["stream", "preload"];
+
+//------------------------------------------------------------//
+
+/**
+ * The sound events of a namespace, from its `sounds.json` and the sound event configs under its `sounds` directory.
+ */
+struct SoundEvents {
+    // the pack path of `sounds.json`, which does not exist yet when all of its events are generated
+    pack_path: String,
+    namespace: String,
+    // the events of `sounds.json` and the generated ones
+    events: serde_json::Map<String, serde_json::Value>,
+    generated_events: Vec<String>,
+    config_paths: Vec<String>,
+}
+
+/**
+ * Builds the sound events of a sound event config, one for every directory under it with `.ogg` files,
+ * e.g. `sounds/block/anvil/` becomes `block.anvil` with the sounds in it.
+ *
+ * Events that are already defined are left as they are, so an event can still be written by hand.
+ */
+fn generate_events(
+    pack_paths: &[String],
+    read_pack_file: &dyn Fn(&str) -> Option<Vec<u8>>,
+    namespace: &str,
+    config_path: &str,
+    events: &serde_json::Map<String, serde_json::Value>,
+    validation: &mut validate::Validation,
+) -> Vec<(String, serde_json::Value)> {
+    let Some(config) = validate::read_pack_json(read_pack_file, config_path) else {
+        return Vec::new();
+    };
+
+    let subtitle_prefix = match config.get("subtitle_prefix").map(|subtitle_prefix| return subtitle_prefix.as_str()) {
+        Some(Some(subtitle_prefix)) => Some(subtitle_prefix),
+        Some(None) => {
+            validation.error(config_path, "`subtitle_prefix` has to be a string".to_string());
+
+            None
+        },
+        None => None,
+    };
+
+    let is_streamed = match config.get("stream").map(|stream| return stream.as_bool()) {
+        Some(Some(stream)) => stream,
+        Some(None) => {
+            validation.error(config_path, "`stream` has to be true or false".to_string());
+
+            false
+        },
+        None => false,
+    };
+
+    let sounds_dir = format!("assets/{}/sounds/", namespace);
+    let config_dir = config_path.strip_suffix(SOUND_EVENTS_CONFIG).expect("Failed to strip config file name");
+
+    let mut sounds_by_dir = collections::BTreeMap::<&str, Vec<String>>::new();
+
+    for pack_path in pack_paths.iter().filter(|pack_path| return pack_path.starts_with(config_dir) && pack_path.ends_with(".ogg")) {
+        let sound_path = pack_path.strip_prefix(&sounds_dir).expect("Failed to strip sounds directory");
+
+        // a sound directly in `sounds/` has no directory to name its event after
+        let Some((sound_dir, _)) = sound_path.rsplit_once('/') else {
+            continue;
+        };
+
+        sounds_by_dir.entry(sound_dir).or_default().push(
+            format!("{}:{}", namespace, sound_path.strip_suffix(".ogg").expect("Failed to strip extension"))
+        );
+    }
+
+    let mut generated_events = Vec::new();
+
+    for (sound_dir, sound_names) in sounds_by_dir {
+        let event = sound_dir.replace('/', ".");
+
+        if events.contains_key(&event) {
+            continue;
+        }
+
+        if !references::is_valid_resource_location(&event) {
+            validation.error(
+                config_path,
+                format!("The sounds in {}{}/ would make the sound event `{}`, which is not a valid name", sounds_dir, sound_dir, event)
+            );
+
+            continue;
+        }
+
+        let sounds = sound_names.into_iter().map(
+            |sound_name| {
+                if is_streamed {
+                    return serde_json::json!({ "name": sound_name, "stream": true });
+                }
+
+                return serde_json::Value::String(sound_name);
+            }
+        ).collect::<Vec<serde_json::Value>>();
+
+        let mut sound_event = serde_json::json!({ "sounds": sounds });
+
+        if let Some(subtitle_prefix) = subtitle_prefix {
+            sound_event["subtitle"] = serde_json::Value::String(format!("{}.{}", subtitle_prefix, event));
+        }
+
+        generated_events.push((event, sound_event));
+    }
+
+    return generated_events;
+}
+
+/**
+ * Whether a pack path is a sound event config, a `sound_events.json` somewhere in the `sounds` directory of a namespace.
+ */
+pub fn is_sound_events_config(
+    pack_path: &str,
+) -> bool {
+    let components = pack_mcmeta::strip_overlay_dir(pack_path).split('/').collect::<Vec<&str>>();
+
+    return components.len() > 3 && components[0] == "assets" && components[2] == "sounds" && components[components.len() - 1] == SOUND_EVENTS_CONFIG;
+}
+
+/**
+ * Reads the sound events of every namespace of a pack (and its overlays) with a `sounds.json` or a sound event config.
+ *
+ * Sound event configs only work in the pack root, an overlay that only has some of the events of the pack root would hide
+ * the others. When configs are nested, the one closest to a directory of sounds generates its event.
+ * `read_pack_file` reads a file of the pack by its pack path, from the pack directory or from memory.
+ */
+fn read_sound_events(
+    pack_paths: &[String],
+    read_pack_file: &dyn Fn(&str) -> Option<Vec<u8>>,
+    validation: &mut validate::Validation,
+) -> Vec<SoundEvents> {
+    let mut layer_namespaces = collections::BTreeSet::<(&str, &str)>::new();
+    let mut config_paths = Vec::<&String>::new();
+
+    for pack_path in pack_paths {
+        let components = pack_mcmeta::strip_overlay_dir(pack_path).split('/').collect::<Vec<&str>>();
+
+        if components.len() == 3 && components[0] == "assets" && components[2] == "sounds.json" {
+            layer_namespaces.insert((references::layer_of(pack_path), components[1]));
+        } else if is_sound_events_config(pack_path) {
+            if !references::layer_of(pack_path).is_empty() {
+                validation.error(pack_path, "Sound event configs only work in the pack root, not in overlays".to_string());
+
+                continue;
+            }
+
+            layer_namespaces.insert(("", components[1]));
+            config_paths.push(pack_path);
+        }
+    }
+
+    // the deepest configs first, so they generate the events of their directories before the configs above them
+    config_paths.sort_by_key(|config_path| return std::cmp::Reverse(config_path.matches('/').count()));
+
+    let mut all_sound_events = Vec::new();
+
+    for (layer, namespace) in layer_namespaces {
+        let pack_path = format!("{}assets/{}/sounds.json", layer, namespace);
+
+        let events = match pack_paths.contains(&pack_path) {
+            true => {
+                let Some(sounds) = validate::read_pack_json(read_pack_file, &pack_path) else {
+                    continue;
+                };
+
+                let serde_json::Value::Object(events) = sounds else {
+                    validation.error(&pack_path, "Has to be an object of sound events by name".to_string());
+
+                    continue;
+                };
+
+                events
+            },
+            false => serde_json::Map::new(),
+        };
+
+        let mut sound_events = SoundEvents {
+            pack_path,
+            namespace: namespace.to_string(),
+            events,
+            generated_events: Vec::new(),
+            config_paths: Vec::new(),
+        };
+
+        if layer.is_empty() {
+            let namespace_configs = config_paths.iter().filter(
+                |config_path| return config_path.starts_with(format!("assets/{}/sounds/", namespace).as_str())
+            ).map(|config_path| return config_path.to_string()).collect::<Vec<String>>();
+
+            for config_path in namespace_configs {
+                for (event, sound_event) in generate_events(pack_paths, read_pack_file, namespace, &config_path, &sound_events.events, validation) {
+                    sound_events.events.insert(event.clone(), sound_event);
+                    sound_events.generated_events.push(event);
+                }
+
+                sound_events.config_paths.push(config_path);
+            }
+        }
+
+        all_sound_events.push(sound_events);
+    }
+
+    return all_sound_events;
+}
+
+/**
+ * The asset paths of the `.ogg` files the sounds of a sound event play, `type: "event"` sounds being left out.
+ */
+fn sound_asset_paths(
+    sound_event: &serde_json::Value,
+) -> Vec<String> {
+    return sound_event.get("sounds").and_then(|sounds| return sounds.as_array()).into_iter().flatten().filter_map(
+        |sound| {
+            let name = match sound {
+                serde_json::Value::Object(sound) if sound.get("type").and_then(|sound_type| return sound_type.as_str()) == Some("event") => return None,
+                serde_json::Value::Object(sound) => sound.get("name")?.as_str()?,
+                _ => sound.as_str()?,
+            };
+
+            return Some(references::to_asset_path(name, "sounds", ".ogg"));
+        }
+    ).collect();
+}
+
+/**
+ * Returns the pack paths the sound event generator reads (the configs) and writes (`sounds.json` files).
+ */
+pub fn list_generated_paths(
+    pack_dir: &path::Path,
+) -> (collections::HashSet<String>, collections::HashSet<String>) {
+    let all_sound_events = read_sound_events(
+        &hash::list_pack_paths(pack_dir),
+        &|pack_path| return fs::read(pack_dir.join(pack_path)).ok(),
+        &mut validate::Validation::default()
+    );
+
+    let config_paths = all_sound_events.iter().flat_map(|sound_events| return sound_events.config_paths.iter().cloned()).collect();
+
+    let generated_paths = all_sound_events.iter().filter(
+        |sound_events| return !sound_events.config_paths.is_empty()
+    ).map(|sound_events| return sound_events.pack_path.clone()).collect();
+
+    return (config_paths, generated_paths);
+}
+
+/**
+ * Returns the sound event configs of a pack and the asset paths of the `.ogg` files the generated sound events play,
+ * which the references of the pack do not have.
+ */
+pub fn list_generator_paths(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+) -> Vec<String> {
+    let all_sound_events = read_sound_events(
+        pack_paths,
+        &|pack_path| return fs::read(pack_dir.join(pack_path)).ok(),
+        &mut validate::Validation::default()
+    );
+
+    return all_sound_events.iter().flat_map(
+        |sound_events| {
+            let mut generator_paths = sound_events.config_paths.clone();
+
+            generator_paths.extend(
+                sound_events.generated_events.iter().flat_map(|event| return sound_asset_paths(&sound_events.events[event]))
+            );
+
+            return generator_paths;
+        }
+    ).collect();
+}
+
+/**
+ * Checks a sound event of the `sounds.json` at `sounds_path` and the sounds it lists.
+ */
+fn check_sound_event(
+    sounds_path: &str,
+    event: &str,
+    sound_event: &serde_json::Value,
+    validation: &mut validate::Validation,
+) {
+    // sound event names are paths, their namespace is the one of `sounds.json`
+    if event.contains(':') || !references::is_valid_resource_location(event) {
+        validation.error(sounds_path, format!("`{}` is not a valid sound event name, it can only have a-z, 0-9, `_`, `-`, `.` and `/`", event));
+    }
+
+    let serde_json::Value::Object(sound_event) = sound_event else {
+        validation.error(sounds_path, format!("`{}` has to be an object", event));
+
+        return;
+    };
+
+    if sound_event.get("subtitle").is_some_and(|subtitle| return !subtitle.is_string()) {
+        validation.error(sounds_path, format!("`{}.subtitle` has to be a translation key", event));
+    }
+
+    if sound_event.get("replace").is_some_and(|replace| return !replace.is_boolean()) {
+        validation.error(sounds_path, format!("`{}.replace` has to be true or false", event));
+    }
+
+    let sounds = match sound_event.get("sounds") {
+        Some(serde_json::Value::Array(sounds)) => sounds,
+        Some(_) => {
+            validation.error(sounds_path, format!("`{}.sounds` has to be a list", event));
+
+            return;
+        },
+        None => return,
+    };
+
+    for (index, sound) in sounds.iter().enumerate() {
+        let sound_json_path = format!("{}.sounds[{}]", event, index);
+
+        let name = match sound {
+            serde_json::Value::String(name) => name,
+            serde_json::Value::Object(sound) => {
+                for field in SOUND_NUMBER_FIELDS {
+                    if sound.get(field).is_some_and(|value| return !value.is_number()) {
+                        validation.error(sounds_path, format!("`{}.{}` has to be a number", sound_json_path, field));
+                    }
+                }
+
+                for field in SOUND_BOOLEAN_FIELDS {
+                    if sound.get(field).is_some_and(|value| return !value.is_boolean()) {
+                        validation.error(sounds_path, format!("`{}.{}` has to be true or false", sound_json_path, field));
+                    }
+                }
+
+                match sound.get("type").map(|sound_type| return sound_type.as_str()) {
+                    Some(Some("file")) | Some(Some("event")) | None => {},
+                    _ => validation.error(sounds_path, format!("`{}.type` has to be `file` or `event`", sound_json_path)),
+                }
+
+                let Some(name) = sound.get("name").and_then(|name| return name.as_str()) else {
+                    validation.error(sounds_path, format!("`{}.name` is missing", sound_json_path));
+
+                    continue;
+                };
+
+                name
+            },
+            _ => {
+                validation.error(sounds_path, format!("`{}` has to be a sound name or an object", sound_json_path));
+
+                continue;
+            },
+        };
+
+        if !references::is_valid_resource_location(name) {
+            validation.error(sounds_path, format!("`{}` `{}` is not a valid resource location", sound_json_path, name));
+        }
+    }
+}
+
+/**
+ * Checks the `sounds.json` files and the `.ogg` files of a pack (and its overlays).
+ *
+ * Missing sound files are reported by the reference checks. Sounds nothing plays are reported, except for `minecraft:`
+ * sounds, which replace vanilla sounds that the vanilla `sounds.json` plays. Stereo sounds ignore attenuation,
+ * so the ones played by a sound event that can be played at a position are reported too.
+ * A sound event of an overlay can play a sound of the pack root and the other way around.
+ */
+pub fn check_sounds(
+    pack_dir: &path::Path,
+    pack_paths: &[String],
+    validation: &mut validate::Validation,
+) {
+    let all_sound_events = read_sound_events(pack_paths, &|pack_path| return fs::read(pack_dir.join(pack_path)).ok(), validation);
+
+    // the sound events by the sounds they play, left out for sound events played without a position
+    let mut played_sounds = collections::HashMap::<String, collections::BTreeSet<String>>::new();
+
+    for sound_events in &all_sound_events {
+        for (event, sound_event) in &sound_events.events {
+            // generated sound events are valid, and their `sounds.json` may not exist
+            if !sound_events.generated_events.contains(event) {
+                check_sound_event(&sound_events.pack_path, event, sound_event, validation);
+            }
+
+            let is_positional = !NON_POSITIONAL_EVENT_PREFIXES.iter().any(|prefix| return event.starts_with(prefix));
+
+            for asset_path in sound_asset_paths(sound_event) {
+                let events = played_sounds.entry(asset_path).or_default();
+
+                if is_positional {
+                    events.insert(format!("{}:{}", sound_events.namespace, event));
+                }
+            }
+        }
+    }
+
+    for pack_path in pack_paths {
+        let asset_path = pack_mcmeta::strip_overlay_dir(pack_path);
+        let components = asset_path.split('/').collect::<Vec<&str>>();
+
+        if components.len() < 4 || components[0] != "assets" || components[2] != "sounds" || !asset_path.ends_with(".ogg") {
+            continue;
+        }
+
+        let channels = match ogg::read_vorbis_channels(&pack_dir.join(pack_path)) {
+            Ok(channels) => channels,
+            Err(message) => {
+                validation.error(pack_path, message);
+
+                continue;
+            },
+        };
+
+        let Some(events) = played_sounds.get(asset_path) else {
+            if components[1] != "minecraft" {
+                validation.warning(pack_path, "Is not played by any sound event in `sounds.json`".to_string());
+            }
+
+            continue;
+        };
+
+        if channels > 1 && !events.is_empty() {
+            validation.warning(
+                pack_path,
+                format!(
+                    "Is stereo, so it sounds the same at every distance when {} plays it at a position, make it mono",
+                    events.iter().map(|event| return format!("`{}`", event)).collect::<Vec<String>>().join(", ")
+                )
+            );
+        }
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Writes the generated sound events of the pack files into their `sounds.json` files, removing the sound event configs.
+ *
+ * Namespaces with errors in their sound event configs are left as they are (`optimize` stops on them before this runs).
+ */
+pub fn generate_sound_events(
+    pack_files: &mut crate::PackFiles,
+) -> crate::StageResult {
+    const STAGE_NAME: &str = "sounds";
+
+    let operation_start = std::time::Instant::now();
+
+    let mut validation = validate::Validation::default();
+    let all_sound_events = read_sound_events(
+        &pack_files.keys().cloned().collect::<Vec<String>>(),
+        &|pack_path| return pack_files.get(pack_path).cloned(),
+        &mut validation
+    );
+
+    validate::log_issues(&validation);
+
+    let mut processed_files = Vec::new();
+    let mut generated_event_count = 0;
+
+    for sound_events in &all_sound_events {
+        let has_errors = validation.issues.iter().any(
+            |issue| return issue.severity == validate::Severity::Error && sound_events.config_paths.contains(&issue.path)
+        );
+
+        if sound_events.config_paths.is_empty() || has_errors {
+            continue;
+        }
+
+        let file_operation_start = std::time::Instant::now();
+
+        let size_before = pack_files.get(&sound_events.pack_path).map_or(0, |sounds_contents| return sounds_contents.len() as u64);
+
+        // the json stage minifies it afterwards
+        let sounds_contents = serde_json::to_vec_pretty(&sound_events.events).expect("Failed to convert sound events to string");
+        let size_after = sounds_contents.len() as u64;
+
+        pack_files.insert(sound_events.pack_path.clone(), sounds_contents);
+
+        for config_path in &sound_events.config_paths {
+            pack_files.remove(config_path);
+        }
+
+        generated_event_count += sound_events.generated_events.len();
+
+        processed_files.push(
+            crate::ProcessedFile {
+                path: path::PathBuf::from(&sound_events.pack_path),
+                size_before,
+                size_after,
+                duration: file_operation_start.elapsed(),
+            }
+        );
+    }
+
+    for processed_file in &processed_files {
+        crate::log_processed_file(STAGE_NAME, processed_file);
+    }
+
+    let operation_end = operation_start.elapsed();
+
+    let stage_result = crate::StageResult {
+        name: STAGE_NAME,
+        files: processed_files,
+        duration: operation_end,
+    };
+
+    crate::log_stage_result(
+        &stage_result,
+        format!("Generated {} sound events in {:.2?}", generated_event_count, operation_end).as_str(),
+    );
+
+    return stage_result;
+}
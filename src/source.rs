@@ -0,0 +1,135 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::fs;
+use std::io::Read;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use tempdir::TempDir; // temporary directories
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::logging::debug;
+
+//------------------------------------------------------------//
+
+/**
+ * A pack read from a directory or a zip file.
+ *
+ * Zip files are extracted into a temporary directory, which is removed when the source is dropped.
+ */
+pub struct PackSource {
+    dir: path::PathBuf,
+    extracted_dir: Option<TempDir>,
+}
+
+impl PackSource {
+    /**
+     * The directory holding the pack files (`pack.mcmeta`, `assets/`, ...).
+     */
+    pub fn dir(
+        &self,
+    ) -> &path::Path {
+        return &self.dir;
+    }
+
+    pub fn is_zip(
+        &self,
+    ) -> bool {
+        return self.extracted_dir.is_some();
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Checks whether a path is a zip file, by its contents rather than its extension.
+ */
+pub fn is_zip_file(
+    file_path: &path::Path,
+) -> bool {
+    if !file_path.is_file() {
+        return false;
+    }
+
+    let mut signature = [0u8; 4];
+
+    let is_read = fs::File::open(file_path).and_then(|mut file| return file.read_exact(&mut signature)).is_ok();
+
+    return is_read && signature == [0x50, 0x4b, 0x03, 0x04];
+}
+
+/**
+ * Zip files are often made by zipping the pack directory itself, nesting the pack in a single directory.
+ */
+fn find_pack_root(
+    dir: &path::Path,
+) -> path::PathBuf {
+    if dir.join("pack.mcmeta").is_file() {
+        return dir.to_path_buf();
+    }
+
+    let entries = fs::read_dir(dir).expect("Failed to read directory").map(
+        |entry| return entry.expect("Failed to read directory entry").path()
+    ).collect::<Vec<path::PathBuf>>();
+
+    if let [nested_dir] = entries.as_slice() {
+        if nested_dir.is_dir() && nested_dir.join("pack.mcmeta").is_file() {
+            return nested_dir.clone();
+        }
+    }
+
+    return dir.to_path_buf();
+}
+
+/**
+ * Opens a pack directory or zip file, exiting if it is neither.
+ */
+pub fn open_pack(
+    pack_path: &path::Path,
+) -> PackSource {
+    if pack_path.is_dir() {
+        return PackSource {
+            dir: pack_path.to_path_buf(),
+            extracted_dir: None,
+        };
+    }
+
+    if !is_zip_file(pack_path) {
+        crate::fail_program(
+            format!(
+                "{} is not a directory or a zip file",
+                pack_path.to_str().expect("Failed to convert pack_path to string")
+            ).as_str()
+        );
+    }
+
+    let extracted_dir = TempDir::new("ms-rpo").expect("Failed to create temporary directory");
+
+    let zip_file = fs::File::open(pack_path).expect("Failed to open zip file");
+    let mut zip_archive = zip::ZipArchive::new(zip_file).expect("Failed to read zip file");
+
+    // `extract` refuses entries with paths leading outside of the directory
+    zip_archive.extract(extracted_dir.path()).expect("Failed to extract zip file");
+
+    debug!(
+        "Extracted {} entries of {} into {}",
+        zip_archive.len(),
+        pack_path.to_str().expect("Failed to convert pack_path to string"),
+        extracted_dir.path().to_str().expect("Failed to convert extracted_dir to string")
+    );
+
+    return PackSource {
+        dir: find_pack_root(extracted_dir.path()),
+        extracted_dir: Some(extracted_dir),
+    };
+}
@@ -0,0 +1,764 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::pack_mcmeta;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+/**
+ * A Minecraft release version, e.g. `(1, 20, 4)` for 1.20.4.
+ */
+pub type Version = (u32, u32, u32);
+
+// the resource pack `pack_format` of each range of releases, oldest first
+const PACK_FORMATS: [(Version, Version, u64); 23] = [
+    ((1, 6, 1), (1, 8, 9), 1),
+    ((1, 9, 0), (1, 10, 2), 2),
+    ((1, 11, 0), (1, 12, 2), 3),
+    ((1, 13, 0), (1, 14, 4), 4),
+    ((1, 15, 0), (1, 16, 1), 5),
+    ((1, 16, 2), (1, 16, 5), 6),
+    ((1, 17, 0), (1, 17, 1), 7),
+    ((1, 18, 0), (1, 18, 2), 8),
+    ((1, 19, 0), (1, 19, 2), 9),
+    ((1, 19, 3), (1, 19, 3), 12),
+    ((1, 19, 4), (1, 19, 4), 13),
+    ((1, 20, 0), (1, 20, 1), 15),
+    ((1, 20, 2), (1, 20, 2), 18),
+    ((1, 20, 3), (1, 20, 4), 22),
+    ((1, 20, 5), (1, 20, 6), 32),
+    ((1, 21, 0), (1, 21, 1), 34),
+    ((1, 21, 2), (1, 21, 3), 42),
+    ((1, 21, 4), (1, 21, 4), 46),
+    ((1, 21, 5), (1, 21, 5), 55),
+    ((1, 21, 6), (1, 21, 6), 63),
+    ((1, 21, 7), (1, 21, 8), 64),
+    ((1, 21, 9), (1, 21, 10), 69),
+    ((1, 21, 11), (1, 21, 11), 75),
+];
+
+// lang files are json files since this version, `.lang` files before it
+const JSON_LANG_VERSION: Version = (1, 13, 0);
+
+// models have to use textures from an atlas since this version
+const ATLASES_VERSION: Version = (1, 19, 3);
+
+// clients read `pack.supported_formats` since this version
+const SUPPORTED_FORMATS_VERSION: Version = (1, 20, 2);
+
+// clients read `pack.min_format` and `pack.max_format` instead of `pack_format` and `supported_formats` since this format (a 1.21.9 snapshot)
+pub const MIN_MAX_FORMATS_FORMAT: u64 = 65;
+
+// item models are picked by the definitions in `items/` since this version, instead of model `overrides`
+const ITEM_DEFINITIONS_VERSION: Version = (1, 21, 4);
+
+// the texture directories in the blocks atlas of every namespace without an atlas file
+const DEFAULT_BLOCK_ATLAS_PREFIXES: [&str; 2] = ["block/", "item/"];
+
+//------------------------------------------------------------//
+
+/**
+ * The range of Minecraft versions a pack is made for, with the range of `pack_format`s they use.
+ */
+#[derive(Clone)]
+pub struct Target {
+    pub first_version: Version,
+    pub last_version: Version,
+    pub min_format: u64,
+    pub max_format: u64,
+}
+
+impl Target {
+    pub fn name(
+        &self,
+    ) -> String {
+        if self.first_version == self.last_version {
+            return format_version(self.first_version);
+        }
+
+        return format!("{}-{}", format_version(self.first_version), format_version(self.last_version));
+    }
+
+    /**
+     * Describes the formats of the target, e.g. `15` or `15 to 22`.
+     */
+    pub fn formats(
+        &self,
+    ) -> String {
+        if self.min_format == self.max_format {
+            return self.min_format.to_string();
+        }
+
+        return format!("{} to {}", self.min_format, self.max_format);
+    }
+
+    /**
+     * Whether any version of the target is older than `version`.
+     */
+    pub fn includes_before(
+        &self,
+        version: Version,
+    ) -> bool {
+        return self.first_version < version;
+    }
+
+    /**
+     * Whether any version of the target is `version` or newer.
+     */
+    pub fn includes_since(
+        &self,
+        version: Version,
+    ) -> bool {
+        return self.last_version >= version;
+    }
+
+    /**
+     * The versions of the target that use one of the formats from `min_format` to `max_format`, if any do.
+     */
+    pub fn narrow_to_formats(
+        &self,
+        min_format: u64,
+        max_format: u64,
+    ) -> Option<Target> {
+        let pack_formats = PACK_FORMATS.iter().filter(
+            |(first_version, last_version, pack_format)| {
+                return (min_format..=max_format).contains(pack_format)
+                    && *last_version >= self.first_version
+                    && *first_version <= self.last_version;
+            }
+        ).collect::<Vec<&(Version, Version, u64)>>();
+
+        let (first_pack_format, last_pack_format) = (pack_formats.first()?, pack_formats.last()?);
+
+        return Some(
+            Target {
+                first_version: first_pack_format.0.max(self.first_version),
+                last_version: last_pack_format.1.min(self.last_version),
+                min_format: first_pack_format.2,
+                max_format: last_pack_format.2,
+            }
+        );
+    }
+}
+
+//------------------------------------------------------------//
+
+pub fn format_version(
+    version: Version,
+) -> String {
+    return match version {
+        (major, minor, 0) => format!("{}.{}", major, minor),
+        (major, minor, patch) => format!("{}.{}.{}", major, minor, patch),
+    };
+}
+
+/**
+ * Parses a release version such as `1.20` or `1.20.4`.
+ */
+pub fn parse_version(
+    text: &str,
+) -> Result<Version, String> {
+    let parts = text.trim().split('.').map(
+        |part| return part.parse::<u32>().map_err(|_| return format!("`{}` is not a Minecraft version (e.g. 1.20.4)", text.trim()))
+    ).collect::<Result<Vec<u32>, String>>()?;
+
+    return match parts.as_slice() {
+        [major, minor] => Ok((*major, *minor, 0)),
+        [major, minor, patch] => Ok((*major, *minor, *patch)),
+        _ => Err(format!("`{}` is not a Minecraft version (e.g. 1.20.4)", text.trim())),
+    };
+}
+
+/**
+ * Looks up the `pack_format` of a release version.
+ */
+pub fn pack_format_of(
+    version: Version,
+) -> Result<u64, String> {
+    let pack_format = PACK_FORMATS.iter().find(
+        |(first_version, last_version, _)| return *first_version <= version && version <= *last_version
+    ).map(|(_, _, pack_format)| return *pack_format);
+
+    return pack_format.ok_or(format!("{} is not a known Minecraft release, use one from {}", format_version(version), known_versions()));
+}
+
+/**
+ * Describes the releases whose `pack_format` is known, e.g. `1.6.1 to 1.21.11`.
+ */
+pub fn known_versions() -> String {
+    return format!("{} to {}", format_version(PACK_FORMATS[0].0), format_version(PACK_FORMATS[PACK_FORMATS.len() - 1].1));
+}
+
+/**
+ * Parses a target such as `1.20.1` or `1.20-1.20.4`.
+ */
+pub fn parse_target(
+    text: &str,
+) -> Result<Target, String> {
+    let (first_version, last_version) = match text.split_once('-') {
+        Some((first_version, last_version)) => (parse_version(first_version)?, parse_version(last_version)?),
+        None => (parse_version(text)?, parse_version(text)?),
+    };
+
+    if first_version > last_version {
+        return Err(format!("`{}` starts after it ends, put the older version first", text));
+    }
+
+    return Ok(
+        Target {
+            first_version,
+            last_version,
+            min_format: pack_format_of(first_version)?,
+            max_format: pack_format_of(last_version)?,
+        }
+    );
+}
+
+/**
+ * Reads `pack.supported_formats`, which is a number, a `[min, max]` list or a `{ min_inclusive, max_inclusive }` object.
+ */
+pub fn read_supported_formats(
+    supported_formats: &serde_json::Value,
+) -> Option<(u64, u64)> {
+    return match supported_formats {
+        serde_json::Value::Number(pack_format) => pack_format.as_u64().map(|pack_format| return (pack_format, pack_format)),
+        serde_json::Value::Array(range) => match range.as_slice() {
+            [min_format, max_format] => Some((min_format.as_u64()?, max_format.as_u64()?)),
+            _ => None,
+        },
+        serde_json::Value::Object(range) => Some((range.get("min_inclusive")?.as_u64()?, range.get("max_inclusive")?.as_u64()?)),
+        _ => None,
+    };
+}
+
+/**
+ * Reads a `min_format` or `max_format`, which is a number or a `[major]` or `[major, minor]` list, as its major and minor version.
+ */
+pub fn read_format(
+    format: &serde_json::Value,
+) -> Option<(u64, u64)> {
+    return match format {
+        serde_json::Value::Number(major) => Some((major.as_u64()?, 0)),
+        serde_json::Value::Array(version) => match version.as_slice() {
+            [major] => Some((major.as_u64()?, 0)),
+            [major, minor] => Some((major.as_u64()?, minor.as_u64()?)),
+            _ => None,
+        },
+        _ => None,
+    };
+}
+
+/**
+ * Reads the `min_format` and `max_format` of `pack` or an overlay entry as the range of release formats they cover.
+ *
+ * Releases use minor version 0, so a minimum such as `[69, 1]` starts at the next release format.
+ */
+pub fn read_min_max_formats(
+    object: &serde_json::Map<String, serde_json::Value>,
+) -> Option<(u64, u64)> {
+    let (min_major, min_minor) = read_format(object.get("min_format")?)?;
+    let (max_major, _) = read_format(object.get("max_format")?)?;
+
+    return match min_minor {
+        0 => Some((min_major, max_major)),
+        _ => Some((min_major + 1, max_major)),
+    };
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Splits the pack path of an asset (in the root or an overlay) into its namespace, its kind (`models`, `lang`, ...)
+ * and the rest of its path.
+ */
+fn split_asset_path(
+    pack_path: &str,
+) -> Option<(&str, &str, &str)> {
+    let mut parts = pack_mcmeta::strip_overlay_dir(pack_path).strip_prefix("assets/")?.splitn(3, '/');
+
+    return Some((parts.next()?, parts.next()?, parts.next()?));
+}
+
+/**
+ * Splits a resource location such as `demo:item/ruby` into its namespace and path, `minecraft` being the default namespace.
+ */
+fn split_resource_location(
+    resource_location: &str,
+) -> (&str, &str) {
+    return resource_location.split_once(':').unwrap_or(("minecraft", resource_location));
+}
+
+/**
+ * Checks that `pack.pack_format` and `pack.supported_formats` match the formats of the versions of the target that read them,
+ * and `pack.min_format` and `pack.max_format` the formats of the newer ones.
+ */
+pub fn check_pack_mcmeta(
+    target: &Target,
+    pack: &serde_json::Map<String, serde_json::Value>,
+    validation: &mut validate::Validation,
+) {
+    const PACK_MCMETA: &str = "pack.mcmeta";
+
+    let min_max_formats = read_min_max_formats(pack);
+
+    if let Some(new_target) = target.narrow_to_formats(MIN_MAX_FORMATS_FORMAT, u64::MAX) {
+        match min_max_formats {
+            Some((min_format, max_format)) if min_format <= new_target.min_format && max_format >= new_target.max_format => {},
+            Some((min_format, max_format)) => validation.error(
+                PACK_MCMETA,
+                format!(
+                    "`pack.min_format` and `pack.max_format` cover {} to {}, but {} uses {}, set them to {} and {}",
+                    min_format,
+                    max_format,
+                    new_target.name(),
+                    new_target.formats(),
+                    new_target.min_format,
+                    new_target.max_format
+                )
+            ),
+            // broken ones are reported on their own
+            None if pack.contains_key("min_format") || pack.contains_key("max_format") => {},
+            None => validation.error(
+                PACK_MCMETA,
+                format!(
+                    "`pack.min_format` and `pack.max_format` are missing, but {} reads them instead of `pack.pack_format`, set them to {} and {}",
+                    new_target.name(),
+                    new_target.min_format,
+                    new_target.max_format
+                )
+            ),
+        }
+    }
+
+    let Some(target) = target.narrow_to_formats(0, MIN_MAX_FORMATS_FORMAT - 1) else {
+        return;
+    };
+
+    // a missing or broken `pack_format` is reported on its own, unless the pack leaves it out for the newer formats only
+    let Some(pack_format) = pack.get("pack_format").and_then(|pack_format| return pack_format.as_u64()) else {
+        if !pack.contains_key("pack_format") && min_max_formats.is_some_and(|(min_format, _)| return min_format >= MIN_MAX_FORMATS_FORMAT) {
+            validation.error(
+                PACK_MCMETA,
+                format!("`pack.pack_format` is missing, but {} reads it, set it to {}", target.name(), target.min_format)
+            );
+        }
+
+        return;
+    };
+
+    if pack_format < target.min_format || pack_format > target.max_format {
+        validation.error(
+            PACK_MCMETA,
+            format!(
+                "`pack.pack_format` is {}, but {} uses {}, set it to {}",
+                pack_format,
+                target.name(),
+                target.formats(),
+                target.min_format
+            )
+        );
+    }
+
+    if target.min_format == target.max_format || !target.includes_since(SUPPORTED_FORMATS_VERSION) {
+        return;
+    }
+
+    match pack.get("supported_formats").and_then(read_supported_formats) {
+        Some((min_format, max_format)) if min_format <= target.min_format && max_format >= target.max_format => {},
+        Some((min_format, max_format)) => validation.warning(
+            PACK_MCMETA,
+            format!(
+                "`pack.supported_formats` is [{}, {}], but {} needs [{}, {}]",
+                min_format,
+                max_format,
+                target.name(),
+                target.min_format,
+                target.max_format
+            )
+        ),
+        None => validation.warning(
+            PACK_MCMETA,
+            format!(
+                "`pack.supported_formats` is missing, so versions of {} without pack_format {} show the pack as incompatible, set it to [{}, {}]",
+                target.name(),
+                pack_format,
+                target.min_format,
+                target.max_format
+            )
+        ),
+    }
+}
+
+/**
+ * The sprites the blocks atlas has besides the default directories, from `assets/minecraft/atlases/blocks.json`.
+ */
+struct BlockAtlas {
+    prefixes: Vec<String>,
+    sprites: collections::HashSet<String>,
+}
+
+impl BlockAtlas {
+    fn contains(
+        &self,
+        texture: &str,
+    ) -> bool {
+        let (namespace, texture_path) = split_resource_location(texture);
+
+        return DEFAULT_BLOCK_ATLAS_PREFIXES.iter().any(|prefix| return texture_path.starts_with(prefix))
+            || self.prefixes.iter().any(|prefix| return texture_path.starts_with(prefix.as_str()))
+            || self.sprites.contains(&format!("{}:{}", namespace, texture_path));
+    }
+}
+
+fn read_block_atlas(
+    layer_dir: &path::Path,
+) -> Option<BlockAtlas> {
+    let mut block_atlas = BlockAtlas { prefixes: Vec::new(), sprites: collections::HashSet::new() };
+
+    let atlas = validate::read_json(&layer_dir.join("assets/minecraft/atlases/blocks.json"))?;
+
+    let normalize = |resource_location: &str| {
+        let (namespace, resource_path) = split_resource_location(resource_location);
+
+        return format!("{}:{}", namespace, resource_path);
+    };
+
+    for source in atlas.get("sources").and_then(|sources| return sources.as_array()).into_iter().flatten() {
+        let source_type = source.get("type").and_then(|source_type| return source_type.as_str()).unwrap_or_default();
+
+        match source_type.trim_start_matches("minecraft:") {
+            "directory" => {
+                if let Some(prefix) = source.get("prefix").and_then(|prefix| return prefix.as_str()) {
+                    block_atlas.prefixes.push(prefix.to_string());
+                }
+            },
+            "single" => {
+                let sprite = source.get("sprite").or(source.get("resource")).and_then(|sprite| return sprite.as_str());
+
+                if let Some(sprite) = sprite {
+                    block_atlas.sprites.insert(normalize(sprite));
+                }
+            },
+            "paletted_permutations" => {
+                let separator = source.get("separator").and_then(|separator| return separator.as_str()).unwrap_or("_");
+                let textures = source.get("textures").and_then(|textures| return textures.as_array()).into_iter().flatten();
+                let permutations = source.get("permutations").and_then(|permutations| return permutations.as_object());
+
+                for texture in textures.filter_map(|texture| return texture.as_str()) {
+                    for permutation in permutations.into_iter().flat_map(|permutations| return permutations.keys()) {
+                        block_atlas.sprites.insert(normalize(&format!("{}{}{}", texture, separator, permutation)));
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    return Some(block_atlas);
+}
+
+/**
+ * Checks the sources of an atlas file, which has to list how its sprites are found.
+ */
+fn check_atlas(
+    pack_path: &str,
+    atlas: &serde_json::Value,
+    validation: &mut validate::Validation,
+) {
+    let Some(sources) = atlas.get("sources").and_then(|sources| return sources.as_array()) else {
+        validation.error(pack_path, "Missing the `sources` list".to_string());
+
+        return;
+    };
+
+    for (index, source) in sources.iter().enumerate() {
+        let source_type = source.get("type").and_then(|source_type| return source_type.as_str()).unwrap_or_default();
+
+        let required_fields: &[&str] = match source_type.trim_start_matches("minecraft:") {
+            "directory" => &["source", "prefix"],
+            "single" => &["resource"],
+            "filter" => &["pattern"],
+            "unstitch" => &["resource", "regions"],
+            "paletted_permutations" => &["textures", "palette_key", "permutations"],
+            _ => {
+                validation.error(pack_path, format!("`sources[{}].type` `{}` is not an atlas source type", index, source_type));
+
+                continue;
+            },
+        };
+
+        for field in required_fields {
+            if source.get(field).is_none() {
+                validation.error(pack_path, format!("`sources[{}]` of type {} is missing `{}`", index, source_type, field));
+            }
+        }
+    }
+}
+
+/**
+ * Runs the checks that only apply to some of the versions of the target on the files of the pack root or an overlay.
+ *
+ * `layer_dir` is the pack root or the overlay directory, whose atlas replaces the one in the pack root.
+ */
+pub fn check_pack(
+    target: &Target,
+    pack_dir: &path::Path,
+    layer_dir: &path::Path,
+    pack_paths: &[String],
+    validation: &mut validate::Validation,
+) {
+    let is_only_before = |version: Version| return !target.includes_since(version);
+    let is_only_since = |version: Version| return !target.includes_before(version);
+
+    let block_atlas = read_block_atlas(layer_dir).or_else(|| return read_block_atlas(pack_dir)).unwrap_or(
+        BlockAtlas { prefixes: Vec::new(), sprites: collections::HashSet::new() }
+    );
+
+    // the lang files of each namespace and language, to find languages missing a format
+    let mut lang_files = collections::BTreeMap::<(String, String), Vec<&str>>::new();
+
+    for pack_path in pack_paths {
+        let Some((namespace, kind, rest)) = split_asset_path(pack_path) else {
+            continue;
+        };
+
+        match kind {
+            "lang" => {
+                let Some((language, extension)) = rest.rsplit_once('.') else {
+                    continue;
+                };
+
+                if extension == "json" && is_only_before(JSON_LANG_VERSION) {
+                    validation.warning(pack_path, format!("Json lang files are only read since {}", format_version(JSON_LANG_VERSION)));
+                } else if extension == "lang" && is_only_since(JSON_LANG_VERSION) {
+                    validation.warning(pack_path, format!(".lang files are only read before {}", format_version(JSON_LANG_VERSION)));
+                }
+
+                lang_files.entry((namespace.to_string(), language.to_lowercase())).or_default().push(pack_path);
+            },
+            "atlases" if pack_path.ends_with(".json") => {
+                if is_only_before(ATLASES_VERSION) {
+                    validation.warning(pack_path, format!("Atlases are only read since {}", format_version(ATLASES_VERSION)));
+                } else if let Some(atlas) = validate::read_json(&pack_dir.join(pack_path)) {
+                    check_atlas(pack_path, &atlas, validation);
+                }
+            },
+            "items" if pack_path.ends_with(".json") => {
+                if is_only_before(ITEM_DEFINITIONS_VERSION) {
+                    validation.warning(
+                        pack_path,
+                        format!("Item model definitions are only read since {}", format_version(ITEM_DEFINITIONS_VERSION))
+                    );
+                } else if let Some(item_definition) = validate::read_json(&pack_dir.join(pack_path)) {
+                    let model_type = item_definition.get("model").and_then(|model| return model.get("type")).and_then(|model_type| return model_type.as_str());
+
+                    if model_type.is_none() {
+                        validation.error(pack_path, "Missing the `model` object with its `type`".to_string());
+                    }
+                }
+            },
+            "models" if pack_path.ends_with(".json") => {
+                let Some(model) = validate::read_json(&pack_dir.join(pack_path)) else {
+                    continue;
+                };
+
+                if model.get("overrides").is_some() && is_only_since(ITEM_DEFINITIONS_VERSION) {
+                    validation.warning(
+                        pack_path,
+                        format!(
+                            "`overrides` are ignored since {}, pick the model in an item model definition in items/ instead",
+                            format_version(ITEM_DEFINITIONS_VERSION)
+                        )
+                    );
+                }
+
+                if !target.includes_since(ATLASES_VERSION) {
+                    continue;
+                }
+
+                let textures = model.get("textures").and_then(|textures| return textures.as_object()).into_iter().flatten();
+
+                for (texture_name, texture) in textures {
+                    let Some(texture) = texture.as_str() else {
+                        continue;
+                    };
+
+                    // `#name` refers to another texture variable, `minecraft:` textures can be from the vanilla atlas
+                    if texture.starts_with('#') || split_resource_location(texture).0 == "minecraft" || block_atlas.contains(texture) {
+                        continue;
+                    }
+
+                    validation.warning(
+                        pack_path,
+                        format!(
+                            "`textures.{}` `{}` is not in the blocks atlas since {}, add it to assets/minecraft/atlases/blocks.json",
+                            texture_name,
+                            texture,
+                            format_version(ATLASES_VERSION)
+                        )
+                    );
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // a target on both sides of the change needs both formats
+    if !target.includes_before(JSON_LANG_VERSION) || !target.includes_since(JSON_LANG_VERSION) {
+        return;
+    }
+
+    for language_files in lang_files.values() {
+        if let [pack_path] = language_files.as_slice() {
+            let missing_format = match pack_path.ends_with(".json") {
+                true => format!(".lang file for versions before {}", format_version(JSON_LANG_VERSION)),
+                false => format!("json file for versions since {}", format_version(JSON_LANG_VERSION)),
+            };
+
+            validation.warning(pack_path, format!("{} covers both lang formats, but this language has no {}", target.name(), missing_format));
+        }
+    }
+}
+
+//------------------------------------------------------------//
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target_range(
+        target: &Target,
+    ) -> (Version, Version, u64, u64) {
+        return (target.first_version, target.last_version, target.min_format, target.max_format);
+    }
+
+    #[test]
+    fn parse_target_reads_versions_and_ranges() {
+        let cases = [
+            ("1.20.1", Some(((1, 20, 1), (1, 20, 1), 15, 15))),
+            ("1.20", Some(((1, 20, 0), (1, 20, 0), 15, 15))),
+            ("1.20-1.20.4", Some(((1, 20, 0), (1, 20, 4), 15, 22))),
+            (" 1.20.2 - 1.20.4 ", Some(((1, 20, 2), (1, 20, 4), 18, 22))),
+            ("1.21.8", Some(((1, 21, 8), (1, 21, 8), 64, 64))),
+            ("1.21.9", Some(((1, 21, 9), (1, 21, 9), 69, 69))),
+            ("1.21.10", Some(((1, 21, 10), (1, 21, 10), 69, 69))),
+            ("1.21.11", Some(((1, 21, 11), (1, 21, 11), 75, 75))),
+            ("1.21.9-1.21.11", Some(((1, 21, 9), (1, 21, 11), 69, 75))),
+            ("1.6.1-1.21.11", Some(((1, 6, 1), (1, 21, 11), 1, 75))),
+            // later releases and the ones before pack formats are unknown
+            ("1.21.12", None),
+            ("1.6", None),
+            ("1.20.4-1.20", None),
+            ("1.20.4.1", None),
+            ("1", None),
+            ("latest", None),
+        ];
+
+        for (text, target) in cases {
+            assert_eq!(parse_target(text).ok().as_ref().map(target_range), target, "Parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn narrow_to_formats_keeps_versions_with_formats_in_range() {
+        let cases = [
+            ("1.20-1.21.11", (0, 100), Some(((1, 20, 0), (1, 21, 11), 15, 75))),
+            ("1.20-1.21.11", (22, 34), Some(((1, 20, 3), (1, 21, 1), 22, 34))),
+            ("1.20-1.21.11", (23, 31), None),
+            ("1.20-1.21.11", (23, 33), Some(((1, 20, 5), (1, 20, 6), 32, 32))),
+            ("1.20-1.21.11", (1, 14), None),
+            ("1.20-1.21.11", (65, 69), Some(((1, 21, 9), (1, 21, 10), 69, 69))),
+            ("1.20-1.21.11", (70, 75), Some(((1, 21, 11), (1, 21, 11), 75, 75))),
+            ("1.20-1.21.11", (76, 100), None),
+            ("1.20-1.20.4", (18, 100), Some(((1, 20, 2), (1, 20, 4), 18, 22))),
+            // the versions outside of the target are left out, even when they share a format with one in it
+            ("1.20.3-1.21", (22, 22), Some(((1, 20, 3), (1, 20, 4), 22, 22))),
+            ("1.20.3", (0, 100), Some(((1, 20, 3), (1, 20, 3), 22, 22))),
+            ("1.21.10-1.21.11", (69, 69), Some(((1, 21, 10), (1, 21, 10), 69, 69))),
+        ];
+
+        for (text, (min_format, max_format), narrowed_target) in cases {
+            let target = parse_target(text).expect("Failed to parse target");
+
+            assert_eq!(
+                target.narrow_to_formats(min_format, max_format).as_ref().map(target_range),
+                narrowed_target,
+                "Narrowing {} to {} to {}",
+                text,
+                min_format,
+                max_format
+            );
+        }
+    }
+
+    #[test]
+    fn read_supported_formats_reads_every_form() {
+        let cases = [
+            (serde_json::json!(15), Some((15, 15))),
+            (serde_json::json!([15, 22]), Some((15, 22))),
+            (serde_json::json!({ "min_inclusive": 15, "max_inclusive": 22 }), Some((15, 22))),
+            (serde_json::json!([15]), None),
+            (serde_json::json!([15, 22, 34]), None),
+            (serde_json::json!({ "min_inclusive": 15 }), None),
+            (serde_json::json!("15"), None),
+            (serde_json::json!(-1), None),
+        ];
+
+        for (supported_formats, formats) in cases {
+            assert_eq!(read_supported_formats(&supported_formats), formats, "Reading {}", supported_formats);
+        }
+    }
+
+    #[test]
+    fn read_format_reads_major_and_minor_versions() {
+        let cases = [
+            (serde_json::json!(65), Some((65, 0))),
+            (serde_json::json!([69]), Some((69, 0))),
+            (serde_json::json!([69, 1]), Some((69, 1))),
+            (serde_json::json!([]), None),
+            (serde_json::json!([69, 1, 0]), None),
+            (serde_json::json!("69"), None),
+            (serde_json::json!(-1), None),
+            (serde_json::json!(69.5), None),
+        ];
+
+        for (format, major_minor) in cases {
+            assert_eq!(read_format(&format), major_minor, "Reading {}", format);
+        }
+    }
+
+    #[test]
+    fn read_min_max_formats_covers_release_formats() {
+        let cases = [
+            (serde_json::json!({ "min_format": 65, "max_format": 75 }), Some((65, 75))),
+            (serde_json::json!({ "min_format": [69], "max_format": [75, 0] }), Some((69, 75))),
+            // releases use minor version 0, so the release formats start after a minimum with a minor version
+            (serde_json::json!({ "min_format": [69, 1], "max_format": 75 }), Some((70, 75))),
+            (serde_json::json!({ "min_format": 15, "max_format": [75, 3] }), Some((15, 75))),
+            (serde_json::json!({ "min_format": 65 }), None),
+            (serde_json::json!({ "max_format": 75 }), None),
+            (serde_json::json!({ "min_format": "65", "max_format": 75 }), None),
+            (serde_json::json!({ "formats": [65, 75] }), None),
+        ];
+
+        for (object, formats) in cases {
+            assert_eq!(
+                read_min_max_formats(object.as_object().expect("Failed to get object")),
+                formats,
+                "Reading {}",
+                object
+            );
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::client_jar;
+use crate::hash;
+use crate::pack_mcmeta;
+use crate::references;
+use crate::sounds;
+
+//------------------------------------------------------------//
+
+// the asset directories whose files are only loaded when something references them
+const REFERENCED_ASSET_DIRS: [&str; 3] = ["models", "textures", "sounds"];
+
+//------------------------------------------------------------//
+
+/**
+ * Parses a glob of pack paths, where `*` matches within a directory and `**` across directories.
+ */
+pub fn parse_keep_pattern(
+    text: &str,
+) -> Result<regex::Regex, String> {
+    let mut pattern = String::from("^");
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            pattern.push_str("(.*/)?");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("**") {
+            pattern.push_str(".*");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('*') {
+            pattern.push_str("[^/]*");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('?') {
+            pattern.push_str("[^/]");
+            rest = after;
+        } else {
+            let character = rest.chars().next().expect("Failed to get character");
+
+            pattern.push_str(&regex::escape(&character.to_string()));
+            rest = &rest[character.len_utf8()..];
+        }
+    }
+
+    pattern.push('$');
+
+    return regex::Regex::new(&pattern).map_err(|error| return format!("`{}` is not a valid pattern: {}", text, error));
+}
+
+/**
+ * Whether an asset is only loaded when something references it, `asset_path` being relative to the pack root.
+ */
+fn is_referenced_asset(
+    asset_path: &str,
+) -> bool {
+    let components = asset_path.split('/').collect::<Vec<&str>>();
+
+    return components.len() > 3 && components[0] == "assets" && REFERENCED_ASSET_DIRS.contains(&components[2]);
+}
+
+/**
+ * Finds the models, textures and sounds nothing in the pack references.
+ *
+ * Everything else (blockstates, item model definitions, fonts, `sounds.json`, ...) is loaded by the game
+ * and followed to what it references, as are the assets matching a keep pattern.
+ * `minecraft:` assets replace vanilla ones, so they are kept unless a client jar shows there is no vanilla asset to replace.
+ * An asset in an overlay is used if the same asset in the pack root or another overlay is.
+ */
+pub fn find_unused_assets(
+    pack_dir: &path::Path,
+    client_jar: Option<&client_jar::ClientJar>,
+    keep_patterns: &[regex::Regex],
+) -> Vec<String> {
+    let pack_paths = hash::list_pack_paths(pack_dir);
+
+    let asset_paths = pack_paths.iter().map(
+        |pack_path| return pack_mcmeta::strip_overlay_dir(pack_path)
+    ).collect::<collections::BTreeSet<&str>>();
+
+    let all_references = references::collect_references(pack_dir, &pack_paths);
+
+    let mut references_by_file = collections::HashMap::<&str, Vec<&references::Reference>>::new();
+
+    for reference in &all_references {
+        references_by_file.entry(pack_mcmeta::strip_overlay_dir(&reference.from)).or_default().push(reference);
+    }
+
+    let is_kept = |pack_path: &str| {
+        let asset_path = pack_mcmeta::strip_overlay_dir(pack_path);
+
+        if !is_referenced_asset(asset_path) || keep_patterns.iter().any(|keep_pattern| return keep_pattern.is_match(pack_path) || keep_pattern.is_match(asset_path)) {
+            return true;
+        }
+
+        // vanilla sounds are not in the client jar, so a `minecraft:` sound always replaces one
+        return asset_path.starts_with("assets/minecraft/sounds/")
+            || asset_path.starts_with("assets/minecraft/") && client_jar.is_none_or(|client_jar| return client_jar.contains(asset_path));
+    };
+
+    let mut used_assets = collections::HashSet::<&str>::new();
+    let mut unvisited_assets = pack_paths.iter().filter(|pack_path| return is_kept(pack_path)).map(
+        |pack_path| return pack_mcmeta::strip_overlay_dir(pack_path)
+    ).collect::<Vec<&str>>();
+
+    // sound event configs and the sounds of the events they generate are not referenced by `sounds.json` yet
+    unvisited_assets.extend(
+        sounds::list_generator_paths(pack_dir, &pack_paths).iter().filter_map(|asset_path| return asset_paths.get(asset_path.as_str()))
+    );
+
+    while let Some(asset_path) = unvisited_assets.pop() {
+        if !used_assets.insert(asset_path) {
+            continue;
+        }
+
+        for reference in references_by_file.get(asset_path).into_iter().flatten() {
+            if reference.kind == "texture directory" {
+                let directory = reference.asset_path.trim_start_matches("assets/*/");
+
+                unvisited_assets.extend(
+                    asset_paths.iter().filter(
+                        |asset_path| return asset_path.splitn(3, '/').nth(2).is_some_and(|rest| return rest.starts_with(directory))
+                    )
+                );
+            } else if let Some(asset_path) = asset_paths.get(reference.asset_path.as_str()) {
+                unvisited_assets.push(asset_path);
+            }
+        }
+    }
+
+    return pack_paths.iter().filter(
+        |pack_path| {
+            let asset_path = pack_mcmeta::strip_overlay_dir(pack_path);
+
+            // the `.mcmeta` of a texture (e.g. its animation) is used with it
+            let texture_path = asset_path.strip_suffix(".mcmeta").unwrap_or(asset_path);
+
+            return !used_assets.contains(asset_path) && !used_assets.contains(texture_path);
+        }
+    ).cloned().collect();
+}
+
+//------------------------------------------------------------//
+
+/**
+ * The `--keep-unused` argument, shared by the `optimize` and `inspect` subcommands.
+ */
+pub fn get_keep_unused_arg() -> clap::Arg {
+    return clap::Arg::new("keep_unused")
+        .long("keep-unused")
+        .help("A pattern of pack paths (e.g. assets/demo/textures/gui/**) that are used by plugins or code, and count as used")
+        .value_name("PATTERN")
+        .value_parser(parse_keep_pattern)
+        .action(clap::ArgAction::Append);
+}
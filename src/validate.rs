@@ -0,0 +1,399 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use rayon::prelude::*; // parallel iterators
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::bitmap_font;
+use crate::client_jar;
+use crate::custom_model_data;
+use crate::font;
+use crate::hash;
+use crate::logging;
+use crate::logging::{error, info, warning};
+use crate::pack_mcmeta;
+use crate::references;
+use crate::sounds;
+use crate::source;
+use crate::target;
+
+//------------------------------------------------------------//
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        return match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+    }
+}
+
+/**
+ * A problem found in a pack file, `path` is the `/` separated path of the file in the pack.
+ */
+pub struct Issue {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+/**
+ * The issues found by the checks run on a pack.
+ */
+#[derive(Default)]
+pub struct Validation {
+    pub issues: Vec<Issue>,
+    // the files that cannot be parsed, which the optimization stages cannot process either
+    pub unreadable_paths: Vec<String>,
+}
+
+impl Validation {
+    pub fn error(
+        &mut self,
+        pack_path: &str,
+        message: String,
+    ) {
+        self.issues.push(Issue { severity: Severity::Error, path: pack_path.to_string(), message });
+    }
+
+    pub fn warning(
+        &mut self,
+        pack_path: &str,
+        message: String,
+    ) {
+        self.issues.push(Issue { severity: Severity::Warning, path: pack_path.to_string(), message });
+    }
+
+    pub fn count(
+        &self,
+        severity: Severity,
+    ) -> usize {
+        return self.issues.iter().filter(|issue| return issue.severity == severity).count();
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Checks that a file can be read the way Minecraft (and the optimization stages) will read it.
+ */
+fn check_file_syntax(
+    file: &path::Path,
+    pack_path: &str,
+) -> Option<Issue> {
+    let error_message = if crate::is_json_like_file(file) {
+        match fs::read_to_string(file) {
+            Ok(file_contents) => serde_json::from_str::<serde_json::Value>(&file_contents).err().map(
+                |error| return format!("Invalid json: {}", error)
+            ),
+            Err(error) => Some(format!("Failed to read file: {}", error)),
+        }
+    } else if crate::is_yaml_like_file(file) {
+        match fs::read_to_string(file) {
+            // parsed into json values like the yaml stage does, which has no room for tags or keys that are not strings
+            Ok(file_contents) => serde_yaml::from_str::<serde_json::Value>(&file_contents).err().map(
+                |error| return format!("Invalid yaml: {}", error)
+            ),
+            Err(error) => Some(format!("Failed to read file: {}", error)),
+        }
+    } else if crate::is_png_like_file(file) {
+        read_png_pixels(file).err()
+    } else {
+        None
+    };
+
+    return error_message.map(
+        |message| return Issue { severity: Severity::Error, path: pack_path.to_string(), message }
+    );
+}
+
+/**
+ * Reads a json file, or `None` if it cannot be read or parsed, which the syntax checks report on their own.
+ */
+pub fn read_json(
+    file: &path::Path,
+) -> Option<serde_json::Value> {
+    return fs::read_to_string(file).ok().and_then(
+        |file_contents| return serde_json::from_str::<serde_json::Value>(&file_contents).ok()
+    );
+}
+
+/**
+ * Like `read_json`, but reads the file by its pack path with `read_pack_file`, from a pack directory or from memory.
+ */
+pub fn read_pack_json(
+    read_pack_file: &dyn Fn(&str) -> Option<Vec<u8>>,
+    pack_path: &str,
+) -> Option<serde_json::Value> {
+    return read_pack_file(pack_path).and_then(
+        |file_contents| return serde_json::from_slice::<serde_json::Value>(&file_contents).ok()
+    );
+}
+
+/**
+ * Decodes a png file into its size and RGBA pixels with 16 bits per sample.
+ *
+ * Like with `read_json`, the syntax checks report the png files that cannot be decoded.
+ */
+pub fn read_png_pixels(
+    file: &path::Path,
+) -> Result<(u32, u32, Vec<u16>), String> {
+    let png_data = fs::read(file).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+    return decode_png_pixels(&png_data);
+}
+
+/**
+ * Decodes a png image to its width, height and RGBA pixels with 16 bits per sample.
+ *
+ * 16-bit images keep every bit so comparisons are exact, and 8-bit samples are widened (`0xAB` becomes `0xABAB`),
+ * so the same image compares as equal at either depth.
+ */
+pub fn decode_png_pixels(
+    png_data: &[u8],
+) -> Result<(u32, u32, Vec<u16>), String> {
+    let mut png_decoder = png::Decoder::new(png_data);
+
+    // expand palettes and low bit depths so every image can be compared as RGBA
+    png_decoder.set_transformations(png::Transformations::EXPAND);
+
+    let mut png_reader = png_decoder.read_info().map_err(|error| return format!("Invalid png: {}", error))?;
+
+    let mut pixels = vec![0; png_reader.output_buffer_size()];
+    let frame_info = png_reader.next_frame(&mut pixels).map_err(|error| return format!("Invalid png: {}", error))?;
+    pixels.truncate(frame_info.buffer_size());
+
+    let samples = match frame_info.bit_depth {
+        png::BitDepth::Sixteen => pixels.chunks(2).map(|sample| return u16::from_be_bytes([sample[0], sample[1]])).collect::<Vec<u16>>(),
+        _ => pixels.iter().map(|sample| return u16::from(*sample) * 257).collect::<Vec<u16>>(),
+    };
+
+    let rgba_pixels = match frame_info.color_type {
+        png::ColorType::Rgba => samples,
+        png::ColorType::Rgb => samples.chunks(3).flat_map(|pixel| return [pixel[0], pixel[1], pixel[2], u16::MAX]).collect(),
+        png::ColorType::GrayscaleAlpha => samples.chunks(2).flat_map(|pixel| return [pixel[0], pixel[0], pixel[0], pixel[1]]).collect(),
+        png::ColorType::Grayscale => samples.iter().flat_map(|pixel| return [*pixel, *pixel, *pixel, u16::MAX]).collect(),
+        png::ColorType::Indexed => return Err("Invalid png: palette was not expanded".to_string()),
+    };
+
+    return Ok((frame_info.width, frame_info.height, rgba_pixels));
+}
+
+/**
+ * Runs every check on the files of a pack directory, and the version specific checks if a target is given.
+ *
+ * References to vanilla assets are only checked if a client jar is given.
+ */
+pub fn validate_pack(
+    pack_dir: &path::Path,
+    target: Option<&target::Target>,
+    client_jar: Option<&client_jar::ClientJar>,
+) -> Validation {
+    let mut validation = Validation::default();
+
+    pack_mcmeta::check_pack_mcmeta(pack_dir, target, &mut validation);
+
+    let pack_paths = hash::list_pack_paths(pack_dir);
+
+    let syntax_issues = pack_paths.par_iter().filter_map(
+        |pack_path| return check_file_syntax(&pack_dir.join(pack_path), pack_path)
+    ).collect::<Vec<Issue>>();
+
+    validation.unreadable_paths = syntax_issues.iter().map(|issue| return issue.path.clone()).collect();
+    validation.issues.extend(syntax_issues);
+
+    references::check_references(pack_dir, &pack_paths, client_jar, &mut validation);
+
+    custom_model_data::check_custom_model_data(pack_dir, &pack_paths, &mut validation);
+
+    bitmap_font::check_glyph_fonts(pack_dir, &pack_paths, &mut validation);
+
+    font::check_fonts(pack_dir, &pack_paths, client_jar, &mut validation);
+
+    sounds::check_sounds(pack_dir, &pack_paths, &mut validation);
+
+    if let Some(target) = target {
+        let overlays = pack_mcmeta::read_overlays(pack_dir);
+
+        let is_in_overlay = |pack_path: &str, overlay: &pack_mcmeta::Overlay| {
+            return pack_path.starts_with(format!("{}/", overlay.directory).as_str());
+        };
+
+        let root_paths = pack_paths.iter().filter(
+            |pack_path| return !overlays.iter().any(|overlay| return is_in_overlay(pack_path, overlay))
+        ).cloned().collect::<Vec<String>>();
+
+        target::check_pack(target, pack_dir, pack_dir, &root_paths, &mut validation);
+
+        // the files of an overlay are only used by the versions of the target with one of its formats
+        for overlay in &overlays {
+            let overlay_target = overlay.formats.and_then(
+                |(min_format, max_format)| return target.narrow_to_formats(min_format, max_format)
+            );
+
+            let Some(overlay_target) = overlay_target else {
+                continue;
+            };
+
+            let overlay_paths = pack_paths.iter().filter(
+                |pack_path| return is_in_overlay(pack_path, overlay)
+            ).cloned().collect::<Vec<String>>();
+
+            target::check_pack(&overlay_target, pack_dir, &pack_dir.join(&overlay.directory), &overlay_paths, &mut validation);
+        }
+    }
+
+    validation.issues.sort_by(|a, b| return a.path.cmp(&b.path).then(b.severity.cmp(&a.severity)));
+
+    return validation;
+}
+
+/**
+ * Logs the issues of a validation as warnings and errors (or `issue` events in json mode).
+ */
+pub fn log_issues(
+    validation: &Validation,
+) {
+    for issue in &validation.issues {
+        let level = match issue.severity {
+            Severity::Warning => logging::Level::Warn,
+            Severity::Error => logging::Level::Error,
+        };
+
+        if logging::is_json() {
+            logging::event(
+                level,
+                "issue",
+                serde_json::json!({ "severity": issue.severity.name(), "path": issue.path, "message": issue.message })
+            );
+
+            continue;
+        }
+
+        match issue.severity {
+            Severity::Warning => warning!("{}: {}", issue.path, issue.message),
+            Severity::Error => error!("{}: {}", issue.path, issue.message),
+        }
+    }
+}
+
+//------------------------------------------------------------//
+
+/**
+ * The `--target` argument, shared with the `optimize` subcommand.
+ */
+pub fn get_target_arg() -> clap::Arg {
+    return clap::Arg::new("target")
+        .long("target")
+        .help(format!("The Minecraft version or range of versions (e.g. 1.20.1 or 1.20-1.20.4) the pack is made for, from {}", target::known_versions()))
+        .value_name("VERSION")
+        .value_parser(target::parse_target);
+}
+
+/**
+ * The `--client-jar` argument, shared with the `optimize` subcommand.
+ */
+pub fn get_client_jar_arg() -> clap::Arg {
+    return clap::Arg::new("client_jar")
+        .long("client-jar")
+        .help("A Minecraft client jar to check references to vanilla assets against")
+        .value_name("PATH")
+        .value_hint(clap::ValueHint::FilePath)
+        .value_parser(clap::value_parser!(path::PathBuf));
+}
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("validate")
+        .about("Checks a pack directory or zip file without writing anything")
+        .arg(
+            clap::Arg::new("path")
+                .help("The pack directory or zip file to check")
+                .value_name("PATH")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("json")
+                .long("json")
+                .help("Print the issues as json")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+            clap::Arg::new("strict")
+                .long("strict")
+                .help("Fail on warnings too")
+                .action(clap::ArgAction::SetTrue)
+        )
+        .arg(get_target_arg())
+        .arg(get_client_jar_arg());
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let pack_path = matched_args.get_one::<path::PathBuf>("path").expect("Failed to get path");
+
+    if !pack_path.exists() {
+        crate::fail_program("Path to validate does not exist");
+    }
+
+    let pack_source = source::open_pack(pack_path);
+
+    let target = matched_args.get_one::<target::Target>("target");
+
+    if let Some(target) = target {
+        info!("Checking the pack for {} (pack_format {})", target.name(), target.formats());
+    }
+
+    let client_jar = matched_args.get_one::<path::PathBuf>("client_jar").map(
+        |jar_path| return client_jar::open_client_jar(jar_path)
+    );
+
+    let validation = validate_pack(pack_source.dir(), target, client_jar.as_ref());
+
+    let error_count = validation.count(Severity::Error);
+    let warning_count = validation.count(Severity::Warning);
+
+    if matched_args.get_flag("json") {
+        let issues = validation.issues.iter().map(
+            |issue| return serde_json::json!({ "severity": issue.severity.name(), "path": issue.path, "message": issue.message })
+        ).collect::<Vec<serde_json::Value>>();
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(
+                &serde_json::json!({ "errors": error_count, "warnings": warning_count, "issues": issues })
+            ).expect("Failed to convert issues to string")
+        );
+    } else {
+        for issue in &validation.issues {
+            println!("{}: {}: {}", issue.severity.name(), issue.path, issue.message);
+        }
+
+        info!("Found {} errors and {} warnings", error_count, warning_count);
+    }
+
+    if error_count > 0 || (matched_args.get_flag("strict") && warning_count > 0) {
+        crate::fail_program("The pack did not pass validation");
+    }
+}
@@ -0,0 +1,86 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use rayon::prelude::*; // parallel iterators
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::client_jar;
+use crate::hash;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+/**
+ * Whether a pack file means the same as the vanilla file it replaces.
+ *
+ * json files are compared by their values and png files by their pixels, so a file that was only reformatted
+ * or recompressed still counts as a copy.
+ */
+fn is_same_as_vanilla(
+    file: &path::Path,
+    contents: &[u8],
+    vanilla_contents: &[u8],
+) -> bool {
+    if contents == vanilla_contents {
+        return true;
+    }
+
+    if crate::is_json_like_file(file) {
+        let parse_json = |contents: &[u8]| return serde_json::from_slice::<serde_json::Value>(contents).ok();
+
+        return parse_json(contents).is_some_and(|json| return Some(json) == parse_json(vanilla_contents));
+    }
+
+    if crate::is_png_like_file(file) {
+        let pixels = validate::decode_png_pixels(contents).ok();
+
+        return pixels.is_some() && pixels == validate::decode_png_pixels(vanilla_contents).ok();
+    }
+
+    return false;
+}
+
+/**
+ * Finds the pack files that are the same as the vanilla file at the same path of a client jar, so they override nothing.
+ *
+ * Only the files in the pack root are checked, leaving out a file of an overlay would let the root file show through.
+ */
+pub fn find_vanilla_copies(
+    pack_dir: &path::Path,
+    client_jar: &client_jar::ClientJar,
+) -> Vec<String> {
+    // vanilla assets are all in the `minecraft` namespace
+    let vanilla_dir = pack_dir.join("assets").join("minecraft");
+
+    if !vanilla_dir.is_dir() {
+        return Vec::new();
+    }
+
+    let pack_paths = hash::list_pack_paths(pack_dir).into_iter().filter(
+        |pack_path| return pack_path.starts_with("assets/minecraft/") && client_jar.contains(pack_path)
+    ).collect::<Vec<String>>();
+
+    let vanilla_files = client_jar.read_files(&pack_paths.iter().map(|pack_path| return pack_path.as_str()).collect::<Vec<&str>>());
+
+    return pack_paths.into_par_iter().filter(
+        |pack_path| {
+            let file = pack_dir.join(pack_path);
+            let contents = fs::read(&file).expect("Failed to read file");
+
+            return is_same_as_vanilla(&file, &contents, &vanilla_files[pack_path]);
+        }
+    ).collect();
+}
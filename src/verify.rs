@@ -0,0 +1,465 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::collections;
+use std::fs;
+use std::path;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use rayon::prelude::*; // parallel iterators
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::TraverseDirLookFor;
+use crate::traverse_dir;
+use crate::bitmap_font;
+use crate::hash;
+use crate::logging::info;
+use crate::ogg;
+use crate::sounds;
+use crate::source;
+use crate::validate;
+
+//------------------------------------------------------------//
+
+// operators that have to be read as one token, longest first
+const OPEN_GL_SL_OPERATORS: [&str; 21] = [
+    "<<=", ">>=", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    "==", "!=", "<=", ">=", "&&", "||", "^^", "<<", ">>",
+];
+
+//------------------------------------------------------------//
+
+/**
+ * Splits a shader into its tokens, each with the line it starts on.
+ *
+ * Comments and whitespace are dropped, except for the end of preprocessor directives (kept as a `\n` token)
+ * since a directive ends with its line.
+ */
+fn tokenize_open_gl_sl(
+    file_contents: &str,
+) -> Result<Vec<(String, usize)>, String> {
+    let characters = file_contents.chars().collect::<Vec<char>>();
+
+    let mut tokens: Vec<(String, usize)> = Vec::new();
+    let mut index = 0;
+    let mut line = 1;
+    let mut is_line_start = true;
+    let mut is_in_directive = false;
+
+    while index < characters.len() {
+        let character = characters[index];
+        let next_character = characters.get(index + 1).copied();
+
+        if character == '/' && next_character == Some('/') {
+            while index < characters.len() && characters[index] != '\n' {
+                index += 1;
+            }
+        } else if character == '/' && next_character == Some('*') {
+            let comment_line = line;
+
+            index += 2;
+
+            loop {
+                if index >= characters.len() {
+                    return Err(format!("Unterminated block comment starting on line {}", comment_line));
+                }
+
+                if characters[index] == '*' && characters.get(index + 1) == Some(&'/') {
+                    index += 2;
+
+                    break;
+                }
+
+                if characters[index] == '\n' {
+                    line += 1;
+                }
+
+                index += 1;
+            }
+        } else if character == '\\' && (next_character == Some('\n') || next_character == Some('\r')) {
+            // a line continuation joins two lines into one
+            index += 1;
+        } else if character == '\n' {
+            if is_in_directive {
+                tokens.push(("\n".to_string(), line));
+                is_in_directive = false;
+            }
+
+            is_line_start = true;
+            line += 1;
+            index += 1;
+        } else if character.is_whitespace() {
+            index += 1;
+        } else {
+            let token = if character.is_alphanumeric() || character == '_' || character == '.' {
+                let token_end = characters[index..].iter().position(
+                    |character| return !(character.is_alphanumeric() || *character == '_' || *character == '.')
+                ).map_or(characters.len(), |position| return index + position);
+
+                characters[index..token_end].iter().collect::<String>()
+            } else {
+                let rest = characters[index..characters.len().min(index + 3)].iter().collect::<String>();
+
+                match OPEN_GL_SL_OPERATORS.iter().find(|operator| return rest.starts_with(*operator)) {
+                    Some(operator) => operator.to_string(),
+                    None => character.to_string(),
+                }
+            };
+
+            if is_line_start && token == "#" {
+                is_in_directive = true;
+            }
+
+            index += token.chars().count();
+            is_line_start = false;
+
+            tokens.push((token, line));
+        }
+    }
+
+    if is_in_directive {
+        tokens.push(("\n".to_string(), line));
+    }
+
+    return Ok(tokens);
+}
+
+fn verify_json_file(
+    source_file: &path::Path,
+    output_file: &path::Path,
+) -> Result<(), String> {
+    let read_json = |file: &path::Path| {
+        let file_contents = fs::read_to_string(file).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+        return serde_json::from_str::<serde_json::Value>(&file_contents).map_err(|error| return format!("Invalid json: {}", error));
+    };
+
+    let (source_json, output_json) = (read_json(source_file)?, read_json(output_file)?);
+
+    if source_json != output_json {
+        return Err("The json values are not the same".to_string());
+    }
+
+    return Ok(());
+}
+
+/**
+ * Checks a lang file that had keys left out, which has to hold the values of the source file without them.
+ */
+fn verify_lang_file(
+    source_file: &path::Path,
+    output_file: &path::Path,
+    pruned_keys: &[String],
+) -> Result<(), String> {
+    let read_lang = |file: &path::Path| {
+        let file_contents = fs::read_to_string(file).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+        return serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&file_contents).map_err(|error| return format!("Invalid lang file: {}", error));
+    };
+
+    let (mut source_lang, output_lang) = (read_lang(source_file)?, read_lang(output_file)?);
+
+    for pruned_key in pruned_keys {
+        source_lang.remove(pruned_key);
+    }
+
+    if source_lang != output_lang {
+        return Err("The lang values other than the pruned ones are not the same".to_string());
+    }
+
+    return Ok(());
+}
+
+fn verify_yaml_file(
+    source_file: &path::Path,
+    output_file: &path::Path,
+) -> Result<(), String> {
+    // the output is json, which is read as yaml too, so tags and non-string keys lost on the way are caught
+    let read_yaml = |file: &path::Path| {
+        let file_contents = fs::read_to_string(file).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+        return serde_yaml::from_str::<serde_yaml::Value>(&file_contents).map_err(|error| return format!("Invalid yaml: {}", error));
+    };
+
+    let (source_yaml, output_yaml) = (read_yaml(source_file)?, read_yaml(output_file)?);
+
+    if source_yaml != output_yaml {
+        return Err("The yaml values are not the same".to_string());
+    }
+
+    return Ok(());
+}
+
+fn verify_open_gl_sl_file(
+    source_file: &path::Path,
+    output_file: &path::Path,
+) -> Result<(), String> {
+    let read_tokens = |file: &path::Path| {
+        let file_contents = fs::read_to_string(file).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+        return tokenize_open_gl_sl(&file_contents);
+    };
+
+    let source_tokens = read_tokens(source_file)?;
+    let output_tokens = read_tokens(output_file).map_err(|error| return format!("Output shader: {}", error))?;
+
+    let describe_token = |token: Option<&(String, usize)>| {
+        return match token {
+            Some((token, _)) if token == "\n" => "the end of a directive".to_string(),
+            Some((token, _)) => format!("`{}`", token),
+            None => "the end of the file".to_string(),
+        };
+    };
+
+    for index in 0..source_tokens.len().max(output_tokens.len()) {
+        let (source_token, output_token) = (source_tokens.get(index), output_tokens.get(index));
+
+        if source_token.map(|(token, _)| return token) != output_token.map(|(token, _)| return token) {
+            return Err(
+                format!(
+                    "The shader tokens are not the same from line {} of the source: {} became {}",
+                    source_token.or(source_tokens.last()).map_or(1, |(_, line)| return *line),
+                    describe_token(source_token),
+                    describe_token(output_token)
+                )
+            );
+        }
+    }
+
+    return Ok(());
+}
+
+fn verify_ogg_file(
+    source_file: &path::Path,
+    output_file: &path::Path,
+) -> Result<(), String> {
+    let source_contents = fs::read(source_file).map_err(|error| return format!("Failed to read file: {}", error))?;
+    let output_contents = fs::read(output_file).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+    // a file that is not a single vorbis stream is copied as it is
+    let Ok(source_stream) = ogg::read_vorbis_stream(&source_contents) else {
+        if source_contents != output_contents {
+            return Err("The file contents are not the same".to_string());
+        }
+
+        return Ok(());
+    };
+
+    let output_stream = ogg::read_vorbis_stream(&output_contents).map_err(|error| return format!("Output ogg: {}", error))?;
+
+    if !ogg::is_same_audio(&source_stream, &output_stream) {
+        return Err("The vorbis headers or audio packets are not the same".to_string());
+    }
+
+    return Ok(());
+}
+
+fn verify_png_file(
+    source_file: &path::Path,
+    output_file: &path::Path,
+) -> Result<(), String> {
+    let (source_width, source_height, source_pixels) = validate::read_png_pixels(source_file)?;
+    let (output_width, output_height, output_pixels) = validate::read_png_pixels(output_file)?;
+
+    if (source_width, source_height) != (output_width, output_height) {
+        return Err(
+            format!(
+                "The image was resized from {}x{} to {}x{}",
+                source_width,
+                source_height,
+                output_width,
+                output_height
+            )
+        );
+    }
+
+    let changed_pixels = source_pixels.chunks(4).zip(output_pixels.chunks(4)).filter(
+        |(source_pixel, output_pixel)| return source_pixel != output_pixel
+    ).count();
+
+    if changed_pixels > 0 {
+        return Err(format!("{} of {} pixels are not the same", changed_pixels, source_width as u64 * source_height as u64));
+    }
+
+    return Ok(());
+}
+
+/**
+ * Checks that an optimized file means the same as the file it was made from, by the rules of its stage.
+ *
+ * Every stage is lossless, so files without a stage have to be byte for byte the same.
+ */
+fn verify_file(
+    source_file: &path::Path,
+    output_file: &path::Path,
+) -> Result<(), String> {
+    if crate::is_json_like_file(source_file) {
+        return verify_json_file(source_file, output_file);
+    }
+
+    if crate::is_yaml_like_file(source_file) {
+        return verify_yaml_file(source_file, output_file);
+    }
+
+    if crate::is_open_gl_sl_like_file(source_file) {
+        return verify_open_gl_sl_file(source_file, output_file);
+    }
+
+    if crate::is_png_like_file(source_file) {
+        return verify_png_file(source_file, output_file);
+    }
+
+    if crate::is_ogg_like_file(source_file) {
+        return verify_ogg_file(source_file, output_file);
+    }
+
+    let source_contents = fs::read(source_file).map_err(|error| return format!("Failed to read file: {}", error))?;
+    let output_contents = fs::read(output_file).map_err(|error| return format!("Failed to read file: {}", error))?;
+
+    if source_contents != output_contents {
+        return Err("The file contents are not the same".to_string());
+    }
+
+    return Ok(());
+}
+
+/**
+ * Compares every file of a pack directory to its optimized version.
+ *
+ * Files left out of the pack (see `is_pack_file`) and `pruned_paths` are not expected in the output,
+ * checksum files are ignored, and so are the glyph directories of generated fonts, the sound event configs
+ * and the files generated from them. Lang files are expected without their `pruned_lang_keys`.
+ */
+pub fn verify_pack(
+    source_dir: &path::Path,
+    output_dir: &path::Path,
+    pruned_paths: &collections::HashSet<String>,
+    pruned_lang_keys: &collections::HashMap<String, Vec<String>>,
+) -> (usize, validate::Validation) {
+    let list_pack_paths = |dir: &path::Path, filter: &dyn Fn(&path::Path) -> bool| {
+        return traverse_dir(dir, &TraverseDirLookFor::Files, Some(filter)).iter().map(
+            |file| return hash::to_pack_path(file.strip_prefix(dir).expect("Failed to strip prefix"))
+        ).collect::<collections::BTreeSet<String>>();
+    };
+
+    let (glyph_paths, mut generated_paths) = bitmap_font::list_generated_paths(source_dir);
+    let (sound_config_paths, generated_sounds_paths) = sounds::list_generated_paths(source_dir);
+    generated_paths.extend(generated_sounds_paths);
+
+    let mut source_paths = list_pack_paths(source_dir, &|file| return crate::is_pack_file(file));
+    source_paths.retain(|pack_path| return !pruned_paths.contains(pack_path) && !glyph_paths.contains(pack_path) && !sound_config_paths.contains(pack_path) && !generated_paths.contains(pack_path));
+    let mut output_paths = list_pack_paths(output_dir, &|file| return !hash::is_metadata_file(file));
+    output_paths.retain(|pack_path| return !generated_paths.contains(pack_path));
+
+    let mut validation = validate::Validation::default();
+
+    for pack_path in source_paths.difference(&output_paths) {
+        validation.error(pack_path, "Missing from the output".to_string());
+    }
+
+    for pack_path in output_paths.difference(&source_paths) {
+        validation.error(pack_path, "Not in the source pack".to_string());
+    }
+
+    let common_paths = source_paths.intersection(&output_paths).collect::<Vec<&String>>();
+
+    validation.issues.extend(
+        common_paths.par_iter().filter_map(
+            |pack_path| {
+                let verification = match pruned_lang_keys.get(*pack_path) {
+                    Some(pruned_keys) => verify_lang_file(&source_dir.join(pack_path), &output_dir.join(pack_path), pruned_keys),
+                    None => verify_file(&source_dir.join(pack_path), &output_dir.join(pack_path)),
+                };
+
+                return verification.err().map(
+                    |message| return validate::Issue { severity: validate::Severity::Error, path: pack_path.to_string(), message }
+                );
+            }
+        ).collect::<Vec<validate::Issue>>()
+    );
+
+    validation.issues.sort_by(|a, b| return a.path.cmp(&b.path));
+
+    return (common_paths.len(), validation);
+}
+
+//------------------------------------------------------------//
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("verify")
+        .about("Checks that an optimized pack means the same as the pack it was made from")
+        .arg(
+            clap::Arg::new("source")
+                .help("The source pack directory or zip file")
+                .value_name("SOURCE")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("output")
+                .help("The optimized pack directory or zip file")
+                .value_name("OUTPUT")
+                .value_hint(clap::ValueHint::AnyPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("json")
+                .long("json")
+                .help("Print the differences as json")
+                .action(clap::ArgAction::SetTrue)
+        );
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let source_path = matched_args.get_one::<path::PathBuf>("source").expect("Failed to get source");
+    let output_path = matched_args.get_one::<path::PathBuf>("output").expect("Failed to get output");
+
+    if !source_path.exists() {
+        crate::fail_program("Source pack does not exist");
+    }
+
+    if !output_path.exists() {
+        crate::fail_program("Optimized pack does not exist");
+    }
+
+    let source_pack = source::open_pack(source_path);
+    let output_pack = source::open_pack(output_path);
+
+    let (verified_files, validation) = verify_pack(source_pack.dir(), output_pack.dir(), &collections::HashSet::new(), &collections::HashMap::new());
+
+    if matched_args.get_flag("json") {
+        let differences = validation.issues.iter().map(
+            |issue| return serde_json::json!({ "path": issue.path, "message": issue.message })
+        ).collect::<Vec<serde_json::Value>>();
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(
+                &serde_json::json!({ "files": verified_files, "differences": differences })
+            ).expect("Failed to convert differences to string")
+        );
+    } else {
+        for issue in &validation.issues {
+            println!("{}: {}", issue.path, issue.message);
+        }
+
+        info!("Compared {} files and found {} differences", verified_files, validation.issues.len());
+    }
+
+    if !validation.issues.is_empty() {
+        crate::fail_program("The optimized pack does not match the source pack");
+    }
+}
@@ -0,0 +1,505 @@
+//------------------------------------------------------------//
+//                 Copyright (C) MineSuperior                 //
+//------------------------------------------------------------//
+
+// import standard library modules
+
+use std::io::Write;
+use std::fs;
+use std::path;
+use std::collections;
+use std::panic;
+use std::sync::mpsc;
+
+//------------------------------------------------------------//
+
+// import third-party modules
+
+use rayon::prelude::*; // parallel iterators
+
+use tempdir::TempDir; // temporary directories
+
+use notify::Watcher; // file system notifications
+
+//------------------------------------------------------------//
+
+// import local modules
+
+use crate::TraverseDirLookFor;
+use crate::traverse_dir;
+use crate::bitmap_font;
+use crate::hash;
+use crate::output;
+use crate::sounds;
+use crate::source;
+use crate::logging::{info, warning};
+
+//------------------------------------------------------------//
+
+struct ChangedFile {
+    relative_path: path::PathBuf,
+    pack_path: String,
+}
+
+//------------------------------------------------------------//
+
+/**
+ * Sorts the changed paths into files that need to be (re)processed and paths that were removed.
+ */
+fn collect_changes(
+    input_dir: &path::Path,
+    changed_paths: &collections::BTreeSet<path::PathBuf>,
+) -> (Vec<ChangedFile>, Vec<String>) {
+    let mut updated_files: Vec<ChangedFile> = Vec::new();
+    let mut removed_pack_paths: Vec<String> = Vec::new();
+
+    for changed_path in changed_paths {
+        let relative_path = match changed_path.strip_prefix(input_dir) {
+            Ok(relative_path) => relative_path,
+            Err(_) => continue,
+        };
+
+        if relative_path.as_os_str().is_empty() {
+            continue;
+        }
+
+        if changed_path.is_dir() {
+            // a directory that was created or moved in brings all of its files with it
+            let traversed_items = traverse_dir(
+                changed_path,
+                &TraverseDirLookFor::Files,
+                Some(&|file| return !file.is_file() || crate::is_pack_file(file)),
+            );
+
+            for file in traversed_items {
+                let relative_path = file.strip_prefix(input_dir).expect("Failed to strip prefix").to_path_buf();
+
+                updated_files.push(
+                    ChangedFile {
+                        pack_path: hash::to_pack_path(&relative_path),
+                        relative_path,
+                    }
+                );
+            }
+        } else if changed_path.is_file() {
+            if !crate::is_pack_file(changed_path) {
+                continue;
+            }
+
+            updated_files.push(
+                ChangedFile {
+                    relative_path: relative_path.to_path_buf(),
+                    pack_path: hash::to_pack_path(relative_path),
+                }
+            );
+        } else {
+            removed_pack_paths.push(hash::to_pack_path(relative_path));
+        }
+    }
+
+    // a file inside of a new directory shows up both on its own and through the directory
+    updated_files.sort_by(|a, b| return a.pack_path.cmp(&b.pack_path));
+    updated_files.dedup_by(|a, b| return a.pack_path == b.pack_path);
+
+    return (updated_files, removed_pack_paths);
+}
+
+/**
+ * Runs the changed files through their stages into `output_dir`.
+ */
+fn process_changed_files(
+    input_dir: &path::Path,
+    output_dir: &path::Path,
+    updated_files: &[ChangedFile],
+) {
+    updated_files.par_iter().filter(
+        |updated_file| return !crate::is_png_like_file(&updated_file.relative_path)
+    ).for_each(
+        |updated_file| {
+            crate::process_file(
+                &input_dir.join(&updated_file.relative_path),
+                &output_dir.join(&updated_file.relative_path),
+            );
+        }
+    );
+
+    // this cannot be `par_iter` because `oxipng` can spawn too many threads and lock up the master process
+    updated_files.iter().filter(
+        |updated_file| return crate::is_png_like_file(&updated_file.relative_path)
+    ).for_each(
+        |updated_file| {
+            crate::process_file(
+                &input_dir.join(&updated_file.relative_path),
+                &output_dir.join(&updated_file.relative_path),
+            );
+        }
+    );
+}
+
+/**
+ * Rewrites a zip file with the updated files, copying all unchanged entries without recompressing them.
+ */
+fn update_zip(
+    zip_file_path: &path::Path,
+    processed_dir: &path::Path,
+    updated_files: &[ChangedFile],
+    removed_pack_paths: &[String],
+) -> hash::Hashes {
+    let updated_zip_file_path = hash::append_to_file_name(zip_file_path, ".tmp");
+
+    {
+        let zip_file = fs::File::open(zip_file_path).expect("Failed to open zip file");
+        let mut zip_archive = zip::ZipArchive::new(zip_file).expect("Failed to read zip file");
+
+        let updated_zip_file = fs::File::create(&updated_zip_file_path).expect("Failed to create zip file");
+        let mut zip_writer = zip::ZipWriter::new(updated_zip_file);
+
+        for index in 0..zip_archive.len() {
+            let zip_entry = zip_archive.by_index_raw(index).expect("Failed to read zip entry");
+            let entry_name = zip_entry.name().to_string();
+
+            let is_updated = updated_files.iter().any(|updated_file| return updated_file.pack_path == entry_name);
+            let is_removed = removed_pack_paths.iter().any(
+                |removed_pack_path| return entry_name == *removed_pack_path || entry_name.starts_with(&format!("{}/", removed_pack_path))
+            );
+
+            if is_updated || is_removed {
+                continue;
+            }
+
+            zip_writer.raw_copy_file(zip_entry).expect("Failed to copy zip entry");
+        }
+
+        for updated_file in updated_files {
+            zip_writer.start_file(
+                updated_file.pack_path.as_str(),
+                zip::write::FileOptions::default()
+            ).expect("Failed to start file");
+
+            let file_contents = fs::read(processed_dir.join(&updated_file.relative_path)).expect("Failed to read file");
+            zip_writer.write_all(&file_contents).expect("Failed to write file");
+        }
+
+        zip_writer.finish().expect("Failed to finish zip");
+    }
+
+    // replace the zip file in one step so it is never seen half-written
+    fs::rename(&updated_zip_file_path, zip_file_path).expect("Failed to replace zip file");
+
+    return hash::hash_file(zip_file_path);
+}
+
+/**
+ * Updates the output directory or zip file with only the changed paths.
+ */
+fn rebuild_changes(
+    input_dir: &path::Path,
+    output_dir: &path::Path,
+    zip_file_path: Option<&path::Path>,
+    changed_paths: &collections::BTreeSet<path::PathBuf>,
+) {
+    let operation_start = std::time::Instant::now();
+
+    let (updated_files, removed_pack_paths) = collect_changes(input_dir, changed_paths);
+
+    if updated_files.is_empty() && removed_pack_paths.is_empty() {
+        return;
+    }
+
+    match zip_file_path {
+        Some(zip_file_path) => {
+            let temp_dir = TempDir::new("ms-rpo").expect("Failed to create temporary directory");
+
+            process_changed_files(input_dir, temp_dir.path(), &updated_files);
+
+            let zip_hashes = update_zip(zip_file_path, temp_dir.path(), &updated_files, &removed_pack_paths);
+
+            temp_dir.close().expect("Failed to remove temporary directory");
+
+            info!("Zip file SHA-1 hash: {}", zip_hashes.sha1);
+
+            hash::write_zip_checksums(input_dir, zip_file_path, &zip_hashes);
+        },
+        None => {
+            process_changed_files(input_dir, output_dir, &updated_files);
+
+            output::add_owned_files(
+                output_dir,
+                &updated_files.iter().map(|updated_file| return updated_file.pack_path.clone()).collect::<Vec<String>>()
+            );
+
+            // files in the output directory that ms-rpo did not create are left alone
+            output::remove_owned_files(output_dir, &removed_pack_paths);
+
+            hash::write_dir_checksums(input_dir, output_dir);
+        },
+    }
+
+    let operation_end = operation_start.elapsed();
+
+    info!("Rebuilt {} changed files and removed {} paths in {:.2?}", updated_files.len(), removed_pack_paths.len(), operation_end);
+}
+
+/**
+ * Builds the whole pack and swaps it into place of the output.
+ */
+fn build_pack(
+    input_dir: &path::Path,
+    output_dir: &path::Path,
+    zip_name: Option<&path::PathBuf>,
+) {
+    let temp_dir = TempDir::new("ms-rpo").expect("Failed to create temporary directory");
+
+    // nothing is pruned, so a full build has the same files as rebuilding each of them on its own
+    crate::process_pack(input_dir, temp_dir.path(), &collections::HashSet::new(), &collections::HashMap::new(), None);
+
+    let staging_dir = output::create_staging_dir(output_dir);
+
+    match zip_name {
+        Some(zip_name) => {
+            let staged_zip_file_path = staging_dir.join(zip_name);
+
+            // if it does not exist, create it
+            fs::create_dir_all(
+                staged_zip_file_path.parent().expect("Failed to get parent directory")
+            ).expect("Failed to create parent directory");
+
+            let zip_hashes = crate::zip_dir(temp_dir.path(), &staged_zip_file_path);
+
+            hash::write_zip_checksums(input_dir, &staged_zip_file_path, &zip_hashes);
+        },
+        None => {
+            crate::clone_dir(temp_dir.path(), &staging_dir, None);
+
+            hash::write_dir_checksums(input_dir, &staging_dir);
+        },
+    }
+
+    temp_dir.close().expect("Failed to remove temporary directory");
+
+    output::commit_staging_dir(output_dir, &staging_dir, false);
+}
+
+/**
+ * Whether the changes touch what a generator reads or writes, so the pack has to be built again as a whole.
+ *
+ * A generated file is built from several files of the pack, which cannot be reprocessed one by one.
+ */
+fn needs_full_rebuild(
+    input_dir: &path::Path,
+    changed_paths: &collections::BTreeSet<path::PathBuf>,
+) -> bool {
+    let changed_pack_paths = changed_paths.iter().filter_map(
+        |changed_path| return changed_path.strip_prefix(input_dir).ok().map(hash::to_pack_path)
+    ).collect::<Vec<String>>();
+
+    // removed glyphs and configs are no longer listed, so their paths are matched as well
+    if changed_pack_paths.iter().any(|pack_path| return bitmap_font::is_glyph_path(pack_path) || sounds::is_sound_events_config(pack_path)) {
+        return true;
+    }
+
+    let (glyph_paths, generated_font_paths) = bitmap_font::list_generated_paths(input_dir);
+    let (sound_config_paths, generated_sounds_paths) = sounds::list_generated_paths(input_dir);
+
+    // the sounds a config generates events for are in its directory, including ones that were removed
+    let is_sound_config_dir_changed = sound_config_paths.iter().any(
+        |config_path| {
+            let config_dir = config_path.rsplit_once('/').expect("Failed to get config directory").0;
+
+            return changed_pack_paths.iter().any(|pack_path| return pack_path.starts_with(&format!("{}/", config_dir)));
+        }
+    );
+
+    if is_sound_config_dir_changed {
+        return true;
+    }
+
+    // a changed directory can hold generator paths as well
+    return glyph_paths.iter().chain(generated_font_paths.iter()).chain(generated_sounds_paths.iter()).any(
+        |generator_path| {
+            return changed_pack_paths.iter().any(
+                |pack_path| return generator_path == pack_path || generator_path.starts_with(&format!("{}/", pack_path))
+            );
+        }
+    );
+}
+
+/**
+ * Adds the paths of a file system event to the pending changes.
+ */
+fn collect_event(
+    event: notify::Result<notify::Event>,
+    changed_paths: &mut collections::BTreeSet<path::PathBuf>,
+) {
+    match event {
+        Ok(event) => {
+            // reading the input files during a rebuild must not trigger another rebuild
+            if let notify::EventKind::Access(_) = event.kind {
+                return;
+            }
+
+            changed_paths.extend(event.paths);
+        },
+        Err(error) => {
+            warning!("Failed to watch for changes: {}", error);
+        },
+    }
+}
+
+//------------------------------------------------------------//
+
+pub fn get_command() -> clap::Command {
+    return clap::Command::new("watch")
+        .about("Builds the pack, then rebuilds only the changed files whenever the input directory changes")
+        .arg(
+            clap::Arg::new("input_path")
+                .short('i')
+                .long("input-path")
+                .help("The directory to read from")
+                .value_name("INPUT_PATH")
+                .value_hint(clap::ValueHint::DirPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("output_path")
+                .short('o')
+                .long("output-path")
+                .help("The directory to output to")
+                .value_name("OUTPUT_PATH")
+                .value_hint(clap::ValueHint::DirPath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .required(true)
+        )
+        .arg(
+            clap::Arg::new("zip")
+                .short('z')
+                .long("zip")
+                .help("Compresses the output files into a .zip file with an optionally specified name")
+                .value_name("ZIP_NAME")
+                .value_hint(clap::ValueHint::FilePath)
+                .value_parser(clap::value_parser!(path::PathBuf))
+                .default_missing_value("output.zip")
+                .num_args(0..=1)
+                .required(false)
+        )
+        .arg(
+            clap::Arg::new("debounce")
+                .long("debounce")
+                .help("How long to wait for further changes before rebuilding, in milliseconds")
+                .value_name("MILLISECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("250")
+        );
+}
+
+pub fn run_command(
+    matched_args: &clap::ArgMatches,
+) {
+    let input_dir = matched_args.get_one::<path::PathBuf>("input_path").expect("Failed to get input_path");
+    let output_dir = matched_args.get_one::<path::PathBuf>("output_path").expect("Failed to get output_path");
+    let zip_name = matched_args.get_one::<path::PathBuf>("zip");
+    let debounce = std::time::Duration::from_millis(*matched_args.get_one::<u64>("debounce").expect("Failed to get debounce"));
+    let assume_yes = matched_args.get_flag("yes");
+
+    // a zip file cannot be watched for changes like the files of a directory
+    if source::is_zip_file(input_dir) {
+        crate::fail_program("watch needs a pack directory");
+    }
+
+    crate::validate_input_and_output_dirs(input_dir, output_dir);
+
+    // file system events carry absolute paths
+    let input_dir = fs::canonicalize(input_dir).expect("Failed to resolve input directory");
+    let output_dir = fs::canonicalize(output_dir).expect("Failed to resolve output directory");
+
+    let zip_file_path = zip_name.map(|zip_name| return output_dir.join(zip_name));
+
+    {
+        let mut plan_steps = vec![
+            format!(
+                "Optimize the pack files in {}, then again whenever they change",
+                input_dir.to_str().expect("Failed to convert input_dir to string")
+            ),
+            match &zip_file_path {
+                Some(zip_file_path) => format!(
+                    "Write {} with its checksums and manifest",
+                    zip_file_path.to_str().expect("Failed to convert zip_file_path to string")
+                ),
+                None => format!(
+                    "Write the optimized files with their checksums and manifest to {}",
+                    output_dir.to_str().expect("Failed to convert output_dir to string")
+                ),
+            },
+        ];
+
+        plan_steps.extend(output::describe_replacement(&output_dir, false));
+
+        crate::confirm_plan(&plan_steps, assume_yes);
+    }
+
+    // start watching before the initial build so no change is missed
+    let (event_sender, event_receiver) = mpsc::channel::<notify::Result<notify::Event>>();
+
+    let mut watcher = notify::recommended_watcher(event_sender).expect("Failed to create file watcher");
+    watcher.watch(&input_dir, notify::RecursiveMode::Recursive).expect("Failed to watch input directory");
+
+    // a broken file should not stop the watcher, the panic message is printed by the default hook
+    let build_result = panic::catch_unwind(panic::AssertUnwindSafe(|| return build_pack(&input_dir, &output_dir, zip_name)));
+
+    // until a full build succeeds, there is no output to update file by file
+    let mut needs_full_build = build_result.is_err();
+
+    if needs_full_build {
+        warning!("Failed to build the pack, waiting for a fix...");
+    }
+
+    info!("");
+    info!("Watching {} for changes. Press Ctrl+C to stop.", input_dir.to_str().expect("Failed to convert input_dir to string"));
+
+    while let Ok(event) = event_receiver.recv() {
+        let mut changed_paths: collections::BTreeSet<path::PathBuf> = collections::BTreeSet::new();
+
+        collect_event(event, &mut changed_paths);
+
+        // wait until the changes settle down (editors often write a file several times)
+        loop {
+            match event_receiver.recv_timeout(debounce) {
+                Ok(event) => {
+                    collect_event(event, &mut changed_paths);
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    break;
+                },
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return;
+                },
+            }
+        }
+
+        if changed_paths.is_empty() {
+            continue;
+        }
+
+        info!("");
+
+        let rebuild_result = panic::catch_unwind(
+            panic::AssertUnwindSafe(
+                || {
+                    if needs_full_build || needs_full_rebuild(&input_dir, &changed_paths) {
+                        return build_pack(&input_dir, &output_dir, zip_name);
+                    }
+
+                    return rebuild_changes(&input_dir, &output_dir, zip_file_path.as_deref(), &changed_paths);
+                }
+            )
+        );
+
+        // a rebuild that failed part way can leave the output with only some of the changes
+        needs_full_build = rebuild_result.is_err();
+
+        if needs_full_build {
+            warning!("Failed to rebuild changes, waiting for further changes...");
+        }
+    }
+}